@@ -14,6 +14,7 @@ import {
   PREVIEW_TAG_NAME,
   ConditionalBlockKind,
   StatementKind,
+  JsString,
   getAttributeStringValue,
   getVisibleChildNodes,
   Slot,
@@ -22,6 +23,7 @@ import {
   AttributeValue,
   AttributeKind,
   AttributeValueKind,
+  DynamicStringAttributeValuePartKind,
   isVisibleNode,
   getImportIds,
   Element,
@@ -174,7 +176,8 @@ const translateImports = (ast: Node, context: TranslateContext) => {
     const id = getAttributeStringValue("id", imp);
     const src = getAttributeStringValue("src", imp);
 
-    if (!src) {
+    // fixtures are only used by previews, which aren't compiled
+    if (!src || /\.json$/.test(src)) {
       continue;
     }
 
@@ -536,6 +539,18 @@ const translateAttributeValue = (
       isPropOnNativeElement && !isSpecialPropName(name),
       context
     );
+  } else if (value.attrValueKind === AttributeValueKind.DynamicString) {
+    // undefined values are omitted by join
+    context = addBuffer(`[`, context);
+    for (const part of value.values) {
+      if (part.partKind === DynamicStringAttributeValuePartKind.Literal) {
+        context = addBuffer(JSON.stringify(part.value), context);
+      } else {
+        context = translateStatment(part, false, false, context);
+      }
+      context = addBuffer(`, `, context);
+    }
+    return addBuffer(`].join("")`, context);
   } else if (value.attrValueKind === AttributeValueKind.String) {
     let strValue = JSON.stringify(value.value);
     if (name === "src") {
@@ -596,12 +611,62 @@ const translateStatment = (
     }
     context = endBlock(context);
     context = addBuffer(`}`, context);
+  } else if (statement.jsKind === StatementKind.String) {
+    return addBuffer(JSON.stringify(statement.value), context);
+  } else if (statement.jsKind === StatementKind.Template) {
+    context = addBuffer("`", context);
+    for (const part of statement.parts) {
+      if (part.jsKind === StatementKind.String) {
+        context = addBuffer(
+          part.value.replace(/[\\`]|\$\{/g, match => "\\" + match),
+          context
+        );
+      } else {
+        context = addBuffer("${", context);
+        context = translateStatment(part, false, false, context);
+        context = addBuffer("}", context);
+      }
+    }
+    context = addBuffer("`", context);
   } else if (
     statement.jsKind === StatementKind.Number ||
-    statement.jsKind === StatementKind.String ||
     statement.jsKind === StatementKind.Boolean
   ) {
     return addBuffer(String(statement.value), context);
+  } else if (statement.jsKind === StatementKind.Member) {
+    context = translateStatment(statement.object, false, false, context);
+    if (statement.computed) {
+      context = addBuffer(`[`, context);
+      context = translateStatment(statement.property, false, false, context);
+      context = addBuffer(`]`, context);
+    } else {
+      context = addBuffer(
+        `.${(statement.property as JsString).value}`,
+        context
+      );
+    }
+  } else if (statement.jsKind === StatementKind.Group) {
+    context = addBuffer(`(`, context);
+    context = translateStatment(statement.expression, false, false, context);
+    context = addBuffer(`)`, context);
+  } else if (statement.jsKind === StatementKind.Unary) {
+    context = addBuffer(statement.operator, context);
+    context = translateStatment(statement.expression, false, false, context);
+  } else if (statement.jsKind === StatementKind.Binary) {
+    context = translateStatment(statement.left, false, false, context);
+    context = addBuffer(` ${statement.operator} `, context);
+    context = translateStatment(statement.right, false, false, context);
+  } else if (statement.jsKind === StatementKind.Conditional) {
+    context = translateStatment(statement.test, false, false, context);
+    context = addBuffer(` ? `, context);
+    context = translateStatment(statement.consequent, false, false, context);
+    context = addBuffer(` : `, context);
+    context = translateStatment(statement.alternate, false, false, context);
+  } else if (statement.jsKind === StatementKind.Call) {
+    // helpers only exist in the engine, so there's nothing to call at runtime
+    throw new Error(
+      `Can't compile the call to the "${statement.callee}" helper in ${context.filePath} (at ${statement.location.start}): helpers aren't supported by the React compiler.`
+    );
   }
 
   return context;
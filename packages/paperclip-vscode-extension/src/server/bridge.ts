@@ -21,7 +21,8 @@ import {
   GraphErrorEvent,
   SourceLocation,
   RuntimeErrorEvent,
-  EvaluatedEvent
+  EvaluatedEvent,
+  DiffedEvent
 } from "paperclip";
 
 import * as parseColor from "color";
@@ -196,13 +197,14 @@ export class VSCServiceBridge {
       case EngineEventKind.Error: {
         return this._onEngineErrorEvent(event);
       }
-      case EngineEventKind.Evaluated: {
-        return this._onEngineEvaluatedEvent(event);
+      case EngineEventKind.Evaluated:
+      case EngineEventKind.Diffed: {
+        return this._onEngineRenderedEvent(event);
       }
     }
   };
 
-  private _onEngineEvaluatedEvent(event: EvaluatedEvent) {
+  private _onEngineRenderedEvent(event: EvaluatedEvent | DiffedEvent) {
     // reset error diagnostics
     this.connection.sendDiagnostics({
       uri: event.uri,
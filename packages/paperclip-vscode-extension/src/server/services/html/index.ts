@@ -27,16 +27,15 @@ import {
   getAttributeStringValue,
   AttributeKind,
   StatementKind,
-  resolveImportUri
+  resolveImportUri,
+  stringifyCSSValue,
+  Value,
+  ValueKind,
+  StyleRule
 } from "paperclip";
 import * as path from "path";
 
 import CSS_COLOR_NAMES from "./css-color-names";
-const CSS_COLOR_NAME_LIST = Object.keys(CSS_COLOR_NAMES);
-const CSS_COLOR_NAME_REGEXP = new RegExp(
-  `\\b(${CSS_COLOR_NAME_LIST.join("|")})\\b`,
-  "g"
-);
 
 /**
  * Main HTML language service. Contains everything for now.
@@ -102,28 +101,41 @@ export class PCHTMLLanguageService extends BaseEngineLanguageService<Node> {
     for (const rule of rules) {
       if (rule.kind === RuleKind.Style) {
         this._handleRule(rule, context);
+        this._handleRules(rule.children, context);
+      } else if (
+        rule.kind === RuleKind.Media ||
+        rule.kind === RuleKind.Supports ||
+        rule.kind === RuleKind.Page ||
+        rule.kind === RuleKind.Document
+      ) {
+        this._handleRules(rule.rules, context);
       }
     }
   }
 
-  private _handleRule(rule: Rule, context: HandleContext) {
+  private _handleRule(rule: StyleRule, context: HandleContext) {
     for (const declaration of rule.declarations) {
-      const colors =
-        declaration.value.match(/\#[^\s,;]+|(rgba|rgb|hsl|hsla)\(.*?\)/g) ||
-        declaration.value.match(CSS_COLOR_NAME_REGEXP) ||
-        [];
-
-      for (const color of colors) {
-        const colorIndex = declaration.value.indexOf(color);
-
-        // Color(color)
-        // const {color: [r, g, b], valpha: a } = Color(color);
-        const colorStart = declaration.valueLocation.start + colorIndex;
+      this._handleValues(declaration.value.values, context);
+    }
+  }
 
+  private _handleValues(values: Value[], context: HandleContext) {
+    for (const value of values) {
+      if (
+        value.kind === ValueKind.Color ||
+        (value.kind === ValueKind.Function &&
+          /^(rgba|rgb|hsl|hsla)$/i.test(value.name)) ||
+        (value.kind === ValueKind.Keyword &&
+          CSS_COLOR_NAMES[value.name.toLowerCase()])
+      ) {
         context.info.colors.push({
-          color,
-          location: { start: colorStart, end: colorStart + color.length }
+          color: stringifyCSSValue(value),
+          location: value.location
         });
+      } else if (value.kind === ValueKind.Function) {
+        this._handleValues(value.arguments, context);
+      } else if (value.kind === ValueKind.Var && value.fallback) {
+        this._handleValues(value.fallback, context);
       }
     }
   }
@@ -0,0 +1,189 @@
+import { Html5Entities } from "html-entities";
+import { Mutation, ActionKind } from "paperclip/lib/events";
+import { stringifyCSSSheet } from "paperclip/lib/stringify-sheet";
+import { createNativeNode, DOMNodeMap } from "./native-renderer";
+
+const entities = new Html5Entities();
+
+// Fragments aren't rendered, so a virtual node's position is described by the native
+// parent that its (flattened) nodes live in, and the offset of the first one.
+type Cursor = {
+  node: any;
+  nativeParent: Node;
+  nativeIndex: number;
+};
+
+// number of native nodes that a virtual node renders to
+const getNativeNodeCount = node => {
+  if (node && node.kind === "Fragment") {
+    return node.children.reduce(
+      (count, child) => count + getNativeNodeCount(child),
+      0
+    );
+  }
+  return 1;
+};
+
+const getChildCursor = (parent: Cursor, index: number): Cursor => {
+  const { node } = parent;
+  let nativeParent: Node;
+  let nativeIndex: number;
+  if (node.kind === "Fragment") {
+    nativeParent = parent.nativeParent;
+    nativeIndex = parent.nativeIndex;
+  } else {
+    nativeParent = parent.nativeParent.childNodes[parent.nativeIndex];
+    nativeIndex = 0;
+  }
+  for (let i = 0; i < index; i++) {
+    nativeIndex += getNativeNodeCount(node.children[i]);
+  }
+  return { node: node.children[index], nativeParent, nativeIndex };
+};
+
+const removeNativeNodes = ({ node, nativeParent, nativeIndex }: Cursor) => {
+  const removed: Node[] = [];
+  for (let i = getNativeNodeCount(node); i--; ) {
+    removed.push(
+      nativeParent.removeChild(nativeParent.childNodes[nativeIndex])
+    );
+  }
+  return removed;
+};
+
+const insertNativeNode = (
+  nativeNode: Node,
+  { nativeParent, nativeIndex }: Cursor
+) => {
+  nativeParent.insertBefore(
+    nativeNode,
+    nativeParent.childNodes[nativeIndex] || null
+  );
+};
+
+const getNativeAttributeValue = (
+  name: string,
+  value: string,
+  protocol: string | null
+) => {
+  if (value == null) {
+    return "";
+  }
+  if (name === "src" && protocol) {
+    return value.replace(/\w+:/, protocol);
+  }
+  return value;
+};
+
+/**
+ * Applies mutations to both the virtual root and the DOM rendered from it
+ * into `mount`. Returns the new virtual root.
+ */
+
+export const patchNativeNode = (
+  mount: Node,
+  root: any,
+  mutations: Mutation[],
+  protocol: string | null,
+  map: DOMNodeMap
+) => {
+  for (const { nodePath, action } of mutations) {
+    const path: Cursor[] = [{ node: root, nativeParent: mount, nativeIndex: 0 }];
+    for (const index of nodePath) {
+      path.push(getChildCursor(path[path.length - 1], index));
+    }
+    const target = path[path.length - 1];
+    const { node } = target;
+
+    switch (action.kind) {
+      case ActionKind.InsertChild: {
+        insertNativeNode(
+          createNativeNode(action.child, protocol, map),
+          getChildCursor(target, action.index)
+        );
+        node.children.splice(action.index, 0, action.child);
+        break;
+      }
+      case ActionKind.DeleteChild: {
+        removeNativeNodes(getChildCursor(target, action.index));
+        node.children.splice(action.index, 1);
+        break;
+      }
+      case ActionKind.MoveChild: {
+        const nativeNodes = removeNativeNodes(
+          getChildCursor(target, action.oldIndex)
+        );
+        const [child] = node.children.splice(action.oldIndex, 1);
+        const cursor = getChildCursor(target, action.newIndex);
+        for (const nativeNode of nativeNodes) {
+          insertNativeNode(nativeNode, cursor);
+          cursor.nativeIndex++;
+        }
+        node.children.splice(action.newIndex, 0, child);
+        break;
+      }
+      case ActionKind.SetAttribute: {
+        const element = target.nativeParent.childNodes[
+          target.nativeIndex
+        ] as Element;
+        element.setAttribute(
+          action.name,
+          getNativeAttributeValue(action.name, action.value, protocol)
+        );
+        const attribute = node.attributes.find(
+          attribute => attribute.name === action.name
+        );
+        if (attribute) {
+          attribute.value = action.value;
+        } else {
+          node.attributes.push({
+            id: "",
+            name: action.name,
+            value: action.value
+          });
+        }
+        break;
+      }
+      case ActionKind.RemoveAttribute: {
+        const element = target.nativeParent.childNodes[
+          target.nativeIndex
+        ] as Element;
+        element.removeAttribute(action.name);
+        node.attributes = node.attributes.filter(
+          attribute => attribute.name !== action.name
+        );
+        break;
+      }
+      case ActionKind.SetText: {
+        target.nativeParent.childNodes[
+          target.nativeIndex
+        ].textContent = entities.decode(action.value);
+        node.value = action.value;
+        break;
+      }
+      case ActionKind.ReplaceSheet: {
+        target.nativeParent.childNodes[
+          target.nativeIndex
+        ].textContent = stringifyCSSSheet(action.sheet, protocol);
+        node.sheet = action.sheet;
+        break;
+      }
+      case ActionKind.ReplaceNode: {
+        removeNativeNodes(target);
+        insertNativeNode(
+          createNativeNode(action.replacement, protocol, map),
+          target
+        );
+        if (path.length === 1) {
+          root = action.replacement;
+        } else {
+          path[path.length - 2].node.children[nodePath[nodePath.length - 1]] =
+            action.replacement;
+        }
+        break;
+      }
+    }
+  }
+
+  return root;
+};
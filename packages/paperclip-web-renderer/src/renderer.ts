@@ -1,4 +1,5 @@
 import { createNativeNode, DOMNodeMap } from "./native-renderer";
+import { patchNativeNode } from "./patch";
 // import { Node as VirtNode } from "paperclip";
 import { EventEmitter } from "events";
 import { preventDefault } from "./utils";
@@ -8,14 +9,15 @@ enum RenderEventTypes {
 }
 
 export class Renderer {
-  private _scopeFilePath: string;
+  private _scopeUri: string;
   private _nativeNodeMap: DOMNodeMap;
   private _em: EventEmitter;
   private _hoverOverlay: HTMLElement;
   private _stage: HTMLElement;
   private _virtualRootNode: any;
   readonly mount: HTMLDivElement;
-  constructor(readonly protocol: string) {
+  // name of the <preview /> to render. The entire document is rendered if omitted.
+  constructor(readonly protocol: string, readonly preview?: string) {
     this._em = new EventEmitter();
     this._hoverOverlay = document.createElement("div");
     Object.assign(this._hoverOverlay.style, {
@@ -45,8 +47,14 @@ export class Renderer {
   };
 
   handleEngineEvent(event) {
-    // only accept events scoped to current file path
-    if (event.kind !== "Evaluated" && event.file_path !== this._scopeFilePath) {
+    // only accept events scoped to current file
+    if (event.kind !== "Evaluated" && event.uri !== this._scopeUri) {
+      return;
+    }
+
+    // named previews are rendered separately
+    const isRenderEvent = event.kind === "Evaluated" || event.kind === "Diffed";
+    if (isRenderEvent && event.preview != this.preview) {
       return;
     }
     switch (event.kind) {
@@ -54,7 +62,7 @@ export class Renderer {
         while (this._stage.childNodes.length) {
           this._stage.removeChild(this._stage.childNodes[0]);
         }
-        this._scopeFilePath = event.file_path;
+        this._scopeUri = event.uri;
         this._virtualRootNode = event.node;
         this._nativeNodeMap = new Map();
         const node = createNativeNode(
@@ -63,9 +71,17 @@ export class Renderer {
           this._nativeNodeMap
         );
         this._stage.appendChild(node);
+        break;
       }
       case "Diffed": {
-        // TODO
+        this._virtualRootNode = patchNativeNode(
+          this._stage,
+          this._virtualRootNode,
+          event.mutations,
+          this.protocol,
+          this._nativeNodeMap
+        );
+        break;
       }
     }
   }
@@ -1,19 +1,71 @@
 use std::fmt;
 use serde::{Serialize};
 
+// Lines & columns are zero-based so that they can be passed directly to
+// editors (LSP, VS Code). `column` is a byte offset from the start of the line, and
+// `utf16_column` is the same column counted in UTF-16 code units (what JS strings use).
+#[derive(Debug, PartialEq, Serialize, Clone, Default)]
+pub struct Position {
+  pub line: usize,
+  pub column: usize,
+
+  #[serde(rename = "utf16Column")]
+  pub utf16_column: usize
+}
+
+impl Position {
+
+  // position after `text`, which starts at this position
+  pub fn advance(&self, text: &str) -> Position {
+    let mut position = self.clone();
+    for c in text.chars() {
+      if c == '\n' {
+        position.line += 1;
+        position.column = 0;
+        position.utf16_column = 0;
+      } else {
+        position.column += c.len_utf8();
+        position.utf16_column += c.len_utf16();
+      }
+    }
+    position
+  }
+}
+
 #[derive(Debug, PartialEq, Serialize, Clone)]
 pub struct Location {
   pub start: usize,
   pub end: usize,
+
+  #[serde(rename = "startPosition")]
+  pub start_position: Position,
+
+  #[serde(rename = "endPosition")]
+  pub end_position: Position
 }
 
 impl Location {
+
+  // positions are left at 0:0, so use `with_positions` or `Tokenizer::location` for anything past the start of a file
   pub fn new(start: usize, end: usize) -> Location {
     Location {
       start,
-      end
+      end,
+      start_position: Position::default(),
+      end_position: Position::default()
     }
   }
+
+  // empty location at the start of a file, for errors about the file as a whole
+  pub fn start_of_file() -> Location {
+    Location::new(0, 0)
+  }
+
+  pub fn with_positions(mut self, line_index: &LineIndex) -> Location {
+    self.start_position = line_index.position(self.start);
+    self.end_position = line_index.position(self.end);
+    self
+  }
 }
 
 impl fmt::Display for Location {
@@ -22,10 +74,50 @@ impl fmt::Display for Location {
   }
 }
 
+#[derive(Debug, Clone)]
+pub struct LineIndex<'a> {
+  source: &'a str,
+  line_starts: Vec<usize>
+}
+
+impl<'a> LineIndex<'a> {
+  pub fn new(source: &'a str) -> LineIndex<'a> {
+    let mut line_starts = vec![0];
+    for (i, c) in source.bytes().enumerate() {
+      if c == b'\n' {
+        line_starts.push(i + 1);
+      }
+    }
+    LineIndex {
+      source,
+      line_starts
+    }
+  }
+
+  pub fn position(&self, offset: usize) -> Position {
+    let offset = offset.min(self.source.len());
+    let line = match self.line_starts.binary_search(&offset) {
+      Ok(line) => line,
+      Err(next_line) => next_line - 1
+    };
+    let line_start = self.line_starts[line];
+    let utf16_column = self.source[line_start..].char_indices()
+    .take_while(|(i, _)| line_start + i < offset)
+    .map(|(_, c)| c.len_utf16())
+    .sum();
+
+    Position {
+      line,
+      column: offset - line_start,
+      utf16_column
+    }
+  }
+}
+
 
 // TODO - change to trait
 #[derive(Debug, PartialEq, Serialize)]
 pub struct Expression<TItem> {
   // TODO - location: Location
   pub item: TItem
-}
\ No newline at end of file
+}
@@ -1,6 +1,6 @@
 use super::tokenizer::*;
 use serde::{Serialize};
-use crate::base::ast::{Location};
+use crate::base::ast::{Location, LineIndex};
 
 #[derive(Debug, PartialEq, Serialize, Clone)]
 pub enum ParseErrorKind {
@@ -22,9 +22,13 @@ impl ParseError {
     ParseError {
       kind,
       message,
-      location: Location { start, end }
+      location: Location::new(start, end)
     }
   }
+  pub fn with_positions(mut self, line_index: &LineIndex) -> ParseError {
+    self.location = self.location.with_positions(line_index);
+    self
+  }
   pub fn unexpected_token(start: usize) -> ParseError {
     ParseError::new(ParseErrorKind::Unexpected, "Unexpected token".to_string(), start, start + 1)
   }
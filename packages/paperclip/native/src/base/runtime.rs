@@ -16,10 +16,7 @@ impl RuntimeError {
       location: location.clone()
     }
   }
-  pub fn unknown(uri: &String) -> RuntimeError {
-    RuntimeError::new("An unknown error has occurred.".to_string(), uri, &ast::Location {
-      start: 0,
-      end: 1
-    })
+  pub fn unknown(uri: &String, location: &ast::Location) -> RuntimeError {
+    RuntimeError::new("An unknown error has occurred.".to_string(), uri, location)
   }
 }
\ No newline at end of file
@@ -1,4 +1,5 @@
 use super::parser::{ParseError};
+use super::ast::{Location, LineIndex};
 
 
 #[derive(PartialEq, Debug)]
@@ -125,7 +126,8 @@ pub enum Token<'a> {
 
 pub struct Tokenizer<'a> {
   pub source: &'a [u8],
-  pub pos: usize
+  pub pos: usize,
+  pub line_index: LineIndex<'a>
 }
 
 impl<'a> Tokenizer<'a> {
@@ -140,8 +142,8 @@ impl<'a> Tokenizer<'a> {
     }
   }
 
-  pub fn utf8_pos() {
-
+  pub fn location(&self, start: usize, end: usize) -> Location {
+    Location::new(start, end).with_positions(&self.line_index)
   }
 
   pub fn peek(&mut self, steps: u8) -> Result<Token<'a>, ParseError> {
@@ -379,7 +381,7 @@ impl<'a> Tokenizer<'a> {
     self.pos >= self.source.len()
   }
   pub fn new(source: &'a str) -> Tokenizer {
-      Tokenizer { source: source.as_bytes(), pos: 0 }
+      Tokenizer { source: source.as_bytes(), pos: 0, line_index: LineIndex::new(source) }
   }
 }
 
@@ -387,6 +389,7 @@ impl<'a> Tokenizer<'a> {
 mod tests {
 
   use super::*;
+  use crate::base::ast::{Position};
 
   #[test]
   fn can_tokenize_a_less_than_tag() {
@@ -566,4 +569,14 @@ mod tests {
     assert_eq!(tokenizer.next(), Ok(Token::Minus));
     assert_eq!(tokenizer.next(), Ok(Token::Number("9")));
   }
+
+  #[test]
+  fn can_get_line_and_column_positions() {
+    let tokenizer = Tokenizer::new("ab\nçd😀e\n");
+    assert_eq!(tokenizer.line_index.position(1), Position { line: 0, column: 1, utf16_column: 1 });
+    assert_eq!(tokenizer.line_index.position(3), Position { line: 1, column: 0, utf16_column: 0 });
+    assert_eq!(tokenizer.line_index.position(6), Position { line: 1, column: 3, utf16_column: 2 });
+    assert_eq!(tokenizer.line_index.position(10), Position { line: 1, column: 7, utf16_column: 4 });
+    assert_eq!(tokenizer.line_index.position(12), Position { line: 2, column: 0, utf16_column: 0 });
+  }
 } 
\ No newline at end of file
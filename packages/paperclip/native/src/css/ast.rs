@@ -5,7 +5,7 @@ use crate::base::ast::{Location};
 #[derive(Debug, PartialEq, Serialize, Clone)]
 pub struct Declaration {
   pub name: String,
-  pub value: String,
+  pub value: DeclarationValue,
   pub location: Location,
 
   #[serde(rename = "nameLocation")]
@@ -22,11 +22,195 @@ impl fmt::Display for Declaration {
   }
 }
 
+// rgba(0, 0, 0, 0.5) !important
+#[derive(Debug, PartialEq, Serialize, Clone)]
+pub struct DeclarationValue {
+  pub values: Vec<Value>,
+  pub important: bool,
+
+  // source text of the value, which output is built from
+  pub raw: String
+}
+
+impl fmt::Display for DeclarationValue {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", &self.raw)
+  }
+}
+
+// values are space separated, apart from commas
+fn stringify_values(values: &Vec<Value>) -> String {
+  let mut buffer = String::new();
+  for value in values {
+    if buffer.len() > 0 && !matches!(value, Value::Comma(_)) {
+      buffer.push(' ');
+    }
+    buffer.push_str(&value.to_string());
+  }
+  buffer
+}
+
+#[derive(Debug, PartialEq, Serialize, Clone)]
+#[serde(tag = "kind")]
+pub enum Value {
+  Keyword(Keyword),
+  Number(Number),
+  Color(Color),
+  String(StringValue),
+  Url(UrlValue),
+  Var(VarValue),
+  Function(FunctionValue),
+  Comma(Comma),
+  Operator(Operator)
+}
+
+impl fmt::Display for Value {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Value::Keyword(value) => write!(f, "{}", value.to_string()),
+      Value::Number(value) => write!(f, "{}", value.to_string()),
+      Value::Color(value) => write!(f, "{}", value.to_string()),
+      Value::String(value) => write!(f, "{}", value.to_string()),
+      Value::Url(value) => write!(f, "{}", value.to_string()),
+      Value::Var(value) => write!(f, "{}", value.to_string()),
+      Value::Function(value) => write!(f, "{}", value.to_string()),
+      Value::Comma(value) => write!(f, "{}", value.to_string()),
+      Value::Operator(value) => write!(f, "{}", value.to_string())
+    }
+  }
+}
+
+// solid, inherit, --custom-property
+#[derive(Debug, PartialEq, Serialize, Clone)]
+pub struct Keyword {
+  pub name: String,
+  pub location: Location
+}
+
+impl fmt::Display for Keyword {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", &self.name)
+  }
+}
+
+// 10px, 50%, -1.5
+#[derive(Debug, PartialEq, Serialize, Clone)]
+pub struct Number {
+  pub value: String,
+  pub unit: String,
+  pub location: Location
+}
+
+impl fmt::Display for Number {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}{}", &self.value, &self.unit)
+  }
+}
+
+// #F00
+#[derive(Debug, PartialEq, Serialize, Clone)]
+pub struct Color {
+  pub hex: String,
+  pub location: Location
+}
+
+impl fmt::Display for Color {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "#{}", &self.hex)
+  }
+}
+
+// "Helvetica Neue"
+#[derive(Debug, PartialEq, Serialize, Clone)]
+pub struct StringValue {
+  pub value: String,
+  pub location: Location
+}
+
+impl fmt::Display for StringValue {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    if self.value.contains('"') {
+      write!(f, "'{}'", &self.value)
+    } else {
+      write!(f, "\"{}\"", &self.value)
+    }
+  }
+}
+
+// url(./image.png)
+#[derive(Debug, PartialEq, Serialize, Clone)]
+pub struct UrlValue {
+  pub url: String,
+  pub location: Location
+}
+
+impl fmt::Display for UrlValue {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "url(\"{}\")", &self.url)
+  }
+}
+
+// var(--color, red)
+#[derive(Debug, PartialEq, Serialize, Clone)]
+pub struct VarValue {
+  pub name: String,
+  pub fallback: Option<Vec<Value>>,
+  pub location: Location
+}
+
+impl fmt::Display for VarValue {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match &self.fallback {
+      Some(fallback) => write!(f, "var({}, {})", &self.name, stringify_values(fallback)),
+      None => write!(f, "var({})", &self.name)
+    }
+  }
+}
+
+// rgba(0, 0, 0, 0.5), calc(100% - 10px)
+#[derive(Debug, PartialEq, Serialize, Clone)]
+pub struct FunctionValue {
+  pub name: String,
+  pub arguments: Vec<Value>,
+  pub location: Location
+}
+
+impl fmt::Display for FunctionValue {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}({})", &self.name, stringify_values(&self.arguments))
+  }
+}
+
+#[derive(Debug, PartialEq, Serialize, Clone)]
+pub struct Comma {
+  pub location: Location
+}
+
+impl fmt::Display for Comma {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, ",")
+  }
+}
+
+// 12px / 1.5, calc(1px + 2px)
+#[derive(Debug, PartialEq, Serialize, Clone)]
+pub struct Operator {
+  pub value: String,
+  pub location: Location
+}
+
+impl fmt::Display for Operator {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", &self.value)
+  }
+}
+
 #[derive(Debug, PartialEq, Serialize, Clone)]
 #[serde(tag = "kind")]
 pub enum Rule {
   Style(StyleRule),
   Charset(String),
+  Import(ImportRule),
   Namespace(String),
   FontFace(FontFaceRule),
   Media(ConditionRule),
@@ -41,6 +225,7 @@ impl fmt::Display for Rule {
     match self {
       Rule::Style(rule) => write!(f, "{}", rule.to_string()),
       Rule::Charset(value) => write!(f, "@charset {}", value),
+      Rule::Import(rule) => write!(f, "{}", rule.to_string()),
       Rule::Namespace(value) => write!(f, "@namespace {}", value),
       Rule::FontFace(rule) => write!(f, "{}", rule.to_string()),
       Rule::Media(rule) => write!(f, "{}", rule.to_string()),
@@ -52,10 +237,27 @@ impl fmt::Display for Rule {
   }
 }
 
+// @import url("./theme.css");
+#[derive(Debug, PartialEq, Serialize, Clone)]
+pub struct ImportRule {
+  pub uri: String,
+  pub location: Location
+}
+
+impl fmt::Display for ImportRule {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    writeln!(f, "@import url(\"{}\");", &self.uri)
+  }
+}
+
 #[derive(Debug, PartialEq, Serialize, Clone)]
 pub struct StyleRule {
   pub selector: Selector,
-  pub declarations: Vec<Declaration>
+  pub declarations: Vec<Declaration>,
+
+  // nested style, @media, and @supports rules
+  pub children: Vec<Rule>,
+  pub location: Location
 }
 
 impl fmt::Display for StyleRule {
@@ -64,6 +266,9 @@ impl fmt::Display for StyleRule {
     for decl in &self.declarations {
       write!(f, "  {}", &decl.to_string())?;
     }
+    for rule in &self.children {
+      write!(f, "{}", &rule.to_string())?;
+    }
     writeln!(f, "}}")?;
 
     Ok(())
@@ -92,7 +297,7 @@ impl fmt::Display for FontFaceRule {
 pub struct ConditionRule {
   pub name: String,
   pub condition_text: String,
-  pub rules: Vec<StyleRule>
+  pub rules: Vec<Rule>
 }
 
 impl fmt::Display for ConditionRule {
@@ -110,12 +315,19 @@ impl fmt::Display for ConditionRule {
 #[derive(Debug, PartialEq, Serialize, Clone)]
 pub struct KeyframesRule {
   pub name: String,
+
+  // global keyframes aren't scoped to the document
+  pub global: bool,
   pub rules: Vec<KeyframeRule>
 }
 
 impl fmt::Display for KeyframesRule {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-    writeln!(f, "@keyframes {} {{", &self.name)?;
+    if self.global {
+      writeln!(f, "@keyframes :global({}) {{", &self.name)?;
+    } else {
+      writeln!(f, "@keyframes {} {{", &self.name)?;
+    }
     for rule in &self.rules {
       write!(f, "{}\n", &rule.to_string())?;
     }
@@ -154,6 +366,9 @@ pub enum Selector {
   PseudoElement(PseudoElementSelector),
   PseudoParamElement(PseudoParamElementSelector),
   Not(NotSelector),
+  Global(GlobalSelector),
+  This(SelfSelector),
+  Nesting(NestingSelector),
   Child(ChildSelector),
   Adjacent(AdjacentSelector),
   Sibling(SiblingSelector),
@@ -161,7 +376,7 @@ pub enum Selector {
   Element(ElementSelector),
   Attribute(AttributeSelector),
   Class(ClassSelector),
-  AllSelector
+  AllSelector(AllSelector)
 }
 
 impl fmt::Display for Selector {
@@ -172,6 +387,9 @@ impl fmt::Display for Selector {
       Selector::Element(selector) => write!(f, "{}", selector.to_string()),
       Selector::Descendent(selector) => write!(f, "{}", selector.to_string()),
       Selector::Not(selector) => write!(f, "{}", selector.to_string()),
+      Selector::Global(selector) => write!(f, "{}", selector.to_string()),
+      Selector::This(selector) => write!(f, "{}", selector.to_string()),
+      Selector::Nesting(selector) => write!(f, "{}", selector.to_string()),
       Selector::Adjacent(selector) => write!(f, "{}", selector.to_string()),
       Selector::PseudoElement(selector) => write!(f, "{}", selector.to_string()),
       Selector::PseudoParamElement(selector) => write!(f, "{}", selector.to_string()),
@@ -180,15 +398,52 @@ impl fmt::Display for Selector {
       Selector::Class(selector) => write!(f, "{}", selector.to_string()),
       Selector::Id(selector) => write!(f, "{}", selector.to_string()),
       Selector::Attribute(selector) => write!(f, "{}", selector.to_string()),
-      Selector::AllSelector => write!(f, "*")
+      Selector::AllSelector(selector) => write!(f, "{}", selector.to_string())
+    }
+  }
+}
+
+impl Selector {
+  pub fn get_location(&self) -> &Location {
+    match self {
+      Selector::Group(selector) => &selector.location,
+      Selector::Combo(selector) => &selector.location,
+      Selector::Element(selector) => &selector.location,
+      Selector::Descendent(selector) => &selector.location,
+      Selector::Not(selector) => &selector.location,
+      Selector::Global(selector) => &selector.location,
+      Selector::This(selector) => &selector.location,
+      Selector::Nesting(selector) => &selector.location,
+      Selector::Adjacent(selector) => &selector.location,
+      Selector::PseudoElement(selector) => &selector.location,
+      Selector::PseudoParamElement(selector) => &selector.location,
+      Selector::Sibling(selector) => &selector.location,
+      Selector::Child(selector) => &selector.location,
+      Selector::Class(selector) => &selector.location,
+      Selector::Id(selector) => &selector.location,
+      Selector::Attribute(selector) => &selector.location,
+      Selector::AllSelector(selector) => &selector.location
     }
   }
 }
 
+// * { }
+#[derive(Debug, PartialEq, Serialize, Clone)]
+pub struct AllSelector {
+  pub location: Location
+}
+
+impl fmt::Display for AllSelector {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "*")
+  }
+}
+
 // a, b, h1, h2 { }
 #[derive(Debug, PartialEq, Serialize, Clone)]
 pub struct GroupSelector {
-  pub selectors: Vec<Selector>
+  pub selectors: Vec<Selector>,
+  pub location: Location
 }
 
 impl fmt::Display for GroupSelector {
@@ -204,7 +459,8 @@ impl fmt::Display for GroupSelector {
 // a.b[c=d] {}
 #[derive(Debug, PartialEq, Serialize, Clone)]
 pub struct ComboSelector {
-  pub selectors: Vec<Selector>
+  pub selectors: Vec<Selector>,
+  pub location: Location
 }
 
 impl fmt::Display for ComboSelector {
@@ -221,7 +477,8 @@ impl fmt::Display for ComboSelector {
 #[derive(Debug, PartialEq, Serialize, Clone)]
 pub struct DescendentSelector {
   pub parent: Box<Selector>,
-  pub descendent: Box<Selector>
+  pub descendent: Box<Selector>,
+  pub location: Location
 }
 
 impl fmt::Display for DescendentSelector {
@@ -234,12 +491,14 @@ impl fmt::Display for DescendentSelector {
 #[derive(Debug, PartialEq, Serialize, Clone)]
 pub struct ChildSelector {
   pub parent: Box<Selector>,
-  pub child: Box<Selector>
+  pub child: Box<Selector>,
+  pub location: Location
 }
 
 #[derive(Debug, PartialEq, Serialize, Clone)]
 pub struct NotSelector {
-  pub selector: Box<Selector>
+  pub selector: Box<Selector>,
+  pub location: Location
 }
 
 impl fmt::Display for NotSelector {
@@ -248,6 +507,47 @@ impl fmt::Display for NotSelector {
   }
 }
 
+// :global(.a .b) {} -- isn't scoped to the document
+#[derive(Debug, PartialEq, Serialize, Clone)]
+pub struct GlobalSelector {
+  pub selector: Box<Selector>,
+  pub location: Location
+}
+
+impl fmt::Display for GlobalSelector {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, ":global({})", self.selector.to_string())
+  }
+}
+
+// :self {}, :self(.primary) {} -- the root element of the document's instances
+#[derive(Debug, PartialEq, Serialize, Clone)]
+pub struct SelfSelector {
+  pub selector: Option<Box<Selector>>,
+  pub location: Location
+}
+
+impl fmt::Display for SelfSelector {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match &self.selector {
+      Some(selector) => write!(f, ":self({})", selector.to_string()),
+      None => write!(f, ":self")
+    }
+  }
+}
+
+// .a { &:hover {} } -- the parent rule's selector
+#[derive(Debug, PartialEq, Serialize, Clone)]
+pub struct NestingSelector {
+  pub location: Location
+}
+
+impl fmt::Display for NestingSelector {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "&")
+  }
+}
+
 impl fmt::Display for ChildSelector {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
     write!(f, "{} > {}", self.parent.to_string(), self.child.to_string())
@@ -258,7 +558,8 @@ impl fmt::Display for ChildSelector {
 #[derive(Debug, PartialEq, Serialize, Clone)]
 pub struct AdjacentSelector {
   pub selector: Box<Selector>,
-  pub next_sibling_selector: Box<Selector>
+  pub next_sibling_selector: Box<Selector>,
+  pub location: Location
 }
 
 impl fmt::Display for AdjacentSelector {
@@ -271,7 +572,8 @@ impl fmt::Display for AdjacentSelector {
 #[derive(Debug, PartialEq, Serialize, Clone)]
 pub struct SiblingSelector {
   pub selector: Box<Selector>,
-  pub sibling_selector: Box<Selector>
+  pub sibling_selector: Box<Selector>,
+  pub location: Location
 }
 
 impl fmt::Display for SiblingSelector {
@@ -285,7 +587,8 @@ impl fmt::Display for SiblingSelector {
 pub struct PseudoElementSelector {
   pub separator: String, // : or ::
   pub target: Option<Box<Selector>>,
-  pub name: String
+  pub name: String,
+  pub location: Location
 }
 
 fn stringify_optional_selector(selector: &Option<Box<Selector>>) -> String {
@@ -308,7 +611,8 @@ impl fmt::Display for PseudoElementSelector {
 pub struct PseudoParamElementSelector {
   pub target: Option<Box<Selector>>,
   pub name: String,
-  pub param: String
+  pub param: String,
+  pub location: Location
 }
 
 impl fmt::Display for PseudoParamElementSelector {
@@ -321,7 +625,8 @@ impl fmt::Display for PseudoParamElementSelector {
 // div { }
 #[derive(Debug, PartialEq, Serialize, Clone)]
 pub struct ElementSelector {
-  pub tag_name: String
+  pub tag_name: String,
+  pub location: Location
 }
 
 impl fmt::Display for ElementSelector {
@@ -334,7 +639,8 @@ impl fmt::Display for ElementSelector {
 // .div { }
 #[derive(Debug, PartialEq, Serialize, Clone)]
 pub struct ClassSelector {
-  pub class_name: String
+  pub class_name: String,
+  pub location: Location
 }
 
 
@@ -348,7 +654,8 @@ impl fmt::Display for ClassSelector {
 // #div { }
 #[derive(Debug, PartialEq, Serialize, Clone)]
 pub struct IdSelector {
-  pub id: String
+  pub id: String,
+  pub location: Location
 }
 
 
@@ -363,7 +670,8 @@ impl fmt::Display for IdSelector {
 #[derive(Debug, PartialEq, Serialize, Clone)]
 pub struct AttributeSelector {
   pub name: String,
-  pub value: Option<String>
+  pub value: Option<String>,
+  pub location: Location
 }
 
 
@@ -392,3 +700,10 @@ impl fmt::Display for Sheet {
     Ok(())
   }
 }
+
+pub fn get_imports<'a>(sheet: &'a Sheet) -> Vec<&'a ImportRule> {
+  sheet.rules.iter().filter_map(|rule| match rule {
+    Rule::Import(rule) => Some(rule),
+    _ => None
+  }).collect()
+}
@@ -5,37 +5,77 @@
 
 use super::ast::*;
 use crate::base::parser::{get_buffer, ParseError};
-use crate::base::ast::{Location};
 use crate::base::tokenizer::{Token, Tokenizer};
 
 type FUntil<'a> = for<'r> fn(&mut Tokenizer<'a>) -> Result<bool, ParseError>;
 
 pub struct Context<'a, 'b> {
   tokenizer: &'b mut Tokenizer<'a>,
-  until: FUntil<'a>
+  until: FUntil<'a>,
+
+  // Some if errors should be collected instead of bailing on the first one
+  errors: Option<Vec<ParseError>>
 }
 
 impl<'a, 'b> Context<'a, 'b> {
   pub fn ended(&mut self) -> Result<bool, ParseError> {
     Ok(self.tokenizer.is_eof() || (self.until)(self.tokenizer)?)
   }
+  fn record(&mut self, error: ParseError) -> Result<(), ParseError> {
+    if let Some(errors) = &mut self.errors {
+      errors.push(error);
+      Ok(())
+    } else {
+      Err(error)
+    }
+  }
 }
 
 pub fn parse<'a>(source: &'a str) -> Result<Sheet, ParseError> {
   let mut tokenizer = Tokenizer::new(&source);
-  parse_with_tokenizer(&mut tokenizer, |_token| { Ok(false) })
+  parse_with_tokenizer(&mut tokenizer, |_token| { Ok(false) }).map_err(|err| err.with_positions(&tokenizer.line_index))
 }
 
 pub fn parse_with_tokenizer<'a>(tokenizer: &mut Tokenizer<'a>, until: FUntil<'a>) -> Result<Sheet, ParseError> {
 
   let mut context = Context {
     tokenizer,
-    until
+    until,
+    errors: None
   };
 
   parse_sheet(&mut context)
 }
 
+// Returns a best-effort sheet along with all errors encountered while parsing.
+pub fn parse_with_recovery<'a>(source: &'a str) -> (Sheet, Vec<ParseError>) {
+  let mut tokenizer = Tokenizer::new(&source);
+  let (sheet, errors) = parse_with_tokenizer_and_recovery(&mut tokenizer, |_token| { Ok(false) });
+  (sheet, errors.into_iter().map(|err| err.with_positions(&tokenizer.line_index)).collect())
+}
+
+pub fn parse_with_tokenizer_and_recovery<'a>(tokenizer: &mut Tokenizer<'a>, until: FUntil<'a>) -> (Sheet, Vec<ParseError>) {
+
+  let mut context = Context {
+    tokenizer,
+    until,
+    errors: Some(vec![])
+  };
+
+  let result = parse_sheet(&mut context);
+  let mut errors = context.errors.unwrap();
+
+  let sheet = match result {
+    Ok(sheet) => sheet,
+    Err(err) => {
+      errors.push(err);
+      Sheet { rules: vec![] }
+    }
+  };
+
+  (sheet, errors)
+}
+
 fn eat_comments<'a, 'b>(context: &mut Context<'a, 'b>, start: Token, end: Token) -> Result<(), ParseError> {
   if context.ended()? || context.tokenizer.peek(1)? != start {
     return Ok(())
@@ -61,12 +101,45 @@ fn parse_rules<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<Vec<Rule>, Parse
   let mut rules = vec![];
   eat_superfluous(context)?;
   while !context.ended()? {
-    rules.push(parse_rule(context)?);
+    let start = context.tokenizer.pos;
+    match parse_rule(context) {
+      Ok(rule) => rules.push(rule),
+      Err(err) => {
+        context.record(err)?;
+        skip_rule(context, start)?;
+      }
+    }
     eat_superfluous(context)?;
   }
   Ok(rules)
 }
 
+// skips to the end of a broken rule so that parsing can continue from the next one
+fn skip_rule<'a, 'b>(context: &mut Context<'a, 'b>, start: usize) -> Result<(), ParseError> {
+  context.tokenizer.pos = start;
+  let mut depth = 0;
+  while !context.ended()? {
+    match context.tokenizer.next()? {
+      Token::CurlyOpen => {
+        depth += 1;
+      },
+      Token::CurlyClose => {
+        depth -= 1;
+        if depth <= 0 {
+          break;
+        }
+      },
+      Token::Semicolon => {
+        if depth == 0 {
+          break;
+        }
+      },
+      _ => {}
+    }
+  }
+  Ok(())
+}
+
 fn eat_superfluous<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<(), ParseError> {
   while !context.ended()? {
     let tok = context.tokenizer.peek(1).unwrap();
@@ -98,30 +171,155 @@ fn parse_style_rule<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<Rule, Parse
 }
 
 fn parse_style_rule2<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<StyleRule, ParseError> {
+  let start = context.tokenizer.pos;
   let selector = parse_selector(context)?;
-  let declarations = parse_declaration_body(context)?;
+  let (declarations, children) = parse_style_rule_body(context)?;
+  let end = context.tokenizer.pos;
+  eat_superfluous(context)?;
   Ok(StyleRule {
     selector,
     declarations,
+    children,
+    location: context.tokenizer.location(start, end)
+  })
+}
+
+// { color: red; &:hover { } @media screen { } }
+fn parse_style_rule_body<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<(Vec<Declaration>, Vec<Rule>), ParseError> {
+  eat_superfluous(context)?;
+  let block_start = context.tokenizer.pos;
+  context.tokenizer.next_expect(Token::CurlyOpen)?; // eat {
+  let mut declarations = vec![];
+  let mut children = vec![];
+  eat_superfluous(context)?;
+  while !context.ended()? {
+    if context.tokenizer.peek(1)? == Token::CurlyClose {
+      break
+    }
+    let start = context.tokenizer.pos;
+    if is_nested_rule(context)? {
+      match parse_nested_rule(context) {
+        Ok(rule) => children.push(rule),
+        Err(err) => {
+          context.record(err)?;
+          skip_rule(context, start)?;
+        }
+      }
+    } else {
+      match parse_declaration(context) {
+        Ok(declaration) => declarations.push(declaration),
+        Err(err) => {
+          context.record(err)?;
+          skip_declaration(context, start)?;
+        }
+      }
+    }
+    eat_superfluous(context)?;
+  }
+  let block_end = context.tokenizer.pos;
+  if context.tokenizer.next_expect(Token::CurlyClose).is_err() {
+    let error = ParseError::unterminated("Unterminated bracket.".to_string(), block_start, context.tokenizer.pos);
+
+    // leave whatever is there for the parent
+    context.tokenizer.pos = block_end;
+    context.record(error)?;
+  }
+
+  Ok((declarations, children))
+}
+
+// nested rules open a block before a declaration would end
+fn is_nested_rule<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<bool, ParseError> {
+  if context.tokenizer.peek(1)? == Token::At {
+    return Ok(true);
+  }
+  let start = context.tokenizer.pos;
+  let mut nested = false;
+  while !context.tokenizer.is_eof() {
+    match context.tokenizer.next()? {
+      Token::CurlyOpen => {
+        nested = true;
+        break;
+      },
+      Token::Semicolon | Token::CurlyClose => break,
+      _ => {}
+    }
+  }
+  context.tokenizer.pos = start;
+  Ok(nested)
+}
+
+fn parse_nested_rule<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<Rule, ParseError> {
+  if context.tokenizer.peek(1)? != Token::At {
+    return parse_style_rule(context);
+  }
+  let start = context.tokenizer.pos;
+  context.tokenizer.next_expect(Token::At)?;
+  let name = parse_selector_name(context)?;
+  eat_superfluous(context)?;
+  match name {
+    "media" => {
+      Ok(Rule::Media(parse_nested_condition_rule(name.to_string(), start, context)?))
+    },
+    "supports" => {
+      Ok(Rule::Supports(parse_nested_condition_rule(name.to_string(), start, context)?))
+    },
+    _ => {
+      Err(ParseError::unexpected(format!("@{} can't be nested in style rules", name), start, context.tokenizer.pos))
+    }
+  }
+}
+
+// .a { @media screen { color: red; } } -- declarations apply to the parent selector, so the
+// body is kept as a single & { } rule
+fn parse_nested_condition_rule<'a, 'b>(name: String, start: usize, context: &mut Context<'a, 'b>) -> Result<ConditionRule, ParseError> {
+  let condition_text = get_buffer(context.tokenizer, |tokenizer| {
+    Ok(tokenizer.peek(1)? != Token::CurlyOpen)
+  })?.to_string();
+  let (declarations, children) = parse_style_rule_body(context)?;
+  let location = context.tokenizer.location(start, context.tokenizer.pos);
+
+  Ok(ConditionRule {
+    name,
+    condition_text,
+    rules: vec![Rule::Style(StyleRule {
+      selector: Selector::Nesting(NestingSelector {
+        location: location.clone()
+      }),
+      declarations,
+      children,
+      location
+    })]
   })
 }
 
 
 fn parse_declaration_body<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<Vec<Declaration>, ParseError> {
+  let declarations = parse_declaration_body2(context)?;
+  eat_superfluous(context)?;
+  Ok(declarations)
+}
+
+fn parse_declaration_body2<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<Vec<Declaration>, ParseError> {
   eat_superfluous(context)?;
   let block_start = context.tokenizer.pos;
   context.tokenizer.next_expect(Token::CurlyOpen)?; // eat {
   let declarations = parse_declarations(context)?;
   eat_superfluous(context)?;
-  context.tokenizer
-  .next_expect(Token::CurlyClose)
-  .or(Err(ParseError::unterminated("Unterminated bracket.".to_string(), block_start, context.tokenizer.pos)))?;
+  let block_end = context.tokenizer.pos;
+  if context.tokenizer.next_expect(Token::CurlyClose).is_err() {
+    let error = ParseError::unterminated("Unterminated bracket.".to_string(), block_start, context.tokenizer.pos);
+
+    // leave whatever is there for the parent
+    context.tokenizer.pos = block_end;
+    context.record(error)?;
+  }
 
-  eat_superfluous(context)?;
   Ok(declarations)
 }
 
 fn parse_at_rule<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<Rule, ParseError> {
+  let start = context.tokenizer.pos;
   context.tokenizer.next_expect(Token::At)?;
   let name = parse_selector_name(context)?;
   eat_superfluous(context)?;
@@ -131,6 +329,9 @@ fn parse_at_rule<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<Rule, ParseErr
       context.tokenizer.next_expect(Token::Semicolon)?;
       Ok(Rule::Charset(value.to_string()))
     },
+    "import" => {
+      Ok(Rule::Import(parse_import_rule(start, context)?))
+    },
     "namespace" => {
       let value = get_buffer(context.tokenizer, |tokenizer| {
         Ok(tokenizer.peek(1)? != Token::Semicolon)
@@ -162,6 +363,38 @@ fn parse_at_rule<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<Rule, ParseErr
   }
 }
 
+// @import url("./theme.css"); or @import "./theme.css";
+fn parse_import_rule<'a, 'b>(start: usize, context: &mut Context<'a, 'b>) -> Result<ImportRule, ParseError> {
+  let uri = match context.tokenizer.peek(1)? {
+    Token::SingleQuote | Token::DoubleQuote => parse_string(context)?.to_string(),
+    Token::Word("url") => {
+      context.tokenizer.next()?; // eat url
+      context.tokenizer.next_expect(Token::ParenOpen)?;
+      eat_superfluous(context)?;
+      let uri = match context.tokenizer.peek(1)? {
+        Token::SingleQuote | Token::DoubleQuote => parse_string(context)?,
+        _ => get_buffer(context.tokenizer, |tokenizer| {
+          let token = tokenizer.peek(1)?;
+          Ok(token != Token::ParenClose && token != Token::Whitespace)
+        })?
+      }.to_string();
+      eat_superfluous(context)?;
+      context.tokenizer.next_expect(Token::ParenClose)?;
+      uri
+    },
+    _ => {
+      return Err(ParseError::unexpected_token(context.tokenizer.pos));
+    }
+  };
+  eat_superfluous(context)?;
+  context.tokenizer.next_expect(Token::Semicolon)?;
+
+  Ok(ImportRule {
+    uri,
+    location: context.tokenizer.location(start, context.tokenizer.pos)
+  })
+}
+
 fn parse_condition_rule<'a, 'b>(name: String, context: &mut Context<'a, 'b>) -> Result<ConditionRule, ParseError> {
   let condition_text = get_buffer(context.tokenizer, |tokenizer| {
     Ok(tokenizer.peek(1)? != Token::CurlyOpen)
@@ -173,7 +406,8 @@ fn parse_condition_rule<'a, 'b>(name: String, context: &mut Context<'a, 'b>) ->
   let mut rules = vec![];
 
   while context.tokenizer.peek(1)? != Token::CurlyClose {
-    rules.push(parse_style_rule2(context)?);
+    rules.push(parse_rule(context)?);
+    eat_superfluous(context)?;
   }
   context.tokenizer.next_expect(Token::CurlyClose)?;
 
@@ -193,7 +427,22 @@ fn parse_font_face_rule<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<FontFac
 
 
 fn parse_keyframes_rule<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<KeyframesRule, ParseError> {
-  let name = parse_selector_name(context)?.to_string();
+
+  // @keyframes :global(fade) { } opts out of scoping
+  let global = context.tokenizer.peek(1)? == Token::Colon;
+  let name = if global {
+    context.tokenizer.next()?; // eat :
+    let start = context.tokenizer.pos;
+    if parse_selector_name(context)? != "global" {
+      return Err(ParseError::unexpected_token(start));
+    }
+    context.tokenizer.next_expect(Token::ParenOpen)?;
+    let name = parse_selector_name(context)?.to_string();
+    context.tokenizer.next_expect(Token::ParenClose)?;
+    name
+  } else {
+    parse_selector_name(context)?.to_string()
+  };
   
   let mut rules = vec![];
   eat_superfluous(context)?;
@@ -207,6 +456,7 @@ fn parse_keyframes_rule<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<Keyfram
 
   Ok(KeyframesRule {
     name,
+    global,
     rules,
   })
 }
@@ -226,6 +476,8 @@ fn parse_selector<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<Selector, Par
 
 // select, select, select
 fn parse_group_selector<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<Selector, ParseError> {
+  eat_superfluous(context)?;
+  let start = context.tokenizer.pos;
   let mut selectors: Vec<Selector> = vec![];
   loop {
     eat_superfluous(context)?;
@@ -240,14 +492,17 @@ fn parse_group_selector<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<Selecto
   if selectors.len() == 1 {
     Ok(selectors.pop().unwrap())
   } else {
+    let end = selectors.last().unwrap().get_location().end;
     Ok(Selector::Group(GroupSelector {
-      selectors
+      selectors,
+      location: context.tokenizer.location(start, end)
     }))
   }
 }
 
 // // parent > child
 fn parse_pair_selector<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<Selector, ParseError> {
+  let start = context.tokenizer.pos;
   let selector = parse_psuedo_element_selector(context)?;
   eat_superfluous(context)?;
   let delim = context.tokenizer.peek(1)?;
@@ -257,6 +512,7 @@ fn parse_pair_selector<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<Selector
       eat_superfluous(context)?;
       let child = parse_pair_selector(context)?;
       Ok(Selector::Child(ChildSelector {
+        location: context.tokenizer.location(start, child.get_location().end),
         parent: Box::new(selector),
         child: Box::new(child)
       }))
@@ -266,6 +522,7 @@ fn parse_pair_selector<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<Selector
       eat_superfluous(context)?;
       let sibling = parse_pair_selector(context)?;
       Ok(Selector::Adjacent(AdjacentSelector {
+        location: context.tokenizer.location(start, sibling.get_location().end),
         selector: Box::new(selector),
         next_sibling_selector: Box::new(sibling)
       }))
@@ -276,6 +533,7 @@ fn parse_pair_selector<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<Selector
       eat_superfluous(context)?;
       let sibling = parse_pair_selector(context)?;
       Ok(Selector::Sibling(SiblingSelector {
+        location: context.tokenizer.location(start, sibling.get_location().end),
         selector: Box::new(selector),
         sibling_selector: Box::new(sibling)
       }))
@@ -288,6 +546,7 @@ fn parse_pair_selector<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<Selector
       let descendent_result = parse_pair_selector(context);
       if let Ok(descendent) = descendent_result {
         Ok(Selector::Descendent(DescendentSelector {
+          location: context.tokenizer.location(start, descendent.get_location().end),
           parent: Box::new(selector),
           descendent: Box::new(descendent)
         }))
@@ -319,13 +578,14 @@ fn parse_combo_selector<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<Selecto
     Ok(selectors.pop().unwrap())
   } else {
     Ok(Selector::Combo(ComboSelector {
-      selectors
+      selectors,
+      location: context.tokenizer.location(pos, context.tokenizer.pos)
     }))
   }
 }
 
 fn parse_psuedo_element_selector<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<Selector, ParseError> { 
-  
+  let start = context.tokenizer.pos;
   let mut colon_count = 1;
   
   let target: Option<Box<Selector>> = if context.tokenizer.peek(1)? != Token::Colon {
@@ -350,28 +610,54 @@ fn parse_psuedo_element_selector<'a, 'b>(context: &mut Context<'a, 'b>) -> Resul
   let name = parse_selector_name(context)?.to_string();
   let selector: Selector = if context.tokenizer.peek(1)? == Token::ParenOpen {
     context.tokenizer.next()?;
-    let selector = if name == "not" {
+    if name == "not" {
       let sel = parse_pair_selector(context)?;
-      Selector::Not(NotSelector { selector: Box::new(sel) })
+      context.tokenizer.next_expect(Token::ParenClose)?;
+      Selector::Not(NotSelector {
+        selector: Box::new(sel),
+        location: context.tokenizer.location(start, context.tokenizer.pos)
+      })
+    } else if name == "global" || name == "self" {
+      if target != None {
+        return Err(ParseError::unexpected(format!(":{}() can't be combined with other selectors", name), start, context.tokenizer.pos));
+      }
+      eat_superfluous(context)?;
+      let sel = Box::new(parse_group_selector(context)?);
+      eat_superfluous(context)?;
+      context.tokenizer.next_expect(Token::ParenClose)?;
+      let location = context.tokenizer.location(start, context.tokenizer.pos);
+      if name == "global" {
+        Selector::Global(GlobalSelector { selector: sel, location })
+      } else {
+        Selector::This(SelfSelector { selector: Some(sel), location })
+      }
     } else {
       let param = get_buffer(context.tokenizer, |tokenizer| {
         Ok(tokenizer.peek(1)? != Token::ParenClose)
       })?.to_string();
+      context.tokenizer.next_expect(Token::ParenClose)?;
 
       Selector::PseudoParamElement(PseudoParamElementSelector {
         target,
         name,
-        param
+        param,
+        location: context.tokenizer.location(start, context.tokenizer.pos)
       })
-    };
-
-    context.tokenizer.next_expect(Token::ParenClose)?;
-    selector
+    }
+  } else if name == "self" && colon_count == 1 {
+    if target != None {
+      return Err(ParseError::unexpected(":self can't be combined with other selectors".to_string(), start, context.tokenizer.pos));
+    }
+    Selector::This(SelfSelector {
+      selector: None,
+      location: context.tokenizer.location(start, context.tokenizer.pos)
+    })
   } else {
     Selector::PseudoElement(PseudoElementSelector {
       separator: ":".to_string().repeat(colon_count),
       target,
-      name
+      name,
+      location: context.tokenizer.location(start, context.tokenizer.pos)
     })
   };
 
@@ -384,27 +670,41 @@ fn parse_element_selector<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<Selec
   let selector: Selector = match token {
     Token::Star => {
       context.tokenizer.next()?; // eat *
-      Selector::AllSelector
+      Selector::AllSelector(AllSelector {
+        location: context.tokenizer.location(pos, context.tokenizer.pos)
+      })
     }
     Token::Dot => {
       context.tokenizer.next()?;
+      let class_name = parse_selector_name(context)?.to_string();
       Selector::Class(ClassSelector {
-        class_name: parse_selector_name(context)?.to_string()
+        class_name,
+        location: context.tokenizer.location(pos, context.tokenizer.pos)
       })
     }
     Token::Hash => {
       context.tokenizer.next()?;
+      let id = parse_selector_name(context)?.to_string();
       Selector::Id(IdSelector {
-        id: parse_selector_name(context)?.to_string()
+        id,
+        location: context.tokenizer.location(pos, context.tokenizer.pos)
       })
     }
     Token::SquareOpen => {
       context.tokenizer.next()?;
-      parse_attribute_selector(context)?
+      parse_attribute_selector(context, pos)?
+    }
+    Token::Byte(b'&') => {
+      context.tokenizer.next()?; // eat &
+      Selector::Nesting(NestingSelector {
+        location: context.tokenizer.location(pos, context.tokenizer.pos)
+      })
     }
     Token::Word(_) => {
+      let tag_name = parse_selector_name(context)?.to_string();
       Selector::Element(ElementSelector {
-        tag_name: parse_selector_name(context)?.to_string()
+        tag_name,
+        location: context.tokenizer.location(pos, context.tokenizer.pos)
       })
     }
     _ => {
@@ -414,7 +714,7 @@ fn parse_element_selector<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<Selec
   Ok(selector)
 }
 
-fn parse_attribute_selector<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<Selector, ParseError> {
+fn parse_attribute_selector<'a, 'b>(context: &mut Context<'a, 'b>, start: usize) -> Result<Selector, ParseError> {
   let name = parse_attribute_name(context)?.to_string();
   let mut value = None;
   if context.tokenizer.peek(1)? == Token::Equals {
@@ -426,7 +726,8 @@ fn parse_attribute_selector<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<Sel
 
   Ok(Selector::Attribute(AttributeSelector {
     name, 
-    value
+    value,
+    location: context.tokenizer.location(start, context.tokenizer.pos)
   }))
 }
 
@@ -508,13 +809,38 @@ fn parse_declarations<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<Vec<Decla
     if context.tokenizer.peek(1)? == Token::CurlyClose {
       break
     }
-    declarations.push(parse_declaration(context)?);
+    let start = context.tokenizer.pos;
+    match parse_declaration(context) {
+      Ok(declaration) => declarations.push(declaration),
+      Err(err) => {
+        context.record(err)?;
+        skip_declaration(context, start)?;
+      }
+    }
     eat_superfluous(context)?;
   }
 
   Ok(declarations)
 }
 
+// skips to the next declaration, or the end of the declaration block
+fn skip_declaration<'a, 'b>(context: &mut Context<'a, 'b>, start: usize) -> Result<(), ParseError> {
+  context.tokenizer.pos = start;
+  while !context.ended()? {
+    match context.tokenizer.peek(1)? {
+      Token::CurlyClose => break,
+      Token::Semicolon => {
+        context.tokenizer.next()?;
+        break;
+      },
+      _ => {
+        context.tokenizer.next()?;
+      }
+    }
+  }
+  Ok(())
+}
+
 fn eat_script_comments<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<(), ParseError> {
   eat_comments(context, Token::ScriptCommentOpen, Token::ScriptCommentClose)
 }
@@ -541,43 +867,252 @@ fn parse_declaration<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<Declaratio
   Ok(Declaration {
     name, 
     value,
-    location: Location {
-      start,
-      end
-    },
-    name_location: Location {
-      start,
-      end: name_end
-    },
-    value_location: Location {
-      start: value_start,
-      end: value_end
+    location: context.tokenizer.location(start, end),
+    name_location: context.tokenizer.location(start, name_end),
+    value_location: context.tokenizer.location(value_start, value_end)
+  })
+}
+
+// color: rgba(0, 0, 0, 0.5) !important;
+fn parse_declaration_value<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<DeclarationValue, ParseError> {
+  let start = context.tokenizer.pos;
+  let values = parse_values(context, false)?;
+  let mut important = false;
+  if context.tokenizer.peek(1)? == Token::Bang {
+    let start = context.tokenizer.pos;
+    context.tokenizer.next()?; // eat !
+    eat_superfluous(context)?;
+    if !parse_value_name(context)?.eq_ignore_ascii_case("important") {
+      return Err(ParseError::unexpected("Expected !important.".to_string(), start, context.tokenizer.pos));
     }
+    important = true;
+    eat_superfluous(context)?;
+  }
+
+  if !context.tokenizer.is_eof() {
+    match context.tokenizer.peek(1)? {
+      Token::Semicolon | Token::CurlyClose => {},
+      _ => {
+        return Err(ParseError::unexpected_token(context.tokenizer.pos));
+      }
+    }
+  }
+
+  // kept as written since re-stringifying values can change their meaning (e.g. progid:DX)
+  let raw = std::str::from_utf8(&context.tokenizer.source[start..context.tokenizer.pos]).unwrap().trim_end().to_string();
+
+  Ok(DeclarationValue {
+    values,
+    important,
+    raw
   })
 }
 
-fn parse_declaration_value<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<String, ParseError> { 
-  let mut buffer = String::new();
+// parses values until the end of the declaration, or the closing ) of a function
+fn parse_values<'a, 'b>(context: &mut Context<'a, 'b>, in_function: bool) -> Result<Vec<Value>, ParseError> {
+  let mut values = vec![];
+  eat_superfluous(context)?;
   while !context.tokenizer.is_eof() {
-    match context.tokenizer.peek(1)? {
-      Token::Semicolon | Token::CurlyClose => {
-        break;
-      },
-      Token::SingleQuote | Token::DoubleQuote => {
-        buffer.push_str(parse_string(context)?);
-      },
+    match context.tokenizer.curr_char()? {
+      b';' | b'}' | b'!' if !in_function => break,
+      b')' if in_function => break,
       _ => {
-        buffer.push(context.tokenizer.curr_char()? as char);
+        values.push(parse_value(context)?);
+      }
+    }
+    eat_superfluous(context)?;
+  }
+  Ok(values)
+}
+
+fn parse_value<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<Value, ParseError> {
+  let start = context.tokenizer.pos;
+  let c = context.tokenizer.curr_char()?;
+  let value = match c {
+    b',' => {
+      context.tokenizer.pos += 1;
+      Value::Comma(Comma {
+        location: context.tokenizer.location(start, context.tokenizer.pos)
+      })
+    },
+    b'"' | b'\'' => {
+      let value = parse_string(context)?.to_string();
+      Value::String(StringValue {
+        value,
+        location: context.tokenizer.location(start, context.tokenizer.pos)
+      })
+    },
+    b'#' => {
+      context.tokenizer.pos += 1;
+      let hex = parse_value_name(context)?.to_string();
+      Value::Color(Color {
+        hex,
+        location: context.tokenizer.location(start, context.tokenizer.pos)
+      })
+    },
+    b';' | b'}' | b')' | b'!' | b'(' => {
+      return Err(ParseError::unexpected_token(start));
+    },
+    _ => {
+      if starts_with_number(context) {
+        parse_number(context)?
+      } else if is_name_char(c) {
+        parse_keyword_or_function(context)?
+      } else {
         context.tokenizer.pos += 1;
+        Value::Operator(Operator {
+          value: (c as char).to_string(),
+          location: context.tokenizer.location(start, context.tokenizer.pos)
+        })
       }
+    }
+  };
+  Ok(value)
+}
+
+// 10, -10px, +.5em, 50%
+fn starts_with_number<'a, 'b>(context: &mut Context<'a, 'b>) -> bool {
+  let source = &context.tokenizer.source[context.tokenizer.pos..];
+  let offset = if source.len() > 0 && (source[0] == b'-' || source[0] == b'+') { 1 } else { 0 };
+  match (source.get(offset), source.get(offset + 1)) {
+    (Some(b'0'..=b'9'), _) => true,
+    (Some(b'.'), Some(b'0'..=b'9')) => true,
+    _ => false
+  }
+}
+
+fn parse_number<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<Value, ParseError> {
+  let start = context.tokenizer.pos;
+  let mut value = scan_value(context, |c, i| {
+    matches!(c, b'0'..=b'9' | b'.') || (i == 0 && (c == b'-' || c == b'+'))
+  }).to_string();
+
+  // 1e3, 1.5E-2
+  let source = &context.tokenizer.source[context.tokenizer.pos..];
+  let exponent_length = match source {
+    [b'e' | b'E', b'0'..=b'9', ..] => 1,
+    [b'e' | b'E', b'-' | b'+', b'0'..=b'9', ..] => 2,
+    _ => 0
+  };
+  if exponent_length > 0 {
+    context.tokenizer.pos += exponent_length;
+    value = format!("{}{}{}", value, std::str::from_utf8(&source[..exponent_length]).unwrap(), scan_value(context, |c, _| c.is_ascii_digit()));
+  }
+
+  let unit = if context.tokenizer.curr_char().ok() == Some(b'%') {
+    context.tokenizer.pos += 1;
+    "%"
+  } else {
+    scan_value(context, |c, _| c.is_ascii_alphabetic())
+  }.to_string();
+
+  Ok(Value::Number(Number {
+    value,
+    unit,
+    location: context.tokenizer.location(start, context.tokenizer.pos)
+  }))
+}
+
+fn parse_keyword_or_function<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<Value, ParseError> {
+  let start = context.tokenizer.pos;
+  let name = parse_value_name(context)?.to_string();
+
+  // U+0025-00FF
+  if (name == "U" || name == "u") && context.tokenizer.curr_char().ok() == Some(b'+') {
+    let range = scan_value(context, |c, _| c == b'+' || c == b'-' || c == b'?' || c.is_ascii_hexdigit());
+    return Ok(Value::Keyword(Keyword {
+      name: format!("{}{}", name, range),
+      location: context.tokenizer.location(start, context.tokenizer.pos)
+    }));
+  }
+
+  if context.tokenizer.curr_char().ok() != Some(b'(') {
+    return Ok(Value::Keyword(Keyword {
+      name,
+      location: context.tokenizer.location(start, context.tokenizer.pos)
+    }));
+  }
+
+  context.tokenizer.next()?; // eat (
+
+  if name.eq_ignore_ascii_case("url") {
+    eat_superfluous(context)?;
+    let url = match context.tokenizer.peek(1)? {
+      Token::SingleQuote | Token::DoubleQuote => parse_string(context)?,
+      _ => scan_value(context, |c, _| c != b')' && !c.is_ascii_whitespace())
+    }.to_string();
+    eat_superfluous(context)?;
+    context.tokenizer.next_expect(Token::ParenClose)?;
+    return Ok(Value::Url(UrlValue {
+      url,
+      location: context.tokenizer.location(start, context.tokenizer.pos)
+    }));
+  }
+
+  let mut arguments = parse_values(context, true)?;
+  context.tokenizer.next_expect(Token::ParenClose)?;
+  let location = context.tokenizer.location(start, context.tokenizer.pos);
+
+  if name.eq_ignore_ascii_case("var") {
+    let fallback = if arguments.len() > 2 {
+      if let Value::Comma(_) = &arguments[1] {
+        Some(arguments.split_off(2))
+      } else {
+        None
+      }
+    } else {
+      None
     };
+    return match arguments.as_slice() {
+      [Value::Keyword(keyword)] | [Value::Keyword(keyword), Value::Comma(_)] if keyword.name.starts_with("--") => Ok(Value::Var(VarValue {
+        name: keyword.name.to_string(),
+        fallback,
+        location
+      })),
+      _ => Err(ParseError::unexpected("Expected a custom property name.".to_string(), location.start, location.end))
+    };
+  }
+
+  Ok(Value::Function(FunctionValue {
+    name,
+    arguments,
+    location
+  }))
+}
+
+fn is_name_char(c: u8) -> bool {
+  c.is_ascii_alphanumeric() || c == b'-' || c == b'_' || c == b'\\' || c >= 0x80
+}
+
+// solid, -webkit-box, --color
+fn parse_value_name<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<&'a str, ParseError> {
+  let start = context.tokenizer.pos;
+  let mut escaped = false;
+  let name = scan_value(context, |c, _| {
+    let matched = escaped || is_name_char(c);
+    escaped = !escaped && c == b'\\';
+    matched
+  });
+  if name.len() == 0 {
+    return Err(ParseError::unexpected_token(start));
   }
-  Ok(buffer)
+  Ok(name)
+}
+
+fn scan_value<'a, 'b, TTest>(context: &mut Context<'a, 'b>, mut test: TTest) -> &'a str
+where TTest: FnMut(u8, usize) -> bool {
+  let start = context.tokenizer.pos;
+  let source: &'a [u8] = context.tokenizer.source;
+  while context.tokenizer.pos < source.len() && test(source[context.tokenizer.pos], context.tokenizer.pos - start) {
+    context.tokenizer.pos += 1;
+  }
+  std::str::from_utf8(&source[start..context.tokenizer.pos]).unwrap()
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::base::ast::{Location, LineIndex};
 
   #[test]
   fn can_smoke_parse_various_selectors() {
@@ -629,6 +1164,25 @@ mod tests {
     parse(source).unwrap();
   }
 
+  #[test]
+  fn includes_rule_and_selector_locations() {
+    let source = "a > .b, c { color: red; }  ";
+    let line_index = LineIndex::new(source);
+    let sheet = parse(source).unwrap();
+    if let Rule::Style(rule) = &sheet.rules[0] {
+      assert_eq!(rule.location, Location::new(0, 25).with_positions(&line_index));
+      if let Selector::Group(group) = &rule.selector {
+        assert_eq!(group.location, Location::new(0, 9).with_positions(&line_index));
+        assert_eq!(group.selectors[0].get_location(), &Location::new(0, 6).with_positions(&line_index));
+        assert_eq!(group.selectors[1].get_location(), &Location::new(8, 9).with_positions(&line_index));
+      } else {
+        panic!("Expected group selector");
+      }
+    } else {
+      panic!("Expected style rule");
+    }
+  }
+
   #[test]
   fn can_smoke_parse_various_at_rules() {
 
@@ -679,9 +1233,136 @@ mod tests {
   /// Error handling
   /// 
 
+  #[test]
+  fn can_parse_global_and_self_selectors() {
+    let sheet = parse(":global(body .a), :self > :global(.b), :self(.c) {}").unwrap();
+    if let Rule::Style(rule) = &sheet.rules[0] {
+      assert_eq!(rule.selector.to_string(), ":global(body .a), :self > :global(.b), :self(.c)");
+      if let Selector::Group(group) = &rule.selector {
+        assert_matches!(group.selectors[0], Selector::Global(_));
+        assert_matches!(group.selectors[2], Selector::This(SelfSelector { selector: Some(_), .. }));
+      }
+    }
+    let err = parse("div:global(.a) {}").unwrap_err();
+    assert_eq!((err.message.as_str(), err.location.start, err.location.end), (":global() can't be combined with other selectors", 0, 11));
+  }
+
+  #[test]
+  fn can_parse_imports() {
+    let sheet = parse("@import url(./a.css); @import url( \"b.css\" ); @import 'c.css';").unwrap();
+    let uris: Vec<&str> = get_imports(&sheet).into_iter().map(|import| import.uri.as_str()).collect();
+    assert_eq!(uris, vec!["./a.css", "b.css", "c.css"]);
+    assert_eq!(sheet.rules[0].to_string(), "@import url(\"./a.css\");\n");
+    if let Rule::Import(import) = &sheet.rules[1] {
+      assert_eq!((import.location.start, import.location.end), (22, 45));
+    }
+  }
+
+  #[test]
+  fn can_parse_nested_rules() {
+    let sheet = parse(".card { color: red; &:hover { color: blue; } .title, & > span { color: green; } @media print { display: none; &.a { color: black; } } }").unwrap();
+    if let Rule::Style(rule) = &sheet.rules[0] {
+      assert_eq!(rule.declarations.len(), 1);
+      assert_eq!(rule.children.len(), 3);
+      let selectors: Vec<String> = rule.children.iter().filter_map(|child| match child {
+        Rule::Style(child) => Some(child.selector.to_string()),
+        _ => None
+      }).collect();
+      assert_eq!(selectors, vec!["&:hover", ".title, & > span"]);
+      if let Rule::Media(ConditionRule { rules, .. }) = &rule.children[2] {
+        if let Rule::Style(nested) = &rules[0] {
+          assert_eq!(nested.selector.to_string(), "&");
+          assert_eq!(nested.declarations[0].name, "display");
+          assert_eq!(nested.children.len(), 1);
+        } else {
+          panic!("Expected & rule");
+        }
+      } else {
+        panic!("Expected nested media rule");
+      }
+    } else {
+      panic!("Expected style rule");
+    }
+  }
+
+  #[test]
+  fn can_parse_any_rule_in_condition_rules() {
+    let sheet = parse("@media screen { @keyframes a { to { color: red; } } @font-face { font-family: b; } @supports (display: grid) { .c { display: grid; } } .d { color: red; } }").unwrap();
+    if let Rule::Media(media) = &sheet.rules[0] {
+      let kinds: Vec<&str> = media.rules.iter().map(|rule| match rule {
+        Rule::Keyframes(_) => "keyframes",
+        Rule::FontFace(_) => "font-face",
+        Rule::Supports(_) => "supports",
+        Rule::Style(_) => "style",
+        _ => "other"
+      }).collect();
+      assert_eq!(kinds, vec!["keyframes", "font-face", "supports", "style"]);
+    } else {
+      panic!("Expected media rule");
+    }
+  }
+
+  #[test]
+  fn displays_an_error_for_nested_rules_that_cant_be_nested() {
+    let (sheet, errors) = parse_with_recovery(".a { @keyframes b { } color: red; }");
+    assert_eq!(errors.len(), 1);
+    assert_eq!(sheet.to_string(), ".a {\n  color:red;\n}\n");
+  }
+
+  #[test]
+  fn can_parse_declaration_values() {
+    let sheet = parse(".a { font: italic 12px/1.5 \"Helvetica Neue\", sans-serif; color: RGBA(0,0,0,.5) !important; background: url(data:image/png;base64,AA==) #FFF; width: calc(100% - var(--gap, -10px)); }").unwrap();
+    if let Rule::Style(rule) = &sheet.rules[0] {
+      let values: Vec<String> = rule.declarations.iter().map(|decl| decl.value.to_string()).collect();
+      assert_eq!(values, vec![
+        "italic 12px/1.5 \"Helvetica Neue\", sans-serif",
+        "RGBA(0,0,0,.5) !important",
+        "url(data:image/png;base64,AA==) #FFF",
+        "calc(100% - var(--gap, -10px))"
+      ]);
+      assert_matches!(&rule.declarations[0].value.values[1], Value::Number(Number { value, unit, .. }) if value == "12" && unit == "px");
+      assert_matches!(&rule.declarations[1].value.values[0], Value::Function(FunctionValue { arguments, .. }) if arguments.len() == 7);
+      assert_matches!(&rule.declarations[2].value.values[1], Value::Color(Color { hex, location }) if hex == "FFF" && (location.start, location.end) == (135, 139));
+      if let Value::Function(calc) = &rule.declarations[3].value.values[0] {
+        assert_matches!(&calc.arguments[2], Value::Var(VarValue { name, fallback: Some(_), .. }) if name == "--gap");
+      } else {
+        panic!("Expected calc()");
+      }
+    } else {
+      panic!("Expected style rule");
+    }
+  }
+
+  #[test]
+  fn can_parse_numbers_with_exponents() {
+    let sheet = parse(".a { width: 1e3px; height: -1.5E-2em; filter: progid:DXImageTransform.Microsoft.Alpha(Opacity=80); }").unwrap();
+    if let Rule::Style(rule) = &sheet.rules[0] {
+      assert_matches!(&rule.declarations[0].value.values[..], [Value::Number(Number { value, unit, .. })] if value == "1e3" && unit == "px");
+      assert_matches!(&rule.declarations[1].value.values[..], [Value::Number(Number { value, unit, .. })] if value == "-1.5E-2" && unit == "em");
+      assert_eq!(rule.declarations[2].value.to_string(), "progid:DXImageTransform.Microsoft.Alpha(Opacity=80)");
+    } else {
+      panic!("Expected style rule");
+    }
+  }
+
+  #[test]
+  fn displays_an_error_for_broken_declaration_values() {
+    let (sheet, errors) = parse_with_recovery(".a { color: red !bad; width: var(gap); height: rgb(0, 0; margin: 0; }");
+    assert_eq!(errors.len(), 3);
+    assert_eq!(sheet.to_string(), ".a {\n  margin:0;\n}\n");
+  }
+
   #[test]
   fn displays_an_error_for_unterminated_curly_bracket() {
-    assert_eq!(parse("div { "), Err(ParseError::unterminated("Unterminated bracket.".to_string(), 4, 6)));
+    assert_eq!(parse("div { "), Err(ParseError::unterminated("Unterminated bracket.".to_string(), 4, 6).with_positions(&LineIndex::new("div { "))));
+  }
+
+  #[test]
+  fn can_recover_from_broken_rules_and_declarations() {
+    let (sheet, errors) = parse_with_recovery("{ color: red; } div { color red; } span { color: green; } b { ");
+    assert_eq!(errors.len(), 3);
+    assert_eq!(sheet.rules.len(), 3);
+    assert_eq!(sheet.to_string(), "div {\n}\nspan {\n  color:green;\n}\nb {\n}\n");
   }
 
 }
@@ -1,46 +1,65 @@
 use super::super::ast;
 use super::virt;
 use crate::base::runtime::{RuntimeError};
+use crate::base::ast::{Location};
 use crate::pc::runtime::vfs::{VirtualFileSystem};
-use regex::Regex;
+use std::collections::HashSet;
 
 pub struct Context<'a> {
   scope: &'a str,
   vfs: &'a VirtualFileSystem,
   uri: &'a String,
+
+  // scoped @keyframes defined in any sheet evaluated under the same scope
+  keyframes_names: &'a HashSet<String>
 }
 
-pub fn evaluate<'a>(expr: &ast::Sheet, uri: &'a String, scope: &'a str, vfs: &'a VirtualFileSystem) -> Result<virt::CSSSheet, RuntimeError> {
-  let context = Context { scope, uri, vfs };
+pub fn evaluate<'a>(expr: &'a ast::Sheet, uri: &'a String, scope: &'a str, keyframes_names: &'a HashSet<String>, vfs: &'a VirtualFileSystem) -> Result<virt::CSSSheet, RuntimeError> {
+  let context = Context { scope, uri, vfs, keyframes_names };
   let mut rules = vec![];
   for rule in &expr.rules {
-    rules.push(evaluate_rule(&rule, &context)?);
+    rules.extend(evaluate_rule(&rule, &None, &context)?);
   }
   Ok(virt::CSSSheet {
     rules,
   })
 }
 
-fn evaluate_rule(rule: &ast::Rule, context: &Context) -> Result<virt::Rule, RuntimeError> {
-  match rule {
-    ast::Rule::Charset(charset) => Ok(virt::Rule::Charset(charset.to_string())),
-    ast::Rule::Namespace(namespace) => Ok(virt::Rule::Namespace(namespace.to_string())),
-    ast::Rule::FontFace(rule) => evaluate_font_family_rule(rule, context),
-    ast::Rule::Media(rule) => evaluate_media_rule(rule, context),
-    ast::Rule::Style(rule) => evaluate_style_rule(rule, context),
-    ast::Rule::Keyframes(rule) => evaluate_keyframes_rule(rule, context),
-    ast::Rule::Supports(rule) => evaluate_supports_rule(rule, context),
-    ast::Rule::Document(rule) => evaluate_document_rule(rule, context),
-    ast::Rule::Page(rule) => evaluate_page_rule(rule, context),
-  }
+pub fn collect_keyframes_names(sheet: &ast::Sheet, keyframes_names: &mut HashSet<String>) {
+  collect_rule_keyframes_names(&sheet.rules, keyframes_names);
 }
 
-pub fn evaluate_style_rules<'a>(rules: &Vec<ast::StyleRule>, context: &Context) -> Result<Vec<virt::StyleRule>, RuntimeError> {
-  let mut css_rules = vec![];
+// keyframes defined in condition rules are scoped too
+fn collect_rule_keyframes_names(rules: &Vec<ast::Rule>, keyframes_names: &mut HashSet<String>) {
   for rule in rules {
-    css_rules.push(evaluate_style_rule2(&rule, &context)?);
+    match rule {
+      ast::Rule::Keyframes(rule) if !rule.global => {
+        keyframes_names.insert(rule.name.to_string());
+      },
+      ast::Rule::Media(rule) | ast::Rule::Supports(rule) | ast::Rule::Page(rule) | ast::Rule::Document(rule) => {
+        collect_rule_keyframes_names(&rule.rules, keyframes_names);
+      },
+      _ => {}
+    }
   }
-  Ok(css_rules)
+}
+
+// parent_selectors are the scoped selectors of the style rule that a rule is nested in
+fn evaluate_rule(rule: &ast::Rule, parent_selectors: &Option<Vec<String>>, context: &Context) -> Result<Vec<virt::Rule>, RuntimeError> {
+  Ok(vec![match rule {
+    ast::Rule::Charset(charset) => virt::Rule::Charset(charset.to_string()),
+    ast::Rule::Namespace(namespace) => virt::Rule::Namespace(namespace.to_string()),
+    ast::Rule::FontFace(rule) => evaluate_font_family_rule(rule, context)?,
+    ast::Rule::Media(rule) => evaluate_media_rule(rule, parent_selectors, context)?,
+    ast::Rule::Style(rule) => return evaluate_style_rule(rule, parent_selectors, context),
+    ast::Rule::Keyframes(rule) => evaluate_keyframes_rule(rule, context)?,
+    ast::Rule::Supports(rule) => evaluate_supports_rule(rule, parent_selectors, context)?,
+    ast::Rule::Document(rule) => evaluate_document_rule(rule, parent_selectors, context)?,
+    ast::Rule::Page(rule) => evaluate_page_rule(rule, parent_selectors, context)?,
+
+    // imported sheets are inlined from the dependency graph
+    ast::Rule::Import(_) => return Ok(vec![])
+  }])
 }
 
 fn evaluate_font_family_rule(font_family: &ast::FontFaceRule, context: &Context) -> Result<virt::Rule, RuntimeError> {
@@ -49,27 +68,31 @@ fn evaluate_font_family_rule(font_family: &ast::FontFaceRule, context: &Context)
   }))
 }
 
-fn evaluate_media_rule(rule: &ast::ConditionRule, context: &Context) -> Result<virt::Rule, RuntimeError> {
-  Ok(virt::Rule::Media(evaluate_condition_rule(rule, context)?))
+fn evaluate_media_rule(rule: &ast::ConditionRule, parent_selectors: &Option<Vec<String>>, context: &Context) -> Result<virt::Rule, RuntimeError> {
+  Ok(virt::Rule::Media(evaluate_condition_rule(rule, parent_selectors, context)?))
 }
 
-fn evaluate_supports_rule(rule: &ast::ConditionRule, context: &Context) -> Result<virt::Rule, RuntimeError> {
-  Ok(virt::Rule::Supports(evaluate_condition_rule(rule, context)?))
+fn evaluate_supports_rule(rule: &ast::ConditionRule, parent_selectors: &Option<Vec<String>>, context: &Context) -> Result<virt::Rule, RuntimeError> {
+  Ok(virt::Rule::Supports(evaluate_condition_rule(rule, parent_selectors, context)?))
 
 }
-fn evaluate_page_rule(rule: &ast::ConditionRule, context: &Context) -> Result<virt::Rule, RuntimeError> {
-  Ok(virt::Rule::Page(evaluate_condition_rule(rule, context)?))
+fn evaluate_page_rule(rule: &ast::ConditionRule, parent_selectors: &Option<Vec<String>>, context: &Context) -> Result<virt::Rule, RuntimeError> {
+  Ok(virt::Rule::Page(evaluate_condition_rule(rule, parent_selectors, context)?))
 }
 
-fn evaluate_document_rule(rule: &ast::ConditionRule, context: &Context) -> Result<virt::Rule, RuntimeError> {
-  Ok(virt::Rule::Document(evaluate_condition_rule(rule, context)?))
+fn evaluate_document_rule(rule: &ast::ConditionRule, parent_selectors: &Option<Vec<String>>, context: &Context) -> Result<virt::Rule, RuntimeError> {
+  Ok(virt::Rule::Document(evaluate_condition_rule(rule, parent_selectors, context)?))
 }
 
-fn evaluate_condition_rule(rule: &ast::ConditionRule, context: &Context) -> Result<virt::ConditionRule, RuntimeError> {
+fn evaluate_condition_rule(rule: &ast::ConditionRule, parent_selectors: &Option<Vec<String>>, context: &Context) -> Result<virt::ConditionRule, RuntimeError> {
+  let mut rules = vec![];
+  for child in &rule.rules {
+    rules.extend(evaluate_rule(child, parent_selectors, context)?);
+  }
   Ok(virt::ConditionRule {
     name: rule.name.to_string(),
     condition_text: rule.condition_text.to_string(),
-    rules: evaluate_style_rules(&rule.rules, context)?
+    rules
   })
 }
 
@@ -82,11 +105,27 @@ fn evaluate_keyframes_rule(rule: &ast::KeyframesRule, context: &Context) -> Resu
   }
   
   Ok(virt::Rule::Keyframes(virt::KeyframesRule {
-    name: rule.name.to_string(),
+    name: if rule.global { rule.name.to_string() } else { get_scoped_keyframes_name(&rule.name, context) },
     rules,
   }))
 }
 
+// fade -> _5e6fa2a1_fade
+fn get_scoped_keyframes_name(name: &String, context: &Context) -> String {
+  format!("_{}_{}", context.scope, name)
+}
+
+// animation: fade 1s -> animation: _5e6fa2a1_fade 1s
+fn scope_animation_names(values: &Vec<ast::Value>, context: &Context, replacements: &mut Vec<(Location, String)>) {
+  for value in values {
+    if let ast::Value::Keyword(keyword) = value {
+      if context.keyframes_names.contains(&keyword.name) {
+        replacements.push((keyword.location.clone(), get_scoped_keyframes_name(&keyword.name, context)));
+      }
+    }
+  }
+}
+
 fn evaluate_keyframe_rule(rule: &ast::KeyframeRule, _context: &Context) -> Result<virt::KeyframeRule, RuntimeError> {
 
   let mut style = vec![];
@@ -111,17 +150,88 @@ fn evaluate_style_declarations(declarations: &Vec<ast::Declaration>, context: &C
   Ok(style)
 }
 
-fn evaluate_style_rule(expr: &ast::StyleRule, context: &Context) -> Result<virt::Rule, RuntimeError> {
-  Ok(virt::Rule::Style(evaluate_style_rule2(expr, context)?))
+// .a { color: red; &:hover { } .b { } } is flattened into .a { color: red; } .a:hover { } .a .b { }
+fn evaluate_style_rule(expr: &ast::StyleRule, parent_selectors: &Option<Vec<String>>, context: &Context) -> Result<Vec<virt::Rule>, RuntimeError> {
+  let selectors = get_selector_texts(&expr.selector, parent_selectors, context);
+  let mut rules = vec![];
+
+  // rules that only hold nested rules don't need to be emitted
+  if expr.children.len() == 0 || expr.declarations.len() > 0 {
+    rules.push(virt::Rule::Style(virt::StyleRule {
+      selector_text: selectors.join(", "),
+      style: evaluate_style_declarations(&expr.declarations, context)?
+    }));
+  }
+
+  let parent_selectors = Some(selectors);
+  for child in &expr.children {
+    rules.extend(evaluate_rule(child, &parent_selectors, context)?);
+  }
+
+  Ok(rules)
 }
 
-fn evaluate_style_rule2(expr: &ast::StyleRule, context: &Context) -> Result<virt::StyleRule, RuntimeError> {
-  let style = evaluate_style_declarations(&expr.declarations, context)?;
-  let selector_text = stringify_element_selector(&expr.selector, context);
-  Ok(virt::StyleRule {
-    selector_text,
-    style
-  })
+// Stands in for & in scoped selector text until it's resolved against the parent's
+// selectors. CSS source can't contain NUL, so this won't collide with attribute values.
+const NESTING_PLACEHOLDER: &str = "\u{0}";
+
+// Scopes each selector in a group, then resolves & against the parent's selectors. Nested
+// selectors without & are descendents of the parent.
+fn get_selector_texts(selector: &ast::Selector, parent_selectors: &Option<Vec<String>>, context: &Context) -> Vec<String> {
+  let selectors = match selector {
+    ast::Selector::Group(group) => group.selectors.iter().collect(),
+    _ => vec![selector]
+  };
+  let mut texts = vec![];
+  for selector in selectors {
+    let text = stringify_element_selector(selector, context);
+    match parent_selectors {
+      Some(parents) => if contains_nesting_selector(selector) {
+        texts.extend(resolve_nesting_selectors(&text, parents));
+      } else {
+        for parent in parents {
+          texts.push(format!("{} {}", parent, text));
+        }
+      },
+      None => texts.push(text.replace(NESTING_PLACEHOLDER, "&"))
+    }
+  }
+  texts
+}
+
+// each & may match any of the parent's selectors, so & + & with .a, .b parents
+// is expanded into .a + .a, .a + .b, .b + .a, .b + .b
+fn resolve_nesting_selectors(text: &String, parents: &Vec<String>) -> Vec<String> {
+  let mut texts = vec![String::new()];
+  for (i, segment) in text.split(NESTING_PLACEHOLDER).enumerate() {
+    if i > 0 {
+      texts = texts.iter().flat_map(|text| {
+        parents.iter().map(move |parent| format!("{}{}", text, parent))
+      }).collect();
+    }
+    for text in &mut texts {
+      text.push_str(segment);
+    }
+  }
+  texts
+}
+
+fn contains_nesting_selector(selector: &ast::Selector) -> bool {
+  match selector {
+    ast::Selector::Nesting(_) => true,
+    ast::Selector::Group(selector) => selector.selectors.iter().any(contains_nesting_selector),
+    ast::Selector::Combo(selector) => selector.selectors.iter().any(contains_nesting_selector),
+    ast::Selector::Descendent(selector) => contains_nesting_selector(&selector.parent) || contains_nesting_selector(&selector.descendent),
+    ast::Selector::Child(selector) => contains_nesting_selector(&selector.parent) || contains_nesting_selector(&selector.child),
+    ast::Selector::Adjacent(selector) => contains_nesting_selector(&selector.selector) || contains_nesting_selector(&selector.next_sibling_selector),
+    ast::Selector::Sibling(selector) => contains_nesting_selector(&selector.selector) || contains_nesting_selector(&selector.sibling_selector),
+    ast::Selector::PseudoElement(ast::PseudoElementSelector { target: Some(target), .. }) |
+    ast::Selector::PseudoParamElement(ast::PseudoParamElementSelector { target: Some(target), .. }) => contains_nesting_selector(target),
+    ast::Selector::Not(selector) => contains_nesting_selector(&selector.selector),
+    ast::Selector::Global(selector) => contains_nesting_selector(&selector.selector),
+    ast::Selector::This(ast::SelfSelector { selector: Some(selector), .. }) => contains_nesting_selector(selector),
+    _ => false
+  }
 }
 
 fn stringify_optional_selector(selector: &Option<Box<ast::Selector>>, context: &Context) -> String {
@@ -137,7 +247,7 @@ fn stringify_element_selector(selector: &ast::Selector, context: &Context) -> St
   let scope_selector = format!("[data-pc-{}]", context.scope);
 
   let scoped_selector_text = match selector {
-    ast::Selector::AllSelector => format!("{}", scope_selector),
+    ast::Selector::AllSelector(_) => format!("{}", scope_selector),
     ast::Selector::Class(selector) => format!(".{}{}", selector.class_name, scope_selector),
     ast::Selector::Id(selector) => format!("#{}{}", selector.id, scope_selector),
     ast::Selector::Element(selector) => format!("{}{}", selector.tag_name, scope_selector),
@@ -153,6 +263,17 @@ fn stringify_element_selector(selector: &ast::Selector, context: &Context) -> St
     },
     ast::Selector::Attribute(selector) => format!("{}{}", selector.to_string(), scope_selector),
     ast::Selector::Not(selector) => format!("{}:not({})", scope_selector, stringify_element_selector(&selector.selector, context)),
+    ast::Selector::Global(selector) => selector.selector.to_string(),
+
+    // resolved against the parent rule's selector
+    ast::Selector::Nesting(_) => NESTING_PLACEHOLDER.to_string(),
+    ast::Selector::This(selector) => {
+      let self_selector = format!("[data-pc-self-{}]", context.scope);
+      match &selector.selector {
+        Some(selector) => format!("{}{}", self_selector, stringify_element_selector(selector, context)),
+        None => self_selector
+      }
+    },
     ast::Selector::Descendent(selector) => format!("{} {}", stringify_element_selector(&selector.parent, context), stringify_element_selector(&selector.descendent, context)),
     ast::Selector::Child(selector) => format!("{} > {}", stringify_element_selector(&selector.parent, context), stringify_element_selector(&selector.child, context)),
     ast::Selector::Adjacent(selector) => format!("{} + {}", stringify_element_selector(&selector.selector, context), stringify_element_selector(&selector.next_sibling_selector, context)),
@@ -164,8 +285,9 @@ fn stringify_element_selector(selector: &ast::Selector, context: &Context) -> St
       text.join(", ")
     },
     ast::Selector::Combo(selector) => {
-      let text: Vec<String> = (&selector.selectors).into_iter().map(|child| {
-        child.to_string()
+      let text: Vec<String> = (&selector.selectors).into_iter().map(|child| match child {
+        ast::Selector::Nesting(_) => NESTING_PLACEHOLDER.to_string(),
+        _ => child.to_string()
       }).collect();
       format!("{}{}", text.join(""), scope_selector)
     }
@@ -176,30 +298,55 @@ fn stringify_element_selector(selector: &ast::Selector, context: &Context) -> St
 
 fn evaluate_style_declaration<'a>(expr: &'a ast::Declaration, context: &Context) -> Result<virt::CSSStyleProperty, RuntimeError> {
 
-  let mut value = expr.value.to_string();
+  let mut replacements = vec![];
+  resolve_urls(&expr.value.values, context, &mut replacements);
 
-  let url_re = Regex::new(r#"url\((?:['"]?)(.*?)(?:['"]?)\)"#).unwrap();
+  // -webkit-animation, animation-name, ...
+  if expr.name.ends_with("animation") || expr.name.ends_with("animation-name") {
+    scope_animation_names(&expr.value.values, context, &mut replacements);
+  }
 
-  // a bit crude, but works for now. Need to eventually consider HTTP paths
-  if url_re.is_match(value.clone().as_str()) {
-    let protocol_re = Regex::new(r"^\w+:").unwrap();
-    for caps in url_re.captures_iter(value.to_string().as_str()) {
-      let url_fn = caps.get(0).unwrap().as_str();
+  Ok(virt::CSSStyleProperty {
+    name: expr.name.to_string(),
+    value: replace_value_parts(&expr.value.raw, expr.value_location.start, replacements),
+  })
+}
 
-      let relative_path = caps.get(1).unwrap().as_str();
+// rewrites parts of the source text so that everything else is kept as written
+fn replace_value_parts(raw: &String, offset: usize, mut replacements: Vec<(Location, String)>) -> String {
+  replacements.sort_by_key(|(location, _)| location.start);
+  let mut buffer = String::new();
+  let mut pos = 0;
+  for (location, text) in replacements {
+    buffer.push_str(&raw[pos..location.start - offset]);
+    buffer.push_str(&text);
+    pos = location.end - offset;
+  }
+  buffer.push_str(&raw[pos..]);
+  buffer
+}
 
-      // skil values with protocol
-      if protocol_re.is_match(relative_path) {
-        continue;
-      }
-      let full_path = context.vfs.resolve(context.uri, &relative_path.to_string());
+// url(./image.png) -> url("file:///path/to/image.png"). Need to eventually consider HTTP paths
+fn resolve_urls(values: &Vec<ast::Value>, context: &Context, replacements: &mut Vec<(Location, String)>) {
+  for value in values {
+    match value {
+      ast::Value::Url(url) => {
 
-      value = url_re.replace(url_fn, format!("url({})", full_path).as_str()).to_string();
+        // skip values with protocol
+        if !has_protocol(&url.url) {
+          replacements.push((url.location.clone(), format!("url(\"{}\")", context.vfs.resolve(context.uri, &url.url))));
+        }
+      },
+      ast::Value::Function(function) => resolve_urls(&function.arguments, context, replacements),
+      ast::Value::Var(ast::VarValue { fallback: Some(fallback), .. }) => resolve_urls(fallback, context, replacements),
+      _ => {}
     }
   }
+}
 
-  Ok(virt::CSSStyleProperty {
-    name: expr.name.to_string(),
-    value,
-  })
-}
\ No newline at end of file
+fn has_protocol(url: &String) -> bool {
+  match url.find(':') {
+    Some(index) => index > 0 && url[..index].chars().all(|c| c.is_ascii_alphanumeric() || c == '_'),
+    None => false
+  }
+}
@@ -1,26 +1,56 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use crate::pc::{runtime};
 use crate::pc::parser::{parse as parse_pc};
 use crate::css::parser::{parse as parse_css};
 use crate::base::parser::{ParseError};
 use crate::pc::ast as pc_ast;
-use crate::pc::runtime::graph::{DependencyGraph, DependencyContent, GraphError};
+use crate::pc::lint::{lint, LintOptions, LintDiagnostic};
+use crate::pc::infer::{infer_parts, PartInference};
+use crate::pc::definition::{generate_definition};
+use crate::pc::runtime::graph::{DependencyGraph, DependencyContent, GraphError, GraphErrorInfo, get_json_syntax_error};
 use crate::pc::runtime::vfs::{VirtualFileSystem, FileExistsFn, FileReaderFn, FileResolverFn};
-use crate::pc::runtime::evaluator::{evaluate_document_styles, evaluate as evaluate_pc};
-use crate::css::runtime::evaluator::{evaluate as evaluate_css};
+use crate::pc::runtime::evaluator::{evaluate_document_styles, get_document_keyframes_names, evaluate as evaluate_pc, evaluate_preview as evaluate_pc_preview};
+use crate::pc::runtime::diff::{diff as diff_pc};
+use crate::pc::runtime::mutation::{Mutation};
+use crate::pc::runtime::html::{render_html, HtmlOptions};
+use crate::css::runtime::evaluator::{evaluate as evaluate_css, collect_keyframes_names};
 use crate::js::runtime::virt as js_virt;
+use crate::js::runtime::helpers::{Helpers, HelperFn};
 use crate::base::runtime::{RuntimeError};
+use crate::base::ast::{Location};
 use serde::{Serialize};
 use crate::css::runtime::virt as css_vrt;
 use crate::base::utils::{get_document_style_scope};
 use ::futures::executor::block_on;
 
+const PC_CONFIG_FILE_NAME: &str = "pcconfig.json";
+
 #[derive(Debug, PartialEq, Serialize)]
 pub struct EvaluatedEvent {
   pub uri: String,
+
+  // set for named previews, which are evaluated separately
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub preview: Option<String>,
   pub node: Option<runtime::virt::Node>
 }
 
+#[derive(Debug, PartialEq, Serialize)]
+pub struct DiffedEvent {
+  pub uri: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub preview: Option<String>,
+  pub mutations: Vec<Mutation>
+}
+
+// <preview name="mobile" width="375" height="667">
+#[derive(Debug, PartialEq, Serialize)]
+pub struct PreviewInfo {
+  pub name: Option<String>,
+  pub width: Option<String>,
+  pub height: Option<String>
+}
+
 #[derive(Debug, PartialEq, Serialize)]
 pub struct NodeParsedEvent {
   pub uri: String,
@@ -40,6 +70,7 @@ pub enum EngineError {
 #[serde(tag = "kind")]
 pub enum EngineEvent {
   Evaluated(EvaluatedEvent),
+  Diffed(DiffedEvent),
   NodeParsed(NodeParsedEvent),
   Error(EngineError)
 }
@@ -56,7 +87,9 @@ async fn evaluate_content_styles(content: &String, uri: &String, vfs: &VirtualFi
     })
     .and_then(|css_ast| {
       let scope = get_document_style_scope(uri);
-      evaluate_css(&css_ast, uri, &scope, vfs)
+      let mut keyframes_names = HashSet::new();
+      collect_keyframes_names(&css_ast, &mut keyframes_names);
+      evaluate_css(&css_ast, uri, &scope, &keyframes_names, vfs)
       .map_err(|err| {
         EngineError::Runtime(err)
       })
@@ -67,7 +100,7 @@ async fn evaluate_content_styles(content: &String, uri: &String, vfs: &VirtualFi
       EngineError::Parser(err)
     })
     .and_then(|node_ast| {
-      evaluate_document_styles(&node_ast, uri, vfs)
+      evaluate_document_styles(&node_ast, uri, &get_document_keyframes_names(&node_ast), vfs)
       .map_err(|err| {
         EngineError::Runtime(err)
       })
@@ -80,8 +113,12 @@ type EngineEventListener = dyn Fn(&EngineEvent);
 pub struct Engine {
   listeners: Vec<Box<EngineEventListener>>,
   pub vfs: VirtualFileSystem,
+  pub helpers: Helpers,
   pub dependency_graph: DependencyGraph,
-  pub load_options: HashMap<String, EvalOptions>
+  pub load_options: HashMap<String, EvalOptions>,
+
+  // keyed by URI & preview name
+  pub rendered: HashMap<(String, Option<String>), runtime::virt::Node>
 }
 
 impl Engine {
@@ -89,21 +126,36 @@ impl Engine {
     Engine {
       listeners: vec![],
       vfs: VirtualFileSystem::new(read_file, file_exists, resolve_file),
+      helpers: Helpers::new(),
       dependency_graph: DependencyGraph::new(),
-      load_options: HashMap::new()
+      load_options: HashMap::new(),
+      rendered: HashMap::new()
     }
   }
   
+  // helpers can be called from templates, e.g: {format(price)} or {price | format}
+  pub fn register_helper(&mut self, name: &str, helper: Box<HelperFn>) {
+    self.helpers.register(name, helper);
+  }
+
   pub async fn load(&mut self, uri: &String, part: Option<String>) -> Result<(), GraphError> {
     self.load_options.insert(uri.to_string(), EvalOptions {
       part
     });
 
+    // ensure that the entire tree is sent over
+    self.rendered.retain(|(rendered_uri, _), _| rendered_uri != uri);
+
     self
     .reload(uri)
     .await
   }
 
+  pub fn unload(&mut self, uri: &String) {
+    self.load_options.remove(uri);
+    self.rendered.retain(|(rendered_uri, _), _| rendered_uri != uri);
+  }
+
   pub fn add_listener(&mut self, listener: Box<EngineEventListener>) {
     self.listeners.push(listener);
   }
@@ -138,6 +190,13 @@ impl Engine {
           if let Some(event) = event_option {
             self.dispatch(event);
           }
+
+          for error in &dep.syntax_errors {
+            self.dispatch(EngineEvent::Error(EngineError::Graph(GraphError {
+              uri: dep.uri.to_string(),
+              info: GraphErrorInfo::Syntax(error.clone())
+            })));
+          }
         }
 
         self.evaluate(uri);
@@ -168,6 +227,136 @@ impl Engine {
     evaluate_content_styles(content, uri, &self.vfs).await
   }
 
+  // options default to the ones in the nearest pcconfig.json
+  pub async fn lint_file(&mut self, uri: &String, options_option: Option<LintOptions>) -> Result<Vec<LintDiagnostic>, GraphError> {
+    let options = match options_option {
+      Some(options) => options,
+      None => self.find_lint_options(uri).await?
+    };
+    self.dependency_graph.load_dependency(uri, &mut self.vfs).await?;
+    Ok(lint(uri, &self.dependency_graph, &options))
+  }
+
+  async fn find_lint_options(&mut self, uri: &String) -> Result<LintOptions, GraphError> {
+    let mut dir = uri.as_str();
+    while let Some(index) = dir.rfind('/') {
+      dir = &dir[..index];
+      let config_uri = format!("{}/{}", dir, PC_CONFIG_FILE_NAME);
+
+      // reloaded since the config may have changed since the last lint
+      if let Ok(source) = self.vfs.reload(&config_uri).await {
+        return LintOptions::from_project_config(source).map_err(|err| GraphError {
+          uri: config_uri.to_string(),
+          info: GraphErrorInfo::Syntax(get_json_syntax_error(source, &err))
+        });
+      }
+
+      // file:///
+      if dir.ends_with('/') {
+        break;
+      }
+    }
+    Ok(LintOptions::default())
+  }
+
+  pub async fn infer_file_parts(&mut self, uri: &String) -> Result<Vec<PartInference>, GraphError> {
+    self.dependency_graph.load_dependency(uri, &mut self.vfs).await?;
+    Ok(match &self.dependency_graph.dependencies.get(uri).unwrap().content {
+      DependencyContent::Node(node) => infer_parts(node),
+      _ => vec![]
+    })
+  }
+
+  // Returns None for files that don't compile to components (CSS)
+  pub async fn generate_file_definition(&mut self, uri: &String) -> Result<Option<String>, GraphError> {
+    self.dependency_graph.load_dependency(uri, &mut self.vfs).await?;
+    Ok(match &self.dependency_graph.dependencies.get(uri).unwrap().content {
+      DependencyContent::Node(node) => Some(generate_definition(node)),
+      _ => None
+    })
+  }
+
+  // Evaluates a part with props supplied by the host, e.g: fixture data
+  pub async fn evaluate_part(&mut self, uri: &String, part: &String, props: &serde_json::Value) -> Result<Option<runtime::virt::Node>, EngineError> {
+    self.dependency_graph.load_dependency(uri, &mut self.vfs).await.map_err(EngineError::Graph)?;
+
+    let node = match &self.dependency_graph.dependencies.get(uri).unwrap().content {
+      DependencyContent::Node(node) => node,
+      _ => return Err(EngineError::Runtime(RuntimeError::new("Incorrect file type".to_string(), uri, &Location::start_of_file())))
+    };
+
+    if !pc_ast::get_part_ids(node).contains(&part) {
+      return Err(EngineError::Runtime(RuntimeError::new(format!("Part \"{}\" doesn't exist.", part), uri, &Location::start_of_file())));
+    }
+
+    if !props.is_object() {
+      return Err(EngineError::Runtime(RuntimeError::new("Props must be an object.".to_string(), uri, node.get_location())));
+    }
+
+    evaluate_pc(
+      uri,
+      &self.dependency_graph,
+      &self.vfs,
+      &self.helpers,
+      &js_virt::JsValue::from_json(props),
+      Some(part.to_string())
+    ).map_err(EngineError::Runtime)
+  }
+
+  pub async fn get_file_previews(&mut self, uri: &String) -> Result<Vec<PreviewInfo>, GraphError> {
+    self.dependency_graph.load_dependency(uri, &mut self.vfs).await?;
+    Ok(match &self.dependency_graph.dependencies.get(uri).unwrap().content {
+      DependencyContent::Node(node) => pc_ast::get_previews(node).into_iter().map(|preview| {
+        PreviewInfo {
+          name: pc_ast::get_attribute_value("name", preview).cloned(),
+          width: pc_ast::get_attribute_value("width", preview).cloned(),
+          height: pc_ast::get_attribute_value("height", preview).cloned()
+        }
+      }).collect(),
+      _ => vec![]
+    })
+  }
+
+  pub async fn evaluate_preview(&mut self, uri: &String, name: &String) -> Result<Option<runtime::virt::Node>, EngineError> {
+    self.dependency_graph.load_dependency(uri, &mut self.vfs).await.map_err(EngineError::Graph)?;
+
+    let node = match &self.dependency_graph.dependencies.get(uri).unwrap().content {
+      DependencyContent::Node(node) => node,
+      _ => return Err(EngineError::Runtime(RuntimeError::new("Incorrect file type".to_string(), uri, &Location::start_of_file())))
+    };
+
+    if !get_preview_names(node).contains(name) {
+      return Err(EngineError::Runtime(RuntimeError::new(format!("Preview \"{}\" doesn't exist.", name), uri, &Location::start_of_file())));
+    }
+
+    evaluate_pc_preview(
+      uri,
+      &self.dependency_graph,
+      &self.vfs,
+      &self.helpers,
+      &js_virt::JsValue::JsObject(js_virt::JsObject::new()),
+      name
+    ).map_err(EngineError::Runtime)
+  }
+
+  // Renders the file as a standalone HTML page. Returns None for files that don't render anything.
+  pub async fn render_file_html(&mut self, uri: &String, options: &HtmlOptions) -> Result<Option<String>, EngineError> {
+    self.dependency_graph.load_dependency(uri, &mut self.vfs).await.map_err(EngineError::Graph)?;
+    if let DependencyContent::Node(_) = &self.dependency_graph.dependencies.get(uri).unwrap().content {
+      let node_option = evaluate_pc(
+        uri,
+        &self.dependency_graph,
+        &self.vfs,
+        &self.helpers,
+        &js_virt::JsValue::JsObject(js_virt::JsObject::new()),
+        None
+      ).map_err(EngineError::Runtime)?;
+      Ok(node_option.map(|node| render_html(&node, options)))
+    } else {
+      Ok(None)
+    }
+  }
+
   pub async fn update_virtual_file_content(&mut self, uri: &String, content: &String) -> Result<(), GraphError> {
     self.vfs.update(uri, content).await;
     self.reload(uri).await?;
@@ -186,39 +375,89 @@ impl Engine {
   fn evaluate(&mut self, uri: &String) {
     let dependency = self.dependency_graph.dependencies.get(uri).unwrap();
 
-    let event_option = match &dependency.content {
-      DependencyContent::Node(node) => {
-        let node_result = evaluate_pc(
-          uri, 
-          &self.dependency_graph, 
-          &self.vfs,
-          &js_virt::JsValue::JsObject(js_virt::JsObject::new()),
-          self.load_options.get(uri).and_then(|options| {
-            options.part.clone()
-          })
-        );
-
-        match node_result {
-          Ok(node) => Some(EngineEvent::Evaluated(EvaluatedEvent {
+    let preview_names = match &dependency.content {
+      DependencyContent::Node(node) => get_preview_names(node),
+      _ => return
+    };
+
+    let part_option = self.load_options.get(uri).and_then(|options| {
+      options.part.clone()
+    });
+
+    let data = js_virt::JsValue::JsObject(js_virt::JsObject::new());
+    let node_result = evaluate_pc(
+      uri, 
+      &self.dependency_graph, 
+      &self.vfs,
+      &self.helpers,
+      &data,
+      part_option.clone()
+    );
+    self.dispatch_rendered(uri, None, node_result);
+
+    // named previews are only shown along with the entire document
+    if part_option != None {
+      return;
+    }
+
+    self.rendered.retain(|(rendered_uri, preview), _| {
+      rendered_uri != uri || preview.as_ref().map(|name| preview_names.contains(name)).unwrap_or(true)
+    });
+
+    for name in preview_names {
+      let node_result = evaluate_pc_preview(
+        uri,
+        &self.dependency_graph,
+        &self.vfs,
+        &self.helpers,
+        &data,
+        &name
+      );
+      self.dispatch_rendered(uri, Some(name), node_result);
+    }
+  }
+
+  fn dispatch_rendered(&mut self, uri: &String, preview: Option<String>, node_result: Result<Option<runtime::virt::Node>, RuntimeError>) {
+    match node_result {
+      Ok(node_option) => {
+        let key = (uri.to_string(), preview.clone());
+        let prev_node_option = match &node_option {
+          Some(node) => self.rendered.insert(key, node.clone()),
+          None => self.rendered.remove(&key)
+        };
+
+        // the full tree is only sent on the first render
+        if let (Some(prev_node), Some(node)) = (&prev_node_option, &node_option) {
+          self.dispatch(EngineEvent::Diffed(DiffedEvent {
+            uri: uri.clone(),
+            preview,
+            mutations: diff_pc(prev_node, node)
+          }));
+        } else {
+          self.dispatch(EngineEvent::Evaluated(EvaluatedEvent {
             uri: uri.clone(),
-            node,
-          })),
-          Err(err) => Some(EngineEvent::Error(EngineError::Runtime(err)))
+            preview,
+            node: node_option,
+          }));
         }
       },
-      _ => None
+      Err(err) => self.dispatch(EngineEvent::Error(EngineError::Runtime(err)))
     };
-
-    if let Some(event) = event_option {
-      self.dispatch(event);
-    }
   }
 }
 
+fn get_preview_names(node: &pc_ast::Node) -> Vec<String> {
+  pc_ast::get_previews(node).into_iter().filter_map(|preview| {
+    pc_ast::get_attribute_value("name", preview).cloned()
+  }).collect()
+}
+
 
 #[cfg(test)]
 mod tests {
   use super::*;
+  use std::rc::Rc;
+  use std::cell::RefCell;
 
   #[test]
   fn can_smoke_parse_various_nodes() {
@@ -235,4 +474,183 @@ mod tests {
     // panic!("maybay");
   
   }
-}
\ No newline at end of file
+
+  #[test]
+  fn can_evaluate_parts_with_props() {
+    let mut engine = Engine::new(
+      Box::new(|_| "<part id=\"item\"><li>{label}: {tags.length}</li></part><preview><item label=\"a\" tags={[]} /></preview>".to_string()),
+      Box::new(|_| true),
+      Box::new(|_,_| "".to_string())
+    );
+
+    let uri = "file.pc".to_string();
+    let node = block_on(engine.evaluate_part(&uri, &"item".to_string(), &serde_json::json!({ "label": "b", "tags": ["c", "d"] }))).unwrap().unwrap();
+    assert!(node.to_string().ends_with(" b :  2</li>"), "{}", node);
+
+    let result = block_on(engine.evaluate_part(&uri, &"nothing".to_string(), &serde_json::json!({})));
+    assert_eq!(result, Err(EngineError::Runtime(RuntimeError::new("Part \"nothing\" doesn't exist.".to_string(), &uri, &Location::start_of_file()))));
+  }
+
+  #[test]
+  fn reevaluates_previews_when_fixtures_change() {
+    let mut engine = Engine::new(
+      Box::new(|uri| if uri.ends_with(".json") {
+        "{\"label\": \"a\"}".to_string()
+      } else {
+        "<import id=\"fixtures\" src=\"./button.fixtures.json\" /><preview><button>{fixtures.label}</button></preview>".to_string()
+      }),
+      Box::new(|_| true),
+      Box::new(|_, uri| uri.replace("./", ""))
+    );
+
+    let uri = "button.pc".to_string();
+    block_on(engine.load(&uri, None)).unwrap();
+    assert!(engine.rendered.get(&(uri.clone(), None)).unwrap().to_string().ends_with(" a</button>"), "{}", engine.rendered.get(&(uri.clone(), None)).unwrap());
+
+    block_on(engine.update_virtual_file_content(&"button.fixtures.json".to_string(), &"{\"label\": \"b\"}".to_string())).unwrap();
+    assert!(engine.rendered.get(&(uri.clone(), None)).unwrap().to_string().ends_with(" b</button>"), "{}", engine.rendered.get(&(uri.clone(), None)).unwrap());
+  }
+
+  #[test]
+  fn evaluates_each_named_preview() {
+    let mut engine = Engine::new(
+      Box::new(|_| "<part id=\"item\"><li>{label}</li></part><preview name=\"mobile\" width=\"375\"><item label=\"a\" /></preview><preview name=\"desktop\" width=\"1440\" height=\"900\"><item label=\"b\" /></preview>".to_string()),
+      Box::new(|_| true),
+      Box::new(|_,_| "".to_string())
+    );
+
+    let events = Rc::new(RefCell::new(vec![]));
+    let listener_events = events.clone();
+    engine.add_listener(Box::new(move |event| {
+      if let EngineEvent::Evaluated(event) = event {
+        listener_events.borrow_mut().push((event.preview.clone(), event.node.as_ref().unwrap().to_string()));
+      }
+    }));
+
+    let uri = "file.pc".to_string();
+    let previews = block_on(engine.get_file_previews(&uri)).unwrap();
+    assert_eq!(previews, vec![
+      PreviewInfo { name: Some("mobile".to_string()), width: Some("375".to_string()), height: None },
+      PreviewInfo { name: Some("desktop".to_string()), width: Some("1440".to_string()), height: Some("900".to_string()) }
+    ]);
+
+    block_on(engine.load(&uri, None)).unwrap();
+    let events = events.borrow();
+    assert_eq!(events.iter().map(|(preview, _)| preview.clone()).collect::<Vec<Option<String>>>(), vec![None, Some("mobile".to_string()), Some("desktop".to_string())]);
+    assert!(events[0].1.ends_with(" a</li>"), "{}", events[0].1);
+    assert!(events[1].1.ends_with(" a</li>"), "{}", events[1].1);
+    assert!(events[2].1.ends_with(" b</li>"), "{}", events[2].1);
+
+    let node = block_on(engine.evaluate_preview(&uri, &"mobile".to_string())).unwrap().unwrap();
+    assert!(node.to_string().ends_with(" a</li>"), "{}", node);
+
+    let result = block_on(engine.evaluate_preview(&uri, &"tablet".to_string()));
+    assert_eq!(result, Err(EngineError::Runtime(RuntimeError::new("Preview \"tablet\" doesn't exist.".to_string(), &uri, &Location::start_of_file()))));
+  }
+
+  #[test]
+  fn points_missing_sheet_imports_at_the_import_rule() {
+    let mut engine = Engine::new(
+      Box::new(|_| "div { } @import url(missing.css);".to_string()),
+      Box::new(|uri| uri == "theme.css"),
+      Box::new(|_, uri| uri.to_string())
+    );
+
+    let result = block_on(engine.load(&"theme.css".to_string(), None));
+    match result {
+      Err(GraphError { info: GraphErrorInfo::IncludeNotFound(err), uri }) => {
+        assert_eq!(uri, "theme.css");
+        assert_eq!((err.location.start, err.location.end), (8, 33));
+      },
+      _ => panic!("{:?}", result)
+    };
+  }
+
+  #[test]
+  fn emits_diffs_after_the_initial_evaluation() {
+    let mut engine = Engine::new(
+      Box::new(|_| "<div>a</div>".to_string()),
+      Box::new(|_| true), 
+      Box::new(|_,_| "".to_string())
+    );
+
+    let events = Rc::new(RefCell::new(vec![]));
+    let listener_events = events.clone();
+    engine.add_listener(Box::new(move |event| {
+      listener_events.borrow_mut().push(match event {
+        EngineEvent::Evaluated(_) => "Evaluated",
+        EngineEvent::Diffed(_) => "Diffed",
+        _ => "Other"
+      });
+    }));
+
+    let uri = "file.pc".to_string();
+    block_on(engine.load(&uri, None)).unwrap();
+    block_on(engine.update_virtual_file_content(&uri, &"<div>b</div>".to_string())).unwrap();
+
+    // loading again sends the entire tree
+    block_on(engine.load(&uri, None)).unwrap();
+    assert_eq!(events.borrow().iter().filter(|kind| **kind != "Other").collect::<Vec<&&str>>(), vec![&"Evaluated", &"Diffed", &"Evaluated"]);
+  }
+
+  #[test]
+  fn dispatches_syntax_errors_and_still_evaluates() {
+    let mut engine = Engine::new(
+      Box::new(|_| "<div>a<span>b</div>".to_string()),
+      Box::new(|_| true), 
+      Box::new(|_,_| "".to_string())
+    );
+
+    let events = Rc::new(RefCell::new(vec![]));
+    let listener_events = events.clone();
+    engine.add_listener(Box::new(move |event| {
+      listener_events.borrow_mut().push(match event {
+        EngineEvent::Evaluated(_) => "Evaluated",
+        EngineEvent::Error(EngineError::Graph(GraphError { info: GraphErrorInfo::Syntax(_), .. })) => "Syntax",
+        _ => "Other"
+      });
+    }));
+
+    let uri = "file.pc".to_string();
+    block_on(engine.load(&uri, None)).unwrap();
+    assert_eq!(events.borrow().iter().filter(|kind| **kind != "Other").collect::<Vec<&&str>>(), vec![&"Syntax", &"Evaluated"]);
+  }
+
+  #[test]
+  fn lints_with_the_nearest_project_config() {
+    let mut engine = Engine::new(
+      Box::new(|uri| if uri.ends_with(".json") {
+        "{ \"lint\": { \"rules\": { \"no-nested-styles\": \"off\" } } }".to_string()
+      } else {
+        "<span /><div><style></style></div>".to_string()
+      }),
+      Box::new(|uri| uri != "file:///a/b/pcconfig.json" && (uri.ends_with(".pc") || uri == "file:///a/pcconfig.json")),
+      Box::new(|_,_| "".to_string())
+    );
+
+    let uri = "file:///a/b/file.pc".to_string();
+    assert_eq!(block_on(engine.lint_file(&uri, None)).unwrap(), vec![]);
+    assert_eq!(block_on(engine.lint_file(&uri, Some(LintOptions::default()))).unwrap().len(), 1);
+  }
+
+  #[test]
+  fn reports_malformed_project_configs() {
+    let mut engine = Engine::new(
+      Box::new(|uri| if uri.ends_with(".json") {
+        "{ \"lint\": { \"rules\": { \"no-nested-styles\": \"of\" } } }".to_string()
+      } else {
+        "<div />".to_string()
+      }),
+      Box::new(|_| true),
+      Box::new(|_,_| "".to_string())
+    );
+
+    match block_on(engine.lint_file(&"file:///a/file.pc".to_string(), None)) {
+      Err(GraphError { uri, info: GraphErrorInfo::Syntax(err) }) => {
+        assert_eq!(uri, "file:///a/pcconfig.json");
+        assert!(err.message.starts_with("Unable to parse JSON: unknown variant `of`"), "{}", err.message);
+      },
+      result => panic!("expected a syntax error, got {:?}", result)
+    };
+  }
+}
@@ -1,51 +1,91 @@
 use std::fmt;
 use serde::{Serialize};
 use crate::pc::ast as pc_ast;
+use crate::base::ast::{Location};
 
 #[derive(Debug, PartialEq, Serialize, Clone)]
 #[serde(tag = "jsKind")]
 pub enum Statement {
   Reference(Reference),
+  Member(MemberExpression),
+  Call(CallExpression),
   Boolean(Boolean),
   String(Str),
+  Template(TemplateLiteral),
   Number(Number),
   Array(Array),
   Object(Object),
-  Node(Box<pc_ast::Node>)
+  Node(Box<pc_ast::Node>),
+  Group(Group),
+  Unary(UnaryExpression),
+  Binary(BinaryExpression),
+  Conditional(ConditionalExpression)
 }
 
 #[derive(Debug, PartialEq, Serialize, Clone)]
 pub struct Str {
-  pub value: String
+  pub value: String,
+  pub location: Location
 }
 
 #[derive(Debug, PartialEq, Serialize, Clone)]
 pub struct Number {
-  pub value: String
+  pub value: String,
+  pub location: Location
 }
 
 #[derive(Debug, PartialEq, Serialize, Clone)]
 pub struct Boolean {
-  pub value: bool
+  pub value: bool,
+  pub location: Location
+}
+
+impl Statement {
+  pub fn get_location(&self) -> &Location {
+    match self {
+      Statement::Reference(value) => &value.location,
+      Statement::Member(value) => &value.location,
+      Statement::Call(value) => &value.location,
+      Statement::Node(value) => value.get_location(),
+      Statement::String(value) => &value.location,
+      Statement::Template(value) => &value.location,
+      Statement::Boolean(value) => &value.location,
+      Statement::Number(value) => &value.location,
+      Statement::Array(value) => &value.location,
+      Statement::Object(value) => &value.location,
+      Statement::Group(value) => &value.location,
+      Statement::Unary(value) => &value.location,
+      Statement::Binary(value) => &value.location,
+      Statement::Conditional(value) => &value.location
+    }
+  }
 }
 
 impl fmt::Display for Statement {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
     match self {
       Statement::Reference(reference) => write!(f, "{}", reference.to_string()),
+      Statement::Member(value) => value.fmt(f),
+      Statement::Call(value) => value.fmt(f),
       Statement::Node(node) => write!(f, "{}", node.to_string()),
       Statement::String(value) => write!(f, "\"{}\"", value.value.to_string()),
+      Statement::Template(value) => value.fmt(f),
       Statement::Boolean(value) => write!(f, "{}", value.value.to_string()),
       Statement::Number(value) => write!(f, "{}", value.value.to_string()),
       Statement::Array(value) => write!(f, "{}", value.to_string()),
-      Statement::Object(value) => value.fmt(f)
+      Statement::Object(value) => value.fmt(f),
+      Statement::Group(value) => value.fmt(f),
+      Statement::Unary(value) => value.fmt(f),
+      Statement::Binary(value) => value.fmt(f),
+      Statement::Conditional(value) => value.fmt(f)
     }
   }
 }
 
 #[derive(Debug, PartialEq, Serialize, Clone)]
 pub struct Array {
-  pub values: Vec<Statement>
+  pub values: Vec<Statement>,
+  pub location: Location
 }
 
 impl fmt::Display for Array {
@@ -60,7 +100,8 @@ impl fmt::Display for Array {
 
 #[derive(Debug, PartialEq, Serialize, Clone)]
 pub struct Object {
-  pub properties: Vec<Property>
+  pub properties: Vec<Property>,
+  pub location: Location
 }
 
 impl fmt::Display for Object {
@@ -87,11 +128,214 @@ impl fmt::Display for Property {
 
 #[derive(Debug, PartialEq, Serialize, Clone)]
 pub struct Reference {
-  pub path: Vec<String>
+  pub path: Vec<String>,
+  pub location: Location
 }
 
 impl fmt::Display for Reference {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-    write!(f, "{}", self.path.join(".Statement"))
+    write!(f, "{}", self.path.join("."))
+  }
+}
+
+// items[0], labels[key], (a || b).c
+#[derive(Debug, PartialEq, Serialize, Clone)]
+pub struct MemberExpression {
+  pub object: Box<Statement>,
+  pub property: Box<Statement>,
+
+  // false for .property, in which case property is a string
+  pub computed: bool,
+  pub location: Location
+}
+
+impl fmt::Display for MemberExpression {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match (self.computed, self.property.as_ref()) {
+      (false, Statement::String(property)) => write!(f, "{}.{}", self.object.to_string(), property.value),
+      _ => write!(f, "{}[{}]", self.object.to_string(), self.property.to_string())
+    }
+  }
+}
+
+// format(price), or price | format
+#[derive(Debug, PartialEq, Serialize, Clone)]
+pub struct CallExpression {
+  pub callee: String,
+  pub arguments: Vec<Statement>,
+  pub location: Location
+}
+
+impl fmt::Display for CallExpression {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let buffer: Vec<String> = self.arguments.iter().map(|argument| {
+      argument.to_string()
+    }).collect();
+    write!(f, "{}({})", self.callee, buffer.join(", "))
+  }
+}
+
+// `btn ${variant}`. Literal parts are strings.
+#[derive(Debug, PartialEq, Serialize, Clone)]
+pub struct TemplateLiteral {
+  pub parts: Vec<Statement>,
+  pub location: Location
+}
+
+impl fmt::Display for TemplateLiteral {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "`")?;
+    for part in &self.parts {
+      match part {
+        Statement::String(value) => write!(f, "{}", value.value.replace("\\", "\\\\").replace("`", "\\`").replace("${", "\\${"))?,
+        _ => write!(f, "${{{}}}", part.to_string())?
+      };
+    }
+    write!(f, "`")
+  }
+}
+
+// (a || b)
+#[derive(Debug, PartialEq, Serialize, Clone)]
+pub struct Group {
+  pub expression: Box<Statement>,
+  pub location: Location
+}
+
+impl fmt::Display for Group {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "({})", self.expression.to_string())
+  }
+}
+
+#[derive(Debug, PartialEq, Serialize, Clone)]
+pub enum UnaryOperator {
+  #[serde(rename = "!")]
+  Not,
+  #[serde(rename = "-")]
+  Negative
+}
+
+impl fmt::Display for UnaryOperator {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", match self {
+      UnaryOperator::Not => "!",
+      UnaryOperator::Negative => "-"
+    })
+  }
+}
+
+// !disabled
+#[derive(Debug, PartialEq, Serialize, Clone)]
+pub struct UnaryExpression {
+  pub operator: UnaryOperator,
+  pub expression: Box<Statement>,
+  pub location: Location
+}
+
+impl fmt::Display for UnaryExpression {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}{}", self.operator, self.expression.to_string())
+  }
+}
+
+#[derive(Debug, PartialEq, Serialize, Clone, Copy)]
+pub enum BinaryOperator {
+  #[serde(rename = "||")]
+  Or,
+  #[serde(rename = "&&")]
+  And,
+  #[serde(rename = "==")]
+  Equal,
+  #[serde(rename = "!=")]
+  NotEqual,
+  #[serde(rename = "===")]
+  StrictEqual,
+  #[serde(rename = "!==")]
+  StrictNotEqual,
+  #[serde(rename = "<")]
+  LessThan,
+  #[serde(rename = "<=")]
+  LessThanOrEqual,
+  #[serde(rename = ">")]
+  GreaterThan,
+  #[serde(rename = ">=")]
+  GreaterThanOrEqual,
+  #[serde(rename = "+")]
+  Add,
+  #[serde(rename = "-")]
+  Subtract,
+  #[serde(rename = "*")]
+  Multiply,
+  #[serde(rename = "/")]
+  Divide,
+  #[serde(rename = "%")]
+  Modulo
+}
+
+impl BinaryOperator {
+
+  // higher binds tighter
+  pub fn precedence(&self) -> u8 {
+    match self {
+      BinaryOperator::Or => 1,
+      BinaryOperator::And => 2,
+      BinaryOperator::Equal | BinaryOperator::NotEqual | BinaryOperator::StrictEqual | BinaryOperator::StrictNotEqual => 3,
+      BinaryOperator::LessThan | BinaryOperator::LessThanOrEqual | BinaryOperator::GreaterThan | BinaryOperator::GreaterThanOrEqual => 4,
+      BinaryOperator::Add | BinaryOperator::Subtract => 5,
+      BinaryOperator::Multiply | BinaryOperator::Divide | BinaryOperator::Modulo => 6
+    }
+  }
+}
+
+impl fmt::Display for BinaryOperator {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", match self {
+      BinaryOperator::Or => "||",
+      BinaryOperator::And => "&&",
+      BinaryOperator::Equal => "==",
+      BinaryOperator::NotEqual => "!=",
+      BinaryOperator::StrictEqual => "===",
+      BinaryOperator::StrictNotEqual => "!==",
+      BinaryOperator::LessThan => "<",
+      BinaryOperator::LessThanOrEqual => "<=",
+      BinaryOperator::GreaterThan => ">",
+      BinaryOperator::GreaterThanOrEqual => ">=",
+      BinaryOperator::Add => "+",
+      BinaryOperator::Subtract => "-",
+      BinaryOperator::Multiply => "*",
+      BinaryOperator::Divide => "/",
+      BinaryOperator::Modulo => "%"
+    })
+  }
+}
+
+// kind == 'primary'
+#[derive(Debug, PartialEq, Serialize, Clone)]
+pub struct BinaryExpression {
+  pub left: Box<Statement>,
+  pub operator: BinaryOperator,
+  pub right: Box<Statement>,
+  pub location: Location
+}
+
+impl fmt::Display for BinaryExpression {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{} {} {}", self.left.to_string(), self.operator, self.right.to_string())
+  }
+}
+
+// active ? 'on' : 'off'
+#[derive(Debug, PartialEq, Serialize, Clone)]
+pub struct ConditionalExpression {
+  pub test: Box<Statement>,
+  pub consequent: Box<Statement>,
+  pub alternate: Box<Statement>,
+  pub location: Location
+}
+
+impl fmt::Display for ConditionalExpression {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{} ? {} : {}", self.test.to_string(), self.consequent.to_string(), self.alternate.to_string())
   }
 }
@@ -2,11 +2,10 @@ use crate::base::tokenizer::{Tokenizer, Token};
 use crate::base::parser::{get_buffer, ParseError};
 use super::ast;
 use crate::pc::parser::parse_tag;
-use std::collections::{HashMap};
 
 pub fn _parse<'a>(source: &'a str) -> Result<ast::Statement, ParseError> {
   let mut tokenizer = Tokenizer::new(source);
-  parse_with_tokenizer(&mut tokenizer, |_token| { true })
+  parse_with_tokenizer(&mut tokenizer, |_token| { true }).map_err(|err| err.with_positions(&tokenizer.line_index))
 }
 
 pub fn parse_with_tokenizer<'a, FUntil>(tokenizer: &mut Tokenizer<'a>, _until: FUntil) -> Result<ast::Statement, ParseError> where
@@ -15,20 +14,214 @@ FUntil: Fn(Token) -> bool {
 }
 
 fn parse_statement<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<ast::Statement, ParseError> {
+  tokenizer.eat_whitespace();
+  let result = parse_pipe(tokenizer);
+  tokenizer.eat_whitespace();
+  result
+}
+
+// value | helper, value | helper(arg) -> helper(value, arg)
+fn parse_pipe<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<ast::Statement, ParseError> {
+  let mut value = parse_conditional(tokenizer)?;
+
+  // || is consumed as an operator before getting here
+  while !tokenizer.is_eof() && tokenizer.peek(1)? == Token::Byte(b'|') {
+    tokenizer.next()?; // eat |
+    tokenizer.eat_whitespace();
+    let callee = tokenizer.next_word_value()?;
+    let mut arguments = vec![value];
+    if !tokenizer.is_eof() && tokenizer.peek(1)? == Token::ParenOpen {
+      arguments.extend(parse_arguments(tokenizer)?);
+    }
+    value = ast::Statement::Call(ast::CallExpression {
+      location: tokenizer.location(arguments[0].get_location().start, tokenizer.pos),
+      callee,
+      arguments
+    });
+    tokenizer.eat_whitespace();
+  }
+
+  Ok(value)
+}
+
+// (a, b, c)
+fn parse_arguments<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<Vec<ast::Statement>, ParseError> {
+  tokenizer.next_expect(Token::ParenOpen)?;
+  let mut arguments = vec![];
+
+  while !tokenizer.is_eof() && tokenizer.peek_eat_whitespace(1)? != Token::ParenClose {
+    arguments.push(parse_statement(tokenizer)?);
+    if tokenizer.peek(1)? == Token::ParenClose {
+      break;
+    }
+    tokenizer.next_expect(Token::Comma)?;
+  }
+
+  tokenizer.eat_whitespace();
+  tokenizer.next_expect(Token::ParenClose)?;
+  Ok(arguments)
+}
+
+fn parse_conditional<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<ast::Statement, ParseError> {
+  let test = parse_binary(tokenizer, 0)?;
+
+  if tokenizer.is_eof() || tokenizer.peek(1)? != Token::Byte(b'?') {
+    return Ok(test);
+  }
+
+  tokenizer.next()?; // eat ?
+  let consequent = parse_statement(tokenizer)?;
+  tokenizer.next_expect(Token::Colon)?;
+  tokenizer.eat_whitespace();
+  let alternate = parse_conditional(tokenizer)?;
+
+  Ok(ast::Statement::Conditional(ast::ConditionalExpression {
+    location: tokenizer.location(test.get_location().start, alternate.get_location().end),
+    test: Box::new(test),
+    consequent: Box::new(consequent),
+    alternate: Box::new(alternate)
+  }))
+}
+
+// precedence climbing: operators that bind tighter than min_precedence are folded into
+// the right-hand side.
+fn parse_binary<'a>(tokenizer: &mut Tokenizer<'a>, min_precedence: u8) -> Result<ast::Statement, ParseError> {
+  let mut left = parse_unary(tokenizer)?;
+
+  while let Some((operator, end)) = peek_binary_operator(tokenizer)? {
+    let precedence = operator.precedence();
+    if precedence <= min_precedence {
+      break;
+    }
+    tokenizer.pos = end;
+    tokenizer.eat_whitespace();
+    let right = parse_binary(tokenizer, precedence)?;
+    left = ast::Statement::Binary(ast::BinaryExpression {
+      location: tokenizer.location(left.get_location().start, right.get_location().end),
+      left: Box::new(left),
+      operator,
+      right: Box::new(right)
+    });
+  }
+
+  Ok(left)
+}
+
+// returns the operator & the position after it without moving the tokenizer
+fn peek_binary_operator<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<Option<(ast::BinaryOperator, usize)>, ParseError> {
+  let start = tokenizer.pos;
+  if tokenizer.is_eof() {
+    return Ok(None);
+  }
+
+  let operator = match tokenizer.next()? {
+    Token::Byte(b'|') if tokenizer.peek(1) == Ok(Token::Byte(b'|')) => Some(ast::BinaryOperator::Or),
+    Token::Byte(b'&') if tokenizer.peek(1) == Ok(Token::Byte(b'&')) => Some(ast::BinaryOperator::And),
+    Token::DoubleEquals => Some(ast::BinaryOperator::Equal),
+    Token::TrippleEquals => Some(ast::BinaryOperator::StrictEqual),
+    Token::Bang => match tokenizer.peek(1) {
+      Ok(Token::Equals) => Some(ast::BinaryOperator::NotEqual),
+      Ok(Token::DoubleEquals) => Some(ast::BinaryOperator::StrictNotEqual),
+      _ => None
+    },
+    Token::LessThan if tokenizer.peek(1) == Ok(Token::Equals) => Some(ast::BinaryOperator::LessThanOrEqual),
+    Token::LessThan => Some(ast::BinaryOperator::LessThan),
+    Token::GreaterThan if tokenizer.peek(1) == Ok(Token::Equals) => Some(ast::BinaryOperator::GreaterThanOrEqual),
+    Token::GreaterThan => Some(ast::BinaryOperator::GreaterThan),
+    Token::Plus => Some(ast::BinaryOperator::Add),
+    Token::Minus => Some(ast::BinaryOperator::Subtract),
+    Token::Star => Some(ast::BinaryOperator::Multiply),
+    Token::Backslash => Some(ast::BinaryOperator::Divide),
+    Token::Byte(b'%') => Some(ast::BinaryOperator::Modulo),
+    _ => None
+  };
 
+  // second half of ||, &&, !=, !==, <=, >=
+  if matches!(operator, Some(ast::BinaryOperator::Or) | Some(ast::BinaryOperator::And) | Some(ast::BinaryOperator::NotEqual) | Some(ast::BinaryOperator::StrictNotEqual) | Some(ast::BinaryOperator::LessThanOrEqual) | Some(ast::BinaryOperator::GreaterThanOrEqual)) {
+    tokenizer.next()?;
+  }
+
+  let end = tokenizer.pos;
+  tokenizer.pos = start;
+  Ok(operator.map(|operator| (operator, end)))
+}
+
+fn parse_unary<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<ast::Statement, ParseError> {
+  let start = tokenizer.pos;
+  let operator = match tokenizer.peek(1)? {
+    Token::Bang => ast::UnaryOperator::Not,
+    Token::Minus => ast::UnaryOperator::Negative,
+    _ => {
+      let primary = parse_primary(tokenizer)?;
+      let result = parse_member(tokenizer, primary);
+      tokenizer.eat_whitespace();
+      return result;
+    }
+  };
+
+  tokenizer.next()?; // eat operator
   tokenizer.eat_whitespace();
-  let result = match tokenizer.peek(1)? {
-    //  Token::Minus  - TODO - negate. Need to consider refs too
+  let expression = parse_unary(tokenizer)?;
+
+  Ok(ast::Statement::Unary(ast::UnaryExpression {
+    operator,
+    location: tokenizer.location(start, expression.get_location().end),
+    expression: Box::new(expression)
+  }))
+}
+
+// items[0].label
+fn parse_member<'a>(tokenizer: &mut Tokenizer<'a>, object: ast::Statement) -> Result<ast::Statement, ParseError> {
+  let mut object = object;
+  while !tokenizer.is_eof() {
+    let (property, computed) = match tokenizer.peek(1)? {
+      Token::SquareOpen => {
+        tokenizer.next()?; // eat [
+        let property = parse_statement(tokenizer)?;
+        tokenizer.next_expect(Token::SquareClose)?;
+        (property, true)
+      },
+      Token::Dot => {
+        tokenizer.next()?; // eat .
+        let start = tokenizer.pos;
+        let value = tokenizer.next_word_value()?;
+        (ast::Statement::String(ast::Str { value, location: tokenizer.location(start, tokenizer.pos) }), false)
+      },
+      _ => break
+    };
+
+    object = ast::Statement::Member(ast::MemberExpression {
+      location: tokenizer.location(object.get_location().start, tokenizer.pos),
+      object: Box::new(object),
+      property: Box::new(property),
+      computed
+    });
+  }
+  Ok(object)
+}
+
+fn parse_primary<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<ast::Statement, ParseError> {
+  match tokenizer.peek(1)? {
     Token::LessThan => parse_node(tokenizer),
     Token::DoubleQuote | Token::SingleQuote => parse_string(tokenizer),
+    Token::Byte(b'`') => parse_template(tokenizer),
     Token::Number(_)=> parse_number(tokenizer),
     Token::SquareOpen => parse_array(tokenizer),
     Token::CurlyOpen => parse_object(tokenizer),
+    Token::ParenOpen => parse_group(tokenizer),
     _ => parse_word(tokenizer)
-  };
+  }
+}
 
-  tokenizer.eat_whitespace();
-  result
+fn parse_group<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<ast::Statement, ParseError> {
+  let start = tokenizer.pos;
+  tokenizer.next_expect(Token::ParenOpen)?;
+  let expression = parse_statement(tokenizer)?;
+  tokenizer.next_expect(Token::ParenClose)?;
+  Ok(ast::Statement::Group(ast::Group {
+    expression: Box::new(expression),
+    location: tokenizer.location(start, tokenizer.pos)
+  }))
 }
 
 fn parse_node<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<ast::Statement, ParseError> {
@@ -36,6 +229,7 @@ fn parse_node<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<ast::Statement, Parse
 }
 
 fn parse_number<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<ast::Statement, ParseError> {
+  let start = tokenizer.pos;
   let mut buffer = String::new();
 
   while !tokenizer.is_eof() {
@@ -50,21 +244,71 @@ fn parse_number<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<ast::Statement, Par
     }
   };
 
-  Ok(ast::Statement::Number(ast::Number { value: buffer }))
+  Ok(ast::Statement::Number(ast::Number { value: buffer, location: tokenizer.location(start, tokenizer.pos) }))
 }
 
 fn parse_string<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<ast::Statement, ParseError> {
-  let start = tokenizer.next()?;
+  let start = tokenizer.pos;
+  let quote = tokenizer.next()?;
   let value = get_buffer(tokenizer, |tokenizer| {
-    Ok(tokenizer.peek(1)? != start)
+    Ok(tokenizer.peek(1)? != quote)
   })?.to_string();
-  tokenizer.next_expect(start)?;
-  Ok(ast::Statement::String(ast::Str { value }))
+  tokenizer.next_expect(quote)?;
+  Ok(ast::Statement::String(ast::Str { value, location: tokenizer.location(start, tokenizer.pos) }))
+}
+
+fn parse_template<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<ast::Statement, ParseError> {
+  let start = tokenizer.pos;
+  tokenizer.next_expect(Token::Byte(b'`'))?;
+  let mut parts = vec![];
+  let mut buffer: Vec<u8> = vec![];
+  let mut buffer_start = tokenizer.pos;
+
+  loop {
+    let c = tokenizer.curr_char()
+    .or(Err(ParseError::unterminated("Unterminated template literal.".to_string(), start, tokenizer.pos)))?;
+
+    if c == b'`' || (c == b'$' && tokenizer.source.get(tokenizer.pos + 1) == Some(&b'{')) {
+      if buffer.len() > 0 {
+        parts.push(ast::Statement::String(ast::Str {
+          value: String::from_utf8_lossy(&buffer).to_string(),
+          location: tokenizer.location(buffer_start, tokenizer.pos)
+        }));
+        buffer.clear();
+      }
+      if c == b'`' {
+        break;
+      }
+      tokenizer.pos += 2; // eat ${
+      parts.push(parse_statement(tokenizer)?);
+      tokenizer.next_expect(Token::CurlyClose)?;
+      buffer_start = tokenizer.pos;
+    } else if c == b'\\' {
+      tokenizer.pos += 1;
+      let escaped = tokenizer.curr_char()?;
+      buffer.push(match escaped {
+        b'n' => b'\n',
+        b't' => b'\t',
+        _ => escaped
+      });
+      tokenizer.pos += 1;
+    } else {
+      buffer.push(c);
+      tokenizer.pos += 1;
+    }
+  }
+
+  tokenizer.next_expect(Token::Byte(b'`'))?;
+
+  Ok(ast::Statement::Template(ast::TemplateLiteral {
+    parts,
+    location: tokenizer.location(start, tokenizer.pos)
+  }))
 }
 
 fn parse_array<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<ast::Statement, ParseError> {
 
-  
+  let start = tokenizer.pos;
   tokenizer.next_expect(Token::SquareOpen)?;
   let mut values = vec![];
 
@@ -81,11 +325,13 @@ fn parse_array<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<ast::Statement, Pars
   tokenizer.next_expect(Token::SquareClose)?;
 
   Ok(ast::Statement::Array(ast::Array {
-    values
+    values,
+    location: tokenizer.location(start, tokenizer.pos)
   }))
 }
 
 fn parse_object<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<ast::Statement, ParseError> {
+  let start = tokenizer.pos;
 
   tokenizer.next_expect(Token::CurlyOpen)?;
   let mut properties = vec![];
@@ -96,7 +342,7 @@ fn parse_object<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<ast::Statement, Par
 
     tokenizer.eat_whitespace();
     let value = if tokenizer.peek(1)? == Token::Colon {
-      let colon = tokenizer.next_expect(Token::Colon)?;
+      tokenizer.next_expect(Token::Colon)?;
       tokenizer.eat_whitespace();
       parse_statement(tokenizer)?
     } else {
@@ -117,7 +363,8 @@ fn parse_object<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<ast::Statement, Par
   tokenizer.next_expect(Token::CurlyClose)?;
 
   Ok(ast::Statement::Object(ast::Object {
-    properties
+    properties,
+    location: tokenizer.location(start, tokenizer.pos)
   }))
 }
 
@@ -126,7 +373,12 @@ fn parse_word<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<ast::Statement, Parse
   if let Token::Word(name) = tokenizer.next()? {
 
     if name == "true" || name == "false" {
-      return Ok(ast::Statement::Boolean(ast::Boolean { value: name == "true" }));
+      return Ok(ast::Statement::Boolean(ast::Boolean { value: name == "true", location: tokenizer.location(pos, tokenizer.pos) }));
+    }
+
+    if !tokenizer.is_eof() && tokenizer.peek(1)? == Token::ParenOpen {
+      let arguments = parse_arguments(tokenizer)?;
+      return Ok(ast::Statement::Call(ast::CallExpression { callee: name.to_string(), arguments, location: tokenizer.location(pos, tokenizer.pos) }));
     }
 
     let mut path = vec![name.to_string()];
@@ -142,7 +394,7 @@ fn parse_word<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<ast::Statement, Parse
         }
       }
     }
-    Ok(ast::Statement::Reference(ast::Reference { path: path }))
+    Ok(ast::Statement::Reference(ast::Reference { path: path, location: tokenizer.location(pos, tokenizer.pos) }))
   } else {
     Err(ParseError::unexpected_token(pos))
   }
@@ -152,6 +404,7 @@ fn parse_word<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<ast::Statement, Parse
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::base::ast::{Location, LineIndex};
 
   #[test]
   fn can_smoke_parse_various_statements() {
@@ -180,7 +433,34 @@ mod tests {
       "[]",
       "{}",
       "[ ]",
-      "{ }"
+      "{ }",
+
+      // operators
+      "!a",
+      "a && b || !c",
+      "kind == 'primary'",
+      "a !== b",
+      "a <= 1 ? 'small' : a >= 10 ? 'large' : 'medium'",
+      "-(a + 1) * 2 / 3 % 4",
+
+      // member access
+      "items[0].label",
+      "labels[key]",
+      "a.b[c.d][0].length",
+      "(a || b).c",
+
+      // helpers
+      "uppercase(label)",
+      "join(items, ', ')",
+      "now()",
+      "price | currency",
+      "date | date('MMM DD') | uppercase",
+      "a || b | default('c')",
+
+      // template literals
+      "`btn ${variant}`",
+      "`${a}${b ? `-${c}` : ''}`",
+      "`escaped \\` \\${b}`"
     ];
 
     for case in cases {
@@ -188,4 +468,69 @@ mod tests {
       println!("{:?}", _ast);
     }
   }
+
+  #[test]
+  fn operators_follow_precedence() {
+    let cases = vec![
+      ("a || b && c", "a || b && c"),
+      ("a + b * c - d", "a + b * c - d"),
+      ("!a == b", "!a == b"),
+      ("a ? b : c ? d : e", "a ? b : c ? d : e")
+    ];
+
+    for (source, expected) in cases {
+      assert_eq!(_parse(source).unwrap().to_string(), expected);
+    }
+
+    match _parse("a || b && c == d + e * f").unwrap() {
+      ast::Statement::Binary(expr) => {
+        assert_eq!(expr.operator, ast::BinaryOperator::Or);
+        assert_eq!(expr.left.to_string(), "a");
+        match *expr.right {
+          ast::Statement::Binary(right) => assert_eq!(right.operator, ast::BinaryOperator::And),
+          _ => panic!("expected a binary expression")
+        }
+      },
+      _ => panic!("expected a binary expression")
+    };
+
+    match _parse("a - b - c").unwrap() {
+      ast::Statement::Binary(expr) => assert_eq!(expr.left.to_string(), "a - b"),
+      _ => panic!("expected a binary expression")
+    };
+  }
+
+  #[test]
+  fn can_parse_computed_member_access() {
+    assert_eq!(_parse("items[0].label").unwrap().to_string(), "items[0].label");
+    assert_eq!(_parse("a.b[c.d[e]]").unwrap().to_string(), "a.b[c.d[e]]");
+
+    match _parse("items[i + 1].label").unwrap() {
+      ast::Statement::Member(member) => {
+        assert_eq!(member.computed, false);
+        assert_eq!(member.object.to_string(), "items[i + 1]");
+        assert_eq!(member.location, Location::new(0, 18).with_positions(&LineIndex::new("items[i + 1].label")));
+      },
+      _ => panic!("expected a member expression")
+    };
+  }
+
+  #[test]
+  fn pipes_are_parsed_as_calls() {
+    assert_eq!(_parse("price | currency").unwrap().to_string(), "currency(price)");
+    assert_eq!(_parse("a || b | default(c ? d : e) | uppercase").unwrap().to_string(), "uppercase(default(a || b, c ? d : e))");
+    assert_eq!(_parse("join(items[0], ', ').length").unwrap().to_string(), "join(items[0], \", \").length");
+  }
+
+  #[test]
+  fn can_parse_template_literals() {
+    match _parse("`btn btn-${variant | lowercase}!`").unwrap() {
+      ast::Statement::Template(template) => {
+        let parts: Vec<String> = template.parts.iter().map(|part| part.to_string()).collect();
+        assert_eq!(parts, vec!["\"btn btn-\"", "lowercase(variant)", "\"!\""]);
+      },
+      _ => panic!("expected a template literal")
+    };
+    assert_eq!(_parse("`a \\` ${b}`").unwrap().to_string(), "`a \\` ${b}`");
+  }
 }
@@ -2,9 +2,9 @@
 use super::super::ast;
 use super::virt;
 use crate::base::runtime::{RuntimeError};
-use crate::base::ast::{Location};
 use crate::pc::runtime::evaluator::{evaluate_node as evaluate_pc_node, Context as PCContext};
 use crate::pc::ast as pc_ast;
+use std::cmp::Ordering;
 
 pub fn evaluate<'a>(expr: &ast::Statement, context: &'a mut PCContext) -> Result<virt::JsValue, RuntimeError> {
   evaluate_statement(&expr, context)
@@ -12,12 +12,85 @@ pub fn evaluate<'a>(expr: &ast::Statement, context: &'a mut PCContext) -> Result
 fn evaluate_statement<'a>(statement: &ast::Statement, context: &'a mut PCContext) -> Result<virt::JsValue, RuntimeError> {
   match statement {
     ast::Statement::Reference(reference) => evaluate_reference(reference, context),
+    ast::Statement::Member(member) => evaluate_member(member, context),
+    ast::Statement::Call(call) => evaluate_call(call, context),
     ast::Statement::Node(node) => evaluate_node(node, context),
     ast::Statement::String(value) => evaluate_string(&value.value, context),
+    ast::Statement::Template(value) => evaluate_template(value, context),
     ast::Statement::Boolean(value) => evaluate_boolean(&value.value, context),
     ast::Statement::Number(value) => evaluate_number(&value.value, context),
     ast::Statement::Array(value) => evaluate_array(value, context),
-    ast::Statement::Object(value) => evaluate_object(value, context)
+    ast::Statement::Object(value) => evaluate_object(value, context),
+    ast::Statement::Group(value) => evaluate_statement(&value.expression, context),
+    ast::Statement::Unary(value) => evaluate_unary(value, context),
+    ast::Statement::Binary(value) => evaluate_binary(value, context),
+    ast::Statement::Conditional(value) => evaluate_conditional(value, context)
+  }
+}
+
+fn evaluate_unary<'a>(expr: &ast::UnaryExpression, context: &'a mut PCContext) -> Result<virt::JsValue, RuntimeError> {
+  let value = evaluate_statement(&expr.expression, context)?;
+  Ok(match expr.operator {
+    ast::UnaryOperator::Not => virt::JsValue::JsBoolean(!value.truthy()),
+    ast::UnaryOperator::Negative => virt::JsValue::JsNumber(-value.to_number())
+  })
+}
+
+fn evaluate_binary<'a>(expr: &ast::BinaryExpression, context: &'a mut PCContext) -> Result<virt::JsValue, RuntimeError> {
+  let left = evaluate_statement(&expr.left, context)?;
+
+  // logical operators short-circuit and return one of their operands
+  match expr.operator {
+    ast::BinaryOperator::And if !left.truthy() => return Ok(left),
+    ast::BinaryOperator::Or if left.truthy() => return Ok(left),
+    ast::BinaryOperator::And | ast::BinaryOperator::Or => return evaluate_statement(&expr.right, context),
+    _ => {}
+  };
+
+  let right = evaluate_statement(&expr.right, context)?;
+
+  Ok(match expr.operator {
+    ast::BinaryOperator::Equal => virt::JsValue::JsBoolean(left.loose_equals(&right)),
+    ast::BinaryOperator::NotEqual => virt::JsValue::JsBoolean(!left.loose_equals(&right)),
+    ast::BinaryOperator::StrictEqual => virt::JsValue::JsBoolean(left.strict_equals(&right)),
+    ast::BinaryOperator::StrictNotEqual => virt::JsValue::JsBoolean(!left.strict_equals(&right)),
+    ast::BinaryOperator::LessThan => virt::JsValue::JsBoolean(compare(&left, &right, |ordering| ordering == Ordering::Less)),
+    ast::BinaryOperator::LessThanOrEqual => virt::JsValue::JsBoolean(compare(&left, &right, |ordering| ordering != Ordering::Greater)),
+    ast::BinaryOperator::GreaterThan => virt::JsValue::JsBoolean(compare(&left, &right, |ordering| ordering == Ordering::Greater)),
+    ast::BinaryOperator::GreaterThanOrEqual => virt::JsValue::JsBoolean(compare(&left, &right, |ordering| ordering != Ordering::Less)),
+    ast::BinaryOperator::Add => add(&left, &right),
+    ast::BinaryOperator::Subtract => virt::JsValue::JsNumber(left.to_number() - right.to_number()),
+    ast::BinaryOperator::Multiply => virt::JsValue::JsNumber(left.to_number() * right.to_number()),
+    ast::BinaryOperator::Divide => virt::JsValue::JsNumber(left.to_number() / right.to_number()),
+    ast::BinaryOperator::Modulo => virt::JsValue::JsNumber(left.to_number() % right.to_number()),
+    ast::BinaryOperator::And | ast::BinaryOperator::Or => unreachable!()
+  })
+}
+
+// strings are compared lexicographically, everything else numerically. NaN is never
+// less than, equal to, or greater than anything.
+fn compare<TTest>(left: &virt::JsValue, right: &virt::JsValue, test: TTest) -> bool where TTest: Fn(Ordering) -> bool {
+  let ordering = match (left, right) {
+    (virt::JsValue::JsString(left), virt::JsValue::JsString(right)) => Some(left.cmp(right)),
+    _ => left.to_number().partial_cmp(&right.to_number())
+  };
+  ordering.map(test).unwrap_or(false)
+}
+
+// concatenates if either side isn't a number-like primitive, e.g: 'item-' + index
+fn add(left: &virt::JsValue, right: &virt::JsValue) -> virt::JsValue {
+  if left.is_numeric() && right.is_numeric() {
+    virt::JsValue::JsNumber(left.to_number() + right.to_number())
+  } else {
+    virt::JsValue::JsString(format!("{}{}", left.to_js_string(), right.to_js_string()))
+  }
+}
+
+fn evaluate_conditional<'a>(expr: &ast::ConditionalExpression, context: &'a mut PCContext) -> Result<virt::JsValue, RuntimeError> {
+  if evaluate_statement(&expr.test, context)?.truthy() {
+    evaluate_statement(&expr.consequent, context)
+  } else {
+    evaluate_statement(&expr.alternate, context)
   }
 }
 
@@ -34,6 +107,14 @@ fn evaluate_string<'a>(value: &String, context: &'a mut PCContext) -> Result<vir
   Ok(virt::JsValue::JsString(value.to_string()))
 }
 
+fn evaluate_template<'a>(template: &ast::TemplateLiteral, context: &'a mut PCContext) -> Result<virt::JsValue, RuntimeError> {
+  let mut buffer = String::new();
+  for part in &template.parts {
+    buffer.push_str(&evaluate_statement(part, context)?.to_js_string());
+  }
+  Ok(virt::JsValue::JsString(buffer))
+}
+
 fn evaluate_boolean<'a>(value: &bool, context: &'a mut PCContext) -> Result<virt::JsValue, RuntimeError> {
   Ok(virt::JsValue::JsBoolean(*value))
 }
@@ -62,7 +143,7 @@ fn evaluate_object<'a>(obj: &ast::Object, context: &'a mut PCContext) -> Result<
 
 fn evaluate_reference<'a>(reference: &ast::Reference, context: &'a mut PCContext) -> Result<virt::JsValue, RuntimeError> {
   
-  let mut curr = Some(context.data);
+  let mut curr = Some(context.data.clone());
 
   for property_name in &reference.path {
     if let Some(object) = &curr {
@@ -71,17 +152,40 @@ fn evaluate_reference<'a>(reference: &ast::Reference, context: &'a mut PCContext
       return Err(RuntimeError {
         uri: context.uri.to_string(),
         message: "Cannot access property of undefined".to_string(), 
-        location: Location {
-          start: 0,
-          end: 1
-        }
+        location: reference.location.clone()
       });
     }
   }
 
-  if let Some(js_value) = curr {
-    Ok(js_value.clone())
-  } else {
-    Ok(virt::JsValue::JsUndefined())
+  Ok(curr.unwrap_or(virt::JsValue::JsUndefined()))
+}
+
+fn evaluate_member<'a>(member: &ast::MemberExpression, context: &'a mut PCContext) -> Result<virt::JsValue, RuntimeError> {
+  let object = evaluate_statement(&member.object, context)?;
+
+  if object == virt::JsValue::JsUndefined() {
+    return Err(RuntimeError {
+      uri: context.uri.to_string(),
+      message: "Cannot access property of undefined".to_string(),
+      location: member.location.clone()
+    });
+  }
+
+  // items[0] -> "0"
+  let property_name = evaluate_statement(&member.property, context)?.to_string();
+
+  Ok(virt::get_js_value_property(&object, &property_name).unwrap_or(virt::JsValue::JsUndefined()))
+}
+
+fn evaluate_call<'a>(call: &ast::CallExpression, context: &'a mut PCContext) -> Result<virt::JsValue, RuntimeError> {
+  let mut arguments = vec![];
+  for argument in &call.arguments {
+    arguments.push(evaluate_statement(argument, context)?);
+  }
+
+  match context.helpers.call(&call.callee, &arguments) {
+    Some(Ok(value)) => Ok(value),
+    Some(Err(message)) => Err(RuntimeError::new(message, context.uri, &call.location)),
+    None => Err(RuntimeError::new(format!("Unknown helper \"{}\".", call.callee), context.uri, &call.location))
   }
 }
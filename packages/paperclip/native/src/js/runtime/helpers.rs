@@ -0,0 +1,222 @@
+// Pure functions that templates can call, e.g: {uppercase(label)} or {price | currency}. Hosts can
+// register their own helpers through the engine.
+
+use std::collections::HashMap;
+use super::virt::JsValue;
+
+pub type HelperFn = dyn Fn(&Vec<JsValue>) -> Result<JsValue, String>;
+
+pub struct Helpers {
+  helpers: HashMap<String, Box<HelperFn>>
+}
+
+impl Helpers {
+  pub fn new() -> Helpers {
+    let mut helpers = Helpers {
+      helpers: HashMap::new()
+    };
+    helpers.register("uppercase", Box::new(uppercase));
+    helpers.register("lowercase", Box::new(lowercase));
+    helpers.register("capitalize", Box::new(capitalize));
+    helpers.register("trim", Box::new(trim));
+    helpers.register("join", Box::new(join));
+    helpers.register("default", Box::new(default));
+    helpers.register("pluralize", Box::new(pluralize));
+    helpers.register("fixed", Box::new(fixed));
+    helpers.register("currency", Box::new(currency));
+    helpers.register("date", Box::new(date));
+    helpers
+  }
+
+  pub fn register(&mut self, name: &str, helper: Box<HelperFn>) {
+    self.helpers.insert(name.to_string(), helper);
+  }
+
+  // None if the helper doesn't exist
+  pub fn call(&self, name: &str, args: &Vec<JsValue>) -> Option<Result<JsValue, String>> {
+    self.helpers.get(name).map(|helper| helper(args))
+  }
+}
+
+fn arg(args: &Vec<JsValue>, index: usize) -> JsValue {
+  args.get(index).cloned().unwrap_or(JsValue::JsUndefined())
+}
+
+fn string_arg(args: &Vec<JsValue>, index: usize, default_value: &str) -> String {
+  match arg(args, index) {
+    JsValue::JsUndefined() => default_value.to_string(),
+    value => value.to_js_string()
+  }
+}
+
+fn uppercase(args: &Vec<JsValue>) -> Result<JsValue, String> {
+  Ok(JsValue::JsString(string_arg(args, 0, "").to_uppercase()))
+}
+
+fn lowercase(args: &Vec<JsValue>) -> Result<JsValue, String> {
+  Ok(JsValue::JsString(string_arg(args, 0, "").to_lowercase()))
+}
+
+fn capitalize(args: &Vec<JsValue>) -> Result<JsValue, String> {
+  let value = string_arg(args, 0, "");
+  let mut chars = value.chars();
+  Ok(JsValue::JsString(match chars.next() {
+    Some(first) => first.to_uppercase().collect::<String>() + chars.as_str(),
+    None => value
+  }))
+}
+
+fn trim(args: &Vec<JsValue>) -> Result<JsValue, String> {
+  Ok(JsValue::JsString(string_arg(args, 0, "").trim().to_string()))
+}
+
+// join(items, ", ")
+fn join(args: &Vec<JsValue>) -> Result<JsValue, String> {
+  match arg(args, 0) {
+    JsValue::JsArray(array) => {
+      let values: Vec<String> = array.values.iter().map(|value| match value {
+        JsValue::JsUndefined() => "".to_string(),
+        _ => value.to_js_string()
+      }).collect();
+      Ok(JsValue::JsString(values.join(&string_arg(args, 1, ","))))
+    },
+    _ => Err("join() expects an array.".to_string())
+  }
+}
+
+// default(label, 'Untitled')
+fn default(args: &Vec<JsValue>) -> Result<JsValue, String> {
+  let value = arg(args, 0);
+  Ok(if value.truthy() { value } else { arg(args, 1) })
+}
+
+// pluralize(count, 'item') -> item | items
+fn pluralize(args: &Vec<JsValue>) -> Result<JsValue, String> {
+  let singular = string_arg(args, 1, "");
+  Ok(JsValue::JsString(if arg(args, 0).to_number() == 1.0 {
+    singular
+  } else {
+    match arg(args, 2) {
+      JsValue::JsUndefined() => format!("{}s", singular),
+      plural => plural.to_string()
+    }
+  }))
+}
+
+// fixed(price, 2)
+fn fixed(args: &Vec<JsValue>) -> Result<JsValue, String> {
+  let digits = arg(args, 1).to_number();
+  let digits = if digits.is_nan() { 0 } else { digits as usize };
+  Ok(JsValue::JsString(format!("{:.*}", digits, arg(args, 0).to_number())))
+}
+
+// currency(1234.5) -> $1,234.50
+fn currency(args: &Vec<JsValue>) -> Result<JsValue, String> {
+  let value = arg(args, 0).to_number();
+  if value.is_nan() {
+    return Err("currency() expects a number.".to_string());
+  }
+
+  let formatted = format!("{:.2}", value.abs());
+  let (whole, fraction) = formatted.split_at(formatted.len() - 3);
+  let mut buffer = String::new();
+  for (i, c) in whole.chars().enumerate() {
+    if i > 0 && (whole.len() - i) % 3 == 0 {
+      buffer.push(',');
+    }
+    buffer.push(c);
+  }
+
+  Ok(JsValue::JsString(format!("{}{}{}{}", if value < 0.0 { "-" } else { "" }, string_arg(args, 1, "$"), buffer, fraction)))
+}
+
+const MONTH_NAMES: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+const DATE_TOKENS: [&str; 7] = ["YYYY", "MMM", "MM", "DD", "HH", "mm", "ss"];
+
+// date('2020-03-14T15:09:26', 'MMM DD, YYYY') -> Mar 14, 2020
+fn date(args: &Vec<JsValue>) -> Result<JsValue, String> {
+  let value = string_arg(args, 0, "");
+  let parts = parse_iso_date(&value).ok_or(format!("Unable to parse date \"{}\".", value))?;
+  let format = string_arg(args, 1, "YYYY-MM-DD");
+
+  let mut buffer = String::new();
+  let mut rest = format.as_str();
+  while let Some(c) = rest.chars().next() {
+    match DATE_TOKENS.iter().find(|token| rest.starts_with(*token)) {
+      Some(token) => {
+        buffer.push_str(&match *token {
+          "YYYY" => format!("{:04}", parts[0]),
+          "MMM" => MONTH_NAMES[parts[1] as usize - 1].to_string(),
+          "MM" => format!("{:02}", parts[1]),
+          "DD" => format!("{:02}", parts[2]),
+          "HH" => format!("{:02}", parts[3]),
+          "mm" => format!("{:02}", parts[4]),
+          _ => format!("{:02}", parts[5])
+        });
+        rest = &rest[token.len()..];
+      },
+      None => {
+        buffer.push(c);
+        rest = &rest[c.len_utf8()..];
+      }
+    }
+  }
+
+  Ok(JsValue::JsString(buffer))
+}
+
+// YYYY-MM-DD[THH:mm[:ss]] -> [year, month, day, hours, minutes, seconds]
+fn parse_iso_date(value: &str) -> Option<[u32; 6]> {
+  let mut parts = [0; 6];
+  let (date, time) = match value.find(|c| c == 'T' || c == ' ') {
+    Some(index) => (&value[..index], &value[index + 1..]),
+    None => (value, "")
+  };
+
+  let date_parts: Vec<&str> = date.split('-').collect();
+  if date_parts.len() != 3 {
+    return None;
+  }
+
+  // ignore fractions of seconds & timezones
+  let time_parts: Vec<&str> = time.split(|c| c == '.' || c == 'Z' || c == '+').next().unwrap_or("").split(':').filter(|part| part.len() > 0).collect();
+
+  for (i, part) in date_parts.iter().chain(time_parts.iter()).take(6).enumerate() {
+    parts[i] = part.parse().ok()?;
+  }
+
+  if parts[1] < 1 || parts[1] > 12 {
+    return None;
+  }
+
+  Some(parts)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn call(name: &str, args: Vec<JsValue>) -> Result<JsValue, String> {
+    Helpers::new().call(name, &args).unwrap()
+  }
+
+  fn string(value: &str) -> JsValue {
+    JsValue::JsString(value.to_string())
+  }
+
+  #[test]
+  fn can_format_numbers() {
+    assert_eq!(call("currency", vec![JsValue::JsNumber(1234567.5)]), Ok(string("$1,234,567.50")));
+    assert_eq!(call("currency", vec![JsValue::JsNumber(-12.0), string("€")]), Ok(string("-€12.00")));
+    assert_eq!(call("fixed", vec![JsValue::JsNumber(3.14159), JsValue::JsNumber(2.0)]), Ok(string("3.14")));
+    assert_eq!(call("pluralize", vec![JsValue::JsNumber(2.0), string("item")]), Ok(string("items")));
+    assert_eq!(call("pluralize", vec![JsValue::JsNumber(1.0), string("person"), string("people")]), Ok(string("person")));
+  }
+
+  #[test]
+  fn can_format_dates() {
+    assert_eq!(call("date", vec![string("2020-03-14T15:09:26.000Z"), string("MMM DD, YYYY HH:mm:ss")]), Ok(string("Mar 14, 2020 15:09:26")));
+    assert_eq!(call("date", vec![string("2020-3-4")]), Ok(string("2020-03-04")));
+    assert_eq!(call("date", vec![string("yesterday")]), Err("Unable to parse date \"yesterday\".".to_string()));
+  }
+}
@@ -1,2 +1,3 @@
 pub mod evaluator;
+pub mod helpers;
 pub mod virt;
\ No newline at end of file
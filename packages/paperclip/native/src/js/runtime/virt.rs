@@ -27,18 +27,110 @@ impl JsValue {
       _ => true,
     }
   }
+
+  // Number(value)
+  pub fn to_number(&self) -> f64 {
+    match self {
+      JsValue::JsNumber(value) => *value,
+      JsValue::JsBoolean(value) => if *value { 1.0 } else { 0.0 },
+      JsValue::JsString(value) => {
+        let value = value.trim();
+        if value.len() == 0 {
+          0.0
+        } else {
+          value.parse::<f64>().unwrap_or(std::f64::NAN)
+        }
+      },
+      _ => std::f64::NAN
+    }
+  }
+
+  // String(value)
+  pub fn to_js_string(&self) -> String {
+    match self {
+      JsValue::JsString(value) => value.to_string(),
+      JsValue::JsBoolean(value) => value.to_string(),
+      JsValue::JsNumber(value) => {
+        if value.is_nan() {
+          "NaN".to_string()
+        } else if value.is_infinite() {
+          (if *value > 0.0 { "Infinity" } else { "-Infinity" }).to_string()
+        } else if *value == 0.0 {
+          "0".to_string()
+        } else {
+          value.to_string()
+        }
+      },
+      JsValue::JsArray(array) => array.values.iter().map(|value| match value {
+        JsValue::JsUndefined() => "".to_string(),
+        _ => value.to_js_string()
+      }).collect::<Vec<String>>().join(","),
+      JsValue::JsObject(_) | JsValue::JsNode(_) => "[object Object]".to_string(),
+      JsValue::JsUndefined() => "undefined".to_string()
+    }
+  }
+
+  // true for primitives that + treats as numbers
+  pub fn is_numeric(&self) -> bool {
+    matches!(self, JsValue::JsNumber(_) | JsValue::JsBoolean(_) | JsValue::JsUndefined())
+  }
+
+  // ===. Values don't have identities, so objects & arrays are compared structurally.
+  pub fn strict_equals(&self, other: &JsValue) -> bool {
+    self == other
+  }
+
+  // ==
+  pub fn loose_equals(&self, other: &JsValue) -> bool {
+    match (self, other) {
+      (JsValue::JsUndefined(), JsValue::JsUndefined()) => true,
+      (JsValue::JsUndefined(), _) | (_, JsValue::JsUndefined()) => false,
+      (JsValue::JsString(left), JsValue::JsString(right)) => left == right,
+      (left, right) if left.is_primitive() && right.is_primitive() => left.to_number() == right.to_number(),
+      (left, right) if left.is_primitive() || right.is_primitive() => left.to_string() == right.to_string(),
+      (left, right) => left.strict_equals(right)
+    }
+  }
+
+  // used to pass values to & from hosts
+  pub fn to_json(&self) -> serde_json::Value {
+    match self {
+      JsValue::JsObject(object) => serde_json::Value::Object(object.values.iter().map(|(key, value)| (key.to_string(), value.to_json())).collect()),
+      JsValue::JsArray(array) => serde_json::Value::Array(array.values.iter().map(|value| value.to_json()).collect()),
+      JsValue::JsNode(node) => serde_json::to_value(node).unwrap_or(serde_json::Value::Null),
+      JsValue::JsString(value) => serde_json::Value::String(value.to_string()),
+      JsValue::JsBoolean(value) => serde_json::Value::Bool(*value),
+      JsValue::JsNumber(value) => serde_json::Number::from_f64(*value).map(serde_json::Value::Number).unwrap_or(serde_json::Value::Null),
+      JsValue::JsUndefined() => serde_json::Value::Null
+    }
+  }
+
+  pub fn from_json(value: &serde_json::Value) -> JsValue {
+    match value {
+      serde_json::Value::Object(object) => {
+        let mut js_object = JsObject::new();
+        for (key, value) in object {
+          js_object.values.insert(key.to_string(), JsValue::from_json(value));
+        }
+        JsValue::JsObject(js_object)
+      },
+      serde_json::Value::Array(values) => JsValue::JsArray(JsArray {
+        values: values.iter().map(JsValue::from_json).collect()
+      }),
+      serde_json::Value::String(value) => JsValue::JsString(value.to_string()),
+      serde_json::Value::Bool(value) => JsValue::JsBoolean(*value),
+      serde_json::Value::Number(value) => JsValue::JsNumber(value.as_f64().unwrap_or(std::f64::NAN)),
+      serde_json::Value::Null => JsValue::JsUndefined()
+    }
+  }
+
+  fn is_primitive(&self) -> bool {
+    matches!(self, JsValue::JsNumber(_) | JsValue::JsBoolean(_) | JsValue::JsString(_) | JsValue::JsUndefined())
+  }
 }
 impl fmt::Display for JsValue {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-    match self {
-      JsValue::JsObject(_object) => write!(f, "[Object object]"),
-      JsValue::JsString(value) => write!(f, "{}", value),
-      JsValue::JsBoolean(value) => write!(f, "{}", if *value { "true" } else { "false" }),
-      JsValue::JsNode(_value) => write!(f, "[Object object]"),
-      JsValue::JsNumber(value) => write!(f, "{}", value),
-      JsValue::JsArray(_value) => write!(f, "[Object object]"),
-      JsValue::JsUndefined() => write!(f, "undefined")
-    }
+    write!(f, "{}", self.to_js_string())
   }
 }
 
@@ -68,9 +160,14 @@ impl JsArray {
   }
 }
 
-pub fn get_js_value_property<'a, 'b>(value: &'a JsValue, property_name: &'b str) -> Option<&'a JsValue> {
+pub fn get_js_value_property(value: &JsValue, property_name: &str) -> Option<JsValue> {
   match value {
-    JsValue::JsObject(object) => object.values.get(property_name),
+    JsValue::JsObject(object) => object.values.get(property_name).cloned(),
+    JsValue::JsArray(array) if property_name == "length" => Some(JsValue::JsNumber(array.values.len() as f64)),
+    JsValue::JsArray(array) => property_name.parse::<usize>().ok()
+      .filter(|index| index.to_string() == property_name)
+      .and_then(|index| array.values.get(index).cloned()),
+    JsValue::JsString(value) if property_name == "length" => Some(JsValue::JsNumber(value.encode_utf16().count() as f64)),
     _ => None
   }
 }
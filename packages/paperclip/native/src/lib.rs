@@ -16,6 +16,9 @@ mod engine;
 
 use ::futures::executor::block_on;
 use engine::{Engine};
+use pc::lint::{LintOptions};
+use pc::runtime::html::{HtmlOptions};
+use js::runtime::virt as js_virt;
 
 extern crate web_sys;
 
@@ -60,6 +63,19 @@ impl NativeEngine {
         listener.call1(&this, &arg).unwrap();
       }));
     }
+    pub fn register_helper(&mut self, name: String, helper: js_sys::Function) {
+      let helper_name = name.to_string();
+      self.target.register_helper(&name, Box::new(move |args| {
+        let this = JsValue::NULL;
+        let args: Vec<serde_json::Value> = args.iter().map(|arg| arg.to_json()).collect();
+        let arg = JsValue::from_serde(&args).unwrap();
+        let result = helper.call1(&this, &arg).map_err(|err| {
+          err.as_string().unwrap_or(format!("Helper \"{}\" failed.", helper_name))
+        })?;
+        let result: serde_json::Value = result.into_serde().unwrap_or(serde_json::Value::Null);
+        Ok(js_virt::JsValue::from_json(&result))
+      }));
+    }
     pub fn evaluate_content_styles(&mut self, content: String, uri: String) -> JsValue {
       console_error_panic_hook::set_once();
       let result = block_on(self.target.evaluate_content_styles(&content, &uri));
@@ -80,6 +96,44 @@ impl NativeEngine {
       let result = block_on(self.target.parse_file(&uri));
       JsValue::from_serde(&result).unwrap()
     }
+    pub fn lint_file(&mut self, uri: String, options: JsValue) -> JsValue {
+      console_error_panic_hook::set_once();
+      let options: Option<LintOptions> = options.into_serde().ok();
+      let result = block_on(self.target.lint_file(&uri, options));
+      JsValue::from_serde(&result).unwrap()
+    }
+    pub fn infer_file_parts(&mut self, uri: String) -> JsValue {
+      console_error_panic_hook::set_once();
+      let result = block_on(self.target.infer_file_parts(&uri));
+      JsValue::from_serde(&result).unwrap()
+    }
+    pub fn generate_file_definition(&mut self, uri: String) -> JsValue {
+      console_error_panic_hook::set_once();
+      let result = block_on(self.target.generate_file_definition(&uri));
+      JsValue::from_serde(&result).unwrap()
+    }
+    pub fn evaluate_part(&mut self, uri: String, part: String, props: JsValue) -> JsValue {
+      console_error_panic_hook::set_once();
+      let props: serde_json::Value = props.into_serde().unwrap_or(serde_json::Value::Object(serde_json::Map::new()));
+      let result = block_on(self.target.evaluate_part(&uri, &part, &props));
+      JsValue::from_serde(&result).unwrap()
+    }
+    pub fn get_file_previews(&mut self, uri: String) -> JsValue {
+      console_error_panic_hook::set_once();
+      let result = block_on(self.target.get_file_previews(&uri));
+      JsValue::from_serde(&result).unwrap()
+    }
+    pub fn evaluate_preview(&mut self, uri: String, name: String) -> JsValue {
+      console_error_panic_hook::set_once();
+      let result = block_on(self.target.evaluate_preview(&uri, &name));
+      JsValue::from_serde(&result).unwrap()
+    }
+    pub fn render_file_html(&mut self, uri: String, options: JsValue) -> JsValue {
+      console_error_panic_hook::set_once();
+      let options: HtmlOptions = options.into_serde().unwrap_or_default();
+      let result = block_on(self.target.render_file_html(&uri, &options));
+      JsValue::from_serde(&result).unwrap()
+    }
     pub fn update_virtual_file_content(&mut self, uri: String, content: String) {
       console_error_panic_hook::set_once();
       block_on(self.target.update_virtual_file_content(&uri, &content));
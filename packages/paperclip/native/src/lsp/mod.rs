@@ -0,0 +1,387 @@
+// Language server for .pc files. Messages are framed with a Content-Length header
+// as described in https://microsoft.github.io/language-server-protocol/specification.
+// Documents are synced in full, and every change goes through the engine so that
+// diagnostics match what the other tools report.
+
+use std::cell::RefCell;
+use std::io::{self, BufRead, Write};
+use std::rc::Rc;
+use serde::{Serialize};
+use serde_json::{json, Value};
+use ::futures::executor::block_on;
+use crate::base::ast::{self, Location};
+use crate::engine::{Engine, EngineEvent, EngineError};
+use crate::pc::runtime::graph::{GraphError, GraphErrorInfo};
+
+const TEXT_DOCUMENT_SYNC_FULL: u8 = 1;
+const DIAGNOSTIC_SEVERITY_ERROR: u8 = 1;
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+
+// bodies are buffered in memory, so anything larger is rejected
+const MAX_CONTENT_LENGTH: usize = 64 * 1024 * 1024;
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Position {
+  pub line: usize,
+  pub character: usize
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Range {
+  pub start: Position,
+  pub end: Position
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Diagnostic {
+  pub range: Range,
+  pub severity: u8,
+  pub source: String,
+  pub message: String
+}
+
+#[derive(Debug, Clone)]
+struct FileError {
+  uri: String,
+  location: Location,
+  message: String
+}
+
+fn get_file_error(error: &EngineError) -> Option<FileError> {
+  match error {
+    EngineError::Graph(GraphError { uri, info }) => match info {
+      GraphErrorInfo::Syntax(err) => Some(FileError {
+        uri: uri.to_string(),
+        location: err.location.clone(),
+        message: err.message.to_string()
+      }),
+      GraphErrorInfo::IncludeNotFound(err) => Some(FileError {
+        uri: uri.to_string(),
+        location: err.location.clone(),
+        message: format!("{}: {}", err.message, err.uri)
+      }),
+
+      // nothing in the document to point to
+      GraphErrorInfo::NotFound => None
+    },
+    EngineError::Runtime(err) => Some(FileError {
+      uri: err.uri.to_string(),
+      location: err.location.clone(),
+      message: err.message.to_string()
+    }),
+
+    // only returned from content that isn't associated with a file
+    EngineError::Parser(_) => None
+  }
+}
+
+pub struct LanguageServer {
+  engine: Engine,
+
+  // errors dispatched by the engine while handling the current message
+  errors: Rc<RefCell<Vec<FileError>>>,
+  shutdown_requested: bool,
+  exited: bool
+}
+
+impl LanguageServer {
+  pub fn new(mut engine: Engine) -> LanguageServer {
+    let errors = Rc::new(RefCell::new(vec![]));
+    let listener_errors = errors.clone();
+    engine.add_listener(Box::new(move |event| {
+      if let EngineEvent::Error(error) = event {
+        if let Some(file_error) = get_file_error(error) {
+          listener_errors.borrow_mut().push(file_error);
+        }
+      }
+    }));
+    LanguageServer {
+      engine,
+      errors,
+      shutdown_requested: false,
+      exited: false
+    }
+  }
+
+  // Returns the messages to send back to the client
+  pub fn handle_message(&mut self, message: &Value) -> Vec<Value> {
+    let method = message.get("method").and_then(Value::as_str).unwrap_or("").to_string();
+    let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+    match message.get("id") {
+      Some(id) => {
+        let response = match self.handle_request(&method) {
+          Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+          Err((code, message)) => json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+        };
+        vec![response]
+      },
+      None => self.handle_notification(&method, &params)
+    }
+  }
+
+  fn handle_request(&mut self, method: &str) -> Result<Value, (i64, String)> {
+    match method {
+      "initialize" => Ok(json!({
+        "capabilities": {
+          "textDocumentSync": TEXT_DOCUMENT_SYNC_FULL
+        },
+        "serverInfo": {
+          "name": "paperclip"
+        }
+      })),
+      "shutdown" => {
+        self.shutdown_requested = true;
+        Ok(Value::Null)
+      },
+      _ => Err((METHOD_NOT_FOUND, format!("Method \"{}\" not found", method)))
+    }
+  }
+
+  fn handle_notification(&mut self, method: &str, params: &Value) -> Vec<Value> {
+    let uri_option = params.pointer("/textDocument/uri").and_then(Value::as_str).map(|uri| uri.to_string());
+    match (method, uri_option) {
+      ("textDocument/didOpen", Some(uri)) => {
+        match params.pointer("/textDocument/text").and_then(Value::as_str) {
+          Some(text) => self.update_document(&uri, &text.to_string()),
+          None => vec![]
+        }
+      },
+      ("textDocument/didChange", Some(uri)) => {
+
+        // full sync, so the last change contains the entire document
+        let text_option = params.get("contentChanges")
+        .and_then(Value::as_array)
+        .and_then(|changes| changes.last())
+        .and_then(|change| change.get("text"))
+        .and_then(Value::as_str);
+
+        match text_option {
+          Some(text) => self.update_document(&uri, &text.to_string()),
+          None => vec![]
+        }
+      },
+      ("textDocument/didClose", Some(uri)) => {
+        self.engine.unload(&uri);
+        vec![self.publish_diagnostics(&uri, vec![])]
+      },
+      ("exit", _) => {
+        self.exited = true;
+        vec![]
+      },
+      _ => vec![]
+    }
+  }
+
+  fn update_document(&mut self, uri: &String, text: &String) -> Vec<Value> {
+    self.errors.borrow_mut().clear();
+
+    // graph errors are dispatched as events, so they're picked up below
+    let _ = block_on(self.engine.update_virtual_file_content(uri, text));
+
+    let errors: Vec<FileError> = self.errors.borrow_mut().drain(..).collect();
+
+    // diagnostics are replaced for every document that was re-evaluated
+    let mut uris = vec![uri.to_string()];
+    let dependent_uris = self.engine.dependency_graph.flatten_dependents(uri).into_iter().map(|dep| dep.uri.to_string());
+    let error_uris = errors.iter().map(|error| error.uri.to_string());
+    for affected_uri in dependent_uris.chain(error_uris).collect::<Vec<String>>() {
+      if !uris.contains(&affected_uri) {
+        uris.push(affected_uri);
+      }
+    }
+
+    uris.iter().map(|affected_uri| {
+      let file_errors = errors.iter().filter(|error| &error.uri == affected_uri).cloned().collect();
+      self.publish_diagnostics(affected_uri, file_errors)
+    }).collect()
+  }
+
+  fn publish_diagnostics(&self, uri: &String, errors: Vec<FileError>) -> Value {
+    let diagnostics: Vec<Diagnostic> = errors.into_iter().map(|error| {
+      Diagnostic {
+        range: Range {
+          start: get_position(&error.location.start_position),
+          end: get_position(&error.location.end_position)
+        },
+        severity: DIAGNOSTIC_SEVERITY_ERROR,
+        source: "paperclip".to_string(),
+        message: error.message
+      }
+    }).collect();
+
+    json!({
+      "jsonrpc": "2.0",
+      "method": "textDocument/publishDiagnostics",
+      "params": {
+        "uri": uri,
+        "diagnostics": diagnostics
+      }
+    })
+  }
+}
+
+fn get_position(position: &ast::Position) -> Position {
+
+  // LSP characters are UTF-16 code units
+  Position {
+    line: position.line,
+    character: position.utf16_column
+  }
+}
+
+pub fn read_message<R: BufRead>(reader: &mut R) -> io::Result<Option<String>> {
+  let mut content_length: Option<usize> = None;
+
+  loop {
+    let mut header = String::new();
+    if reader.read_line(&mut header)? == 0 {
+      return Ok(None);
+    }
+    let header = header.trim_end();
+    if header.is_empty() {
+      if content_length.is_some() {
+        break;
+      }
+      continue;
+    }
+    let mut parts = header.splitn(2, ':');
+    let name = parts.next().unwrap_or("").trim();
+    let value = parts.next().unwrap_or("").trim();
+    if name.eq_ignore_ascii_case("Content-Length") {
+      content_length = Some(value.parse().map_err(|_| {
+        io::Error::new(io::ErrorKind::InvalidData, format!("Invalid Content-Length \"{}\"", value))
+      })?);
+    }
+  }
+
+  let content_length = content_length.unwrap();
+  if content_length > MAX_CONTENT_LENGTH {
+    return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Content-Length {} exceeds the maximum of {} bytes", content_length, MAX_CONTENT_LENGTH)));
+  }
+
+  let mut body = vec![0; content_length];
+  reader.read_exact(&mut body)?;
+  String::from_utf8(body).map(Some).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+pub fn write_message<W: Write>(writer: &mut W, message: &Value) -> io::Result<()> {
+  let body = message.to_string();
+  write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+  writer.flush()
+}
+
+// Returns the process exit code
+pub fn serve<R: BufRead, W: Write>(server: &mut LanguageServer, reader: &mut R, writer: &mut W) -> io::Result<i32> {
+  while let Some(body) = read_message(reader)? {
+    let responses = match serde_json::from_str::<Value>(&body) {
+      Ok(message) => server.handle_message(&message),
+      Err(err) => vec![json!({ "jsonrpc": "2.0", "id": Value::Null, "error": { "code": PARSE_ERROR, "message": err.to_string() } })]
+    };
+    for response in responses {
+      write_message(writer, &response)?;
+    }
+    if server.exited {
+      break;
+    }
+  }
+
+  Ok(if server.shutdown_requested { 0 } else { 1 })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::io::Cursor;
+
+  #[test]
+  fn can_publish_diagnostics_for_a_scripted_session() {
+    let uri = "file:///entry.pc";
+    let (exit_code, messages) = run_session(vec![
+      json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": { "capabilities": {} } }),
+      json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} }),
+      json!({ "jsonrpc": "2.0", "method": "textDocument/didOpen", "params": { "textDocument": { "uri": uri, "languageId": "paperclip", "version": 1, "text": "<div>\n  {a.b.c}\n</div>" } } }),
+      json!({ "jsonrpc": "2.0", "method": "textDocument/didChange", "params": { "textDocument": { "uri": uri, "version": 2 }, "contentChanges": [{ "text": "<div>\n  {a}\n</div>" }] } }),
+      json!({ "jsonrpc": "2.0", "id": 2, "method": "shutdown" }),
+      json!({ "jsonrpc": "2.0", "method": "exit" }),
+    ]);
+
+    assert_eq!(exit_code, 0);
+    assert_eq!(messages.len(), 4);
+    assert_eq!(messages[0]["result"]["capabilities"]["textDocumentSync"], 1);
+    assert_eq!(messages[1]["params"], json!({
+      "uri": uri,
+      "diagnostics": [{
+        "range": { "start": { "line": 1, "character": 3 }, "end": { "line": 1, "character": 8 } },
+        "severity": 1,
+        "source": "paperclip",
+        "message": "Cannot access property of undefined"
+      }]
+    }));
+    assert_eq!(messages[2]["params"]["diagnostics"], json!([]));
+    assert_eq!(messages[3], json!({ "jsonrpc": "2.0", "id": 2, "result": null }));
+  }
+
+  #[test]
+  fn can_publish_syntax_errors() {
+    let uri = "file:///entry.pc";
+    let (_, messages) = run_session(vec![
+      json!({ "jsonrpc": "2.0", "method": "textDocument/didOpen", "params": { "textDocument": { "uri": uri, "text": "<div>a<span>b</div>" } } }),
+      json!({ "jsonrpc": "2.0", "method": "textDocument/didClose", "params": { "textDocument": { "uri": uri } } }),
+    ]);
+
+    assert_eq!(messages.len(), 2);
+    assert_eq!(messages[0]["params"]["diagnostics"].as_array().unwrap().len(), 1);
+    assert_eq!(messages[1]["params"]["diagnostics"], json!([]));
+  }
+
+  #[test]
+  fn publishes_diagnostics_at_the_error_positions() {
+    let uri = "file:///fixtures.json";
+    let (_, messages) = run_session(vec![
+      json!({ "jsonrpc": "2.0", "method": "textDocument/didOpen", "params": { "textDocument": { "uri": uri, "text": "{\n  \"a\": b\n}" } } }),
+    ]);
+
+    assert_eq!(messages[0]["params"]["diagnostics"][0]["range"]["start"], json!({ "line": 1, "character": 7 }));
+  }
+
+  #[test]
+  fn rejects_oversized_messages() {
+    let err = read_message(&mut Cursor::new(format!("Content-Length: {}\r\n\r\n", usize::MAX))).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert!(err.to_string().starts_with("Content-Length "), "{}", err);
+  }
+
+  #[test]
+  fn returns_errors_for_unknown_requests() {
+    let (exit_code, messages) = run_session(vec![
+      json!({ "jsonrpc": "2.0", "id": 1, "method": "textDocument/hover", "params": {} }),
+    ]);
+    assert_eq!(exit_code, 1);
+    assert_eq!(messages[0]["error"]["code"], METHOD_NOT_FOUND);
+  }
+
+  fn run_session(messages: Vec<Value>) -> (i32, Vec<Value>) {
+    let mut input = vec![];
+    for message in &messages {
+      write_message(&mut input, message).unwrap();
+    }
+
+    let mut server = LanguageServer::new(Engine::new(
+      Box::new(|_| "".to_string()),
+      Box::new(|_| true),
+      Box::new(|_, uri| uri.to_string())
+    ));
+
+    let mut output = vec![];
+    let exit_code = serve(&mut server, &mut Cursor::new(input), &mut output).unwrap();
+
+    let mut reader = Cursor::new(output);
+    let mut responses = vec![];
+    while let Some(body) = read_message(&mut reader).unwrap() {
+      responses.push(serde_json::from_str(&body).unwrap());
+    }
+    (exit_code, responses)
+  }
+}
@@ -1,5 +1,8 @@
-// see https://github.com/paritytech/jsonrpc/blob/master/pubsub/more-examples/examples/pubsub_ws.rs
-// #![recursion_limit="1024"]
+// Newline-delimited JSON-RPC 2.0 server for the engine. Each line on stdin is a request,
+// and each response is written as a single line to stdout. Files are read from disk.
+// Run with --lsp to start the language server instead, with --dts <file.pc>... to
+// write a TypeScript declaration file next to each file, or with --html [--pretty] <file.pc>...
+// to write a static HTML page next to each file.
 
 #[macro_use]
 extern crate matches;
@@ -9,15 +12,27 @@ mod css;
 mod pc;
 mod js;
 mod engine;
+mod lsp;
 
 use serde::{Deserialize, Serialize};
-// use jsonrpc_core::*;
-// use std::sync::{Arc, Mutex};
-// use std::env;
-// use jsonrpc_tcp_server::*;
-// use ::futures::executor::block_on;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use std::cell::RefCell;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Component, Path, PathBuf};
+use std::rc::Rc;
+use ::futures::executor::block_on;
 
-// use engine::{Engine};
+use engine::{Engine};
+use pc::lint::{LintOptions};
+use pc::runtime::html::{HtmlOptions};
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const ENGINE_ERROR: i64 = -32000;
 
 #[derive(Deserialize, Debug, Serialize)]
 struct LoadParams {
@@ -57,94 +72,439 @@ struct UpdateVirtualFileContentParams {
     content: String
 }
 
+#[derive(Deserialize, Debug, Serialize)]
+struct InferFilePartsParams {
+    uri: String
+}
+
+#[derive(Deserialize, Debug, Serialize)]
+struct GenerateFileDefinitionParams {
+    uri: String
+}
+
+#[derive(Deserialize, Debug)]
+struct EvaluatePartParams {
+    uri: String,
+    part: String,
+    #[serde(default = "empty_props")]
+    props: Value
+}
+
+fn empty_props() -> Value {
+    json!({})
+}
+
+#[derive(Deserialize, Debug)]
+struct GetFilePreviewsParams {
+    uri: String
+}
+
+#[derive(Deserialize, Debug)]
+struct EvaluatePreviewParams {
+    uri: String,
+    name: String
+}
+
+#[derive(Deserialize, Debug)]
+struct RenderFileHtmlParams {
+    uri: String,
+    #[serde(default)]
+    options: HtmlOptions
+}
+
+#[derive(Deserialize, Debug)]
+struct LintFileParams {
+    uri: String,
+
+    // defaults to the options in the nearest pcconfig.json
+    options: Option<LintOptions>
+}
+
+#[derive(Deserialize, Debug)]
+struct Request {
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct RpcError {
+    code: i64,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<Value>
+}
+
+impl RpcError {
+    fn new(code: i64, message: String) -> RpcError {
+        RpcError {
+            code,
+            message,
+            data: None
+        }
+    }
+    fn engine<TData: Serialize>(data: &TData) -> RpcError {
+        RpcError {
+            code: ENGINE_ERROR,
+            message: "Engine error".to_string(),
+            data: Some(serde_json::to_value(data).unwrap())
+        }
+    }
+}
+
+struct Server {
+    engine: Engine,
+
+    // events are queued up until the client calls drain_events
+    events: Rc<RefCell<Vec<Value>>>
+}
+
+impl Server {
+    fn new(mut engine: Engine) -> Server {
+        let events = Rc::new(RefCell::new(vec![]));
+        let listener_events = events.clone();
+        engine.add_listener(Box::new(move |event| {
+            listener_events.borrow_mut().push(serde_json::to_value(event).unwrap());
+        }));
+        Server {
+            engine,
+            events
+        }
+    }
+
+    // Returns None for notifications (requests without an id)
+    fn handle_message(&mut self, message: &str) -> Option<String> {
+        let (id, result) = match serde_json::from_str::<Value>(message) {
+            Ok(value) => {
+                let id = value.get("id").cloned();
+                match serde_json::from_value::<Request>(value) {
+                    Ok(request) => {
+                        let result = self.call(&request.method, request.params);
+                        if request.id == None {
+                            return None;
+                        }
+                        (request.id, result)
+                    },
+                    Err(err) => (id, Err(RpcError::new(INVALID_REQUEST, err.to_string())))
+                }
+            },
+            Err(err) => (None, Err(RpcError::new(PARSE_ERROR, err.to_string())))
+        };
+
+        let response = match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(error) => json!({ "jsonrpc": "2.0", "id": id, "error": error })
+        };
+
+        Some(response.to_string())
+    }
+
+    fn call(&mut self, method: &str, params: Value) -> Result<Value, RpcError> {
+        match method {
+            "load" => {
+                let params: LoadParams = parse_params(params)?;
+                block_on(self.engine.load(&params.uri, params.part)).map_err(|err| RpcError::engine(&err))?;
+                Ok(json!("ok"))
+            },
+            "unload" => {
+                let params: UnloadParams = parse_params(params)?;
+                self.engine.unload(&params.uri);
+                Ok(json!("ok"))
+            },
+            "parse_file" => {
+                let params: ParseFileParams = parse_params(params)?;
+                assert_file_exists(&params.uri)?;
+                let node = block_on(self.engine.parse_file(&params.uri)).map_err(|err| RpcError::engine(&err))?;
+                Ok(serde_json::to_value(&node).unwrap())
+            },
+            "parse_content" => {
+                let params: ParseContentParams = parse_params(params)?;
+                let node = block_on(self.engine.parse_content(&params.content)).map_err(|err| RpcError::engine(&err))?;
+                Ok(serde_json::to_value(&node).unwrap())
+            },
+            "evaluate_file_styles" => {
+                let params: EvaluateFileStylesParams = parse_params(params)?;
+                assert_file_exists(&params.uri)?;
+                let sheet = block_on(self.engine.evaluate_file_styles(&params.uri)).map_err(|err| RpcError::engine(&err))?;
+                Ok(serde_json::to_value(&sheet).unwrap())
+            },
+            "evaluate_content_styles" => {
+                let params: EvaluateContentStylesParams = parse_params(params)?;
+                let sheet = block_on(self.engine.evaluate_content_styles(&params.content, &params.uri)).map_err(|err| RpcError::engine(&err))?;
+                Ok(serde_json::to_value(&sheet).unwrap())
+            },
+            "lint_file" => {
+                let params: LintFileParams = parse_params(params)?;
+                let diagnostics = block_on(self.engine.lint_file(&params.uri, params.options)).map_err(|err| RpcError::engine(&err))?;
+                Ok(serde_json::to_value(&diagnostics).unwrap())
+            },
+            "infer_file_parts" => {
+                let params: InferFilePartsParams = parse_params(params)?;
+                let parts = block_on(self.engine.infer_file_parts(&params.uri)).map_err(|err| RpcError::engine(&err))?;
+                Ok(serde_json::to_value(&parts).unwrap())
+            },
+            "generate_file_definition" => {
+                let params: GenerateFileDefinitionParams = parse_params(params)?;
+                let definition = block_on(self.engine.generate_file_definition(&params.uri)).map_err(|err| RpcError::engine(&err))?;
+                Ok(serde_json::to_value(&definition).unwrap())
+            },
+            "evaluate_part" => {
+                let params: EvaluatePartParams = parse_params(params)?;
+                let node = block_on(self.engine.evaluate_part(&params.uri, &params.part, &params.props)).map_err(|err| RpcError::engine(&err))?;
+                Ok(serde_json::to_value(&node).unwrap())
+            },
+            "get_file_previews" => {
+                let params: GetFilePreviewsParams = parse_params(params)?;
+                let previews = block_on(self.engine.get_file_previews(&params.uri)).map_err(|err| RpcError::engine(&err))?;
+                Ok(serde_json::to_value(&previews).unwrap())
+            },
+            "evaluate_preview" => {
+                let params: EvaluatePreviewParams = parse_params(params)?;
+                let node = block_on(self.engine.evaluate_preview(&params.uri, &params.name)).map_err(|err| RpcError::engine(&err))?;
+                Ok(serde_json::to_value(&node).unwrap())
+            },
+            "render_file_html" => {
+                let params: RenderFileHtmlParams = parse_params(params)?;
+                let html = block_on(self.engine.render_file_html(&params.uri, &params.options)).map_err(|err| RpcError::engine(&err))?;
+                Ok(serde_json::to_value(&html).unwrap())
+            },
+            "update_virtual_file_content" => {
+                let params: UpdateVirtualFileContentParams = parse_params(params)?;
+                block_on(self.engine.update_virtual_file_content(&params.uri, &params.content)).map_err(|err| RpcError::engine(&err))?;
+                Ok(json!("ok"))
+            },
+            "drain_events" => {
+                let events: Vec<Value> = self.events.borrow_mut().drain(..).collect();
+                Ok(Value::Array(events))
+            },
+            _ => Err(RpcError::new(METHOD_NOT_FOUND, format!("Method \"{}\" not found", method)))
+        }
+    }
+}
+
+fn parse_params<TParams: DeserializeOwned>(params: Value) -> Result<TParams, RpcError> {
+    serde_json::from_value(params).map_err(|err| RpcError::new(INVALID_PARAMS, err.to_string()))
+}
+
+fn assert_file_exists(uri: &String) -> Result<(), RpcError> {
+    if file_exists(uri) {
+        Ok(())
+    } else {
+        Err(RpcError::new(ENGINE_ERROR, format!("File \"{}\" does not exist", uri)))
+    }
+}
+
+fn uri_to_path(uri: &String) -> PathBuf {
+    PathBuf::from(uri.trim_start_matches("file://"))
+}
+
+fn read_file(uri: &String) -> String {
+    fs::read_to_string(uri_to_path(uri)).unwrap_or_default()
+}
+
+fn file_exists(uri: &String) -> bool {
+    uri_to_path(uri).is_file()
+}
+
+fn resolve_file(from_uri: &String, relative_path: &String) -> String {
+    let prefix = if from_uri.starts_with("file://") { "file://" } else { "" };
+    let from_path = uri_to_path(from_uri);
+    let joined = from_path.parent().unwrap_or(Path::new("")).join(relative_path);
+
+    // normalize without touching the file system since the file may not exist yet
+    let mut resolved = PathBuf::new();
+    for component in joined.components() {
+        match component {
+            Component::CurDir => {},
+            Component::ParentDir => { resolved.pop(); },
+            _ => resolved.push(component.as_os_str())
+        }
+    }
+
+    format!("{}{}", prefix, resolved.to_string_lossy())
+}
+
+fn write_definition_file(path: &String, engine: &mut Engine) -> Result<(), String> {
+    if !file_exists(path) {
+        return Err(format!("File \"{}\" does not exist", path));
+    }
+    let definition = block_on(engine.generate_file_definition(path))
+    .map_err(|err| format!("Unable to load \"{}\": {}", path, serde_json::to_string(&err).unwrap()))?
+    .ok_or_else(|| format!("Can't generate a definition for \"{}\"", path))?;
+
+    let definition_path = format!("{}.d.ts", path);
+    fs::write(&definition_path, definition).map_err(|err| format!("Unable to write \"{}\": {}", definition_path, err))?;
+    println!("{}", definition_path);
+    Ok(())
+}
+
+fn write_html_file(path: &String, options: &HtmlOptions, engine: &mut Engine) -> Result<(), String> {
+    if !file_exists(path) {
+        return Err(format!("File \"{}\" does not exist", path));
+    }
+    let html = block_on(engine.render_file_html(path, options))
+    .map_err(|err| format!("Unable to render \"{}\": {}", path, serde_json::to_string(&err).unwrap()))?
+    .ok_or_else(|| format!("\"{}\" doesn't render anything", path))?;
+
+    let html_path = format!("{}.html", path);
+    fs::write(&html_path, html).map_err(|err| format!("Unable to write \"{}\": {}", html_path, err))?;
+    println!("{}", html_path);
+    Ok(())
+}
+
 fn main() {
-    // let args: Vec<String> = env::args().collect();
-
-    // let port: String = args[1].to_string();
-    // let http_path: Option<String> = if args.len() > 2 { Some(args[2].to_string()) } else { None};
-
-    // // TODO - http opts
-    // let engine_mutex = Arc::new(Mutex::new(Engine::new(http_path)));
-
-    // let mut io = IoHandler::new();
-    // let load_engine_mutex = engine_mutex.clone();
-	// io.add_method("load", move |params: Params| {
-    //     let params: LoadParams = params.parse().unwrap();
-    //     block_on(load_engine_mutex.lock().unwrap().load(&params.uri, params.part));
-	// 	Ok(Value::String("ok".into()))
-    // });
-
-    // let unload_engine_mutex = engine_mutex.clone();
-	// io.add_method("unload", move |params: Params| {
-	// 	let params: UnloadParams = params.parse().unwrap();
-    //     unload_engine_mutex.lock().unwrap().unload(params.uri);
-	// 	Ok(Value::String("ok".into()))
-    // });
-
-    // let parse_file_engine_mutex = engine_mutex.clone();
-	// io.add_method("parse_file", move |params: Params| {
-    //     let params: ParseFileParams = params.parse().unwrap();
-    //     let result = block_on(parse_file_engine_mutex.lock().unwrap().parse_file(&params.uri));
-    //     let json = match result {
-    //         Ok(node) => serde_json::to_string(&node).unwrap(),
-    //         Err(error) => format!("{{\"error\":{}}}", serde_json::to_string(&error).unwrap())
-    //     };
-	// 	Ok(Value::String(json.to_string()))
-    // });
-
-    // let parse_content_engine_mutex = engine_mutex.clone();
-	// io.add_method("parse_content", move |params: Params| {
-    //     let params: ParseContentParams = params.parse().unwrap();
-    //     let result = block_on(parse_content_engine_mutex.lock().unwrap().parse_content(&params.content));
-    //     let json = match result {
-    //         Ok(node) => serde_json::to_string(&node).unwrap(),
-    //         Err(error) => format!("{{\"error\":{}}}", serde_json::to_string(&error).unwrap())
-    //     };
-	// 	Ok(Value::String(json.into()))
-    // });
-
-
-    // let evaluate_file_styles_mutex = engine_mutex.clone();
-	// io.add_method("evaluate_file_styles", move |params: Params| {
-    //     let params: EvaluateFileStylesParams = params.parse().unwrap();
-    //     let result = block_on(evaluate_file_styles_mutex.lock().unwrap().evaluate_file_styles(&params.uri));
-    //     let json = match result {
-    //         Ok(sheet) => serde_json::to_string(&sheet).unwrap(),
-    //         Err(error) => format!("{{\"error\":{}}}", serde_json::to_string(&error).unwrap())
-    //     };
-	// 	Ok(Value::String(json.into()))
-    // });
-    
-
-    // let evaluate_file_styles_mutex = engine_mutex.clone();
-	// io.add_method("evaluate_content_styles", move |params: Params| {
-    //     let params: EvaluateContentStylesParams = params.parse().unwrap();
-    //     let result = block_on(evaluate_file_styles_mutex.lock().unwrap().evaluate_content_styles(&params.content, &params.uri));
-    //     let json = match result {
-    //         Ok(sheet) => serde_json::to_string(&sheet).unwrap(),
-    //         Err(error) => format!("{{\"error\":{}}}", serde_json::to_string(&error).unwrap())
-    //     };
-	// 	Ok(Value::String(json.into()))
-    // });
-    
-    // let update_virtual_file_content_engine_mutex = engine_mutex.clone();
-	// io.add_method("update_virtual_file_content", move |params: Params| {
-    //     let params: UpdateVirtualFileContentParams = params.parse().unwrap();
-    //     block_on(update_virtual_file_content_engine_mutex.lock().unwrap().update_virtual_file_content(&params.uri, &params.content));
-	// 	Ok(Value::String("ok".into()))
-    // });
-    
-    // let drain_events_engine_mutex = engine_mutex.clone();
-	// io.add_method("drain_events", move |_params| {
-    //     let events = drain_events_engine_mutex.lock().unwrap().drain_events();
-	// 	Ok(Value::String(serde_json::to_string(&events).unwrap()))
-    // });
-
-	// let server = ServerBuilder::new(io)
-    // .start(&format!("127.0.0.1:{}", port).parse().unwrap())
-    // .expect("Server must start with no issues");
-
-    // println!("--READY--");
-
-    // server.wait();
+    let engine = Engine::new(
+        Box::new(read_file),
+        Box::new(file_exists),
+        Box::new(resolve_file)
+    );
+
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    if args.first().map(|arg| arg.as_str()) == Some("--dts") {
+        let mut engine = engine;
+        let mut exit_code = 0;
+        for path in &args[1..] {
+            if let Err(message) = write_definition_file(path, &mut engine) {
+                eprintln!("{}", message);
+                exit_code = 1;
+            }
+        }
+        std::process::exit(exit_code);
+    }
+
+    if args.first().map(|arg| arg.as_str()) == Some("--html") {
+        let mut engine = engine;
+        let options = HtmlOptions {
+            pretty: args.iter().any(|arg| arg == "--pretty")
+        };
+        let mut exit_code = 0;
+        for path in args[1..].iter().filter(|arg| !arg.starts_with("--")) {
+            if let Err(message) = write_html_file(path, &options, &mut engine) {
+                eprintln!("{}", message);
+                exit_code = 1;
+            }
+        }
+        std::process::exit(exit_code);
+    }
+
+    if args.iter().any(|arg| arg == "--lsp") {
+        let mut server = lsp::LanguageServer::new(engine);
+        let exit_code = lsp::serve(&mut server, &mut stdin.lock(), &mut stdout.lock()).expect("Unable to communicate with the client");
+        std::process::exit(exit_code);
+    }
+
+    let mut server = Server::new(engine);
+
+    for line in stdin.lock().lines() {
+        let line = line.expect("Unable to read from stdin");
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = server.handle_message(&line) {
+            let mut out = stdout.lock();
+            writeln!(out, "{}", response).unwrap();
+            out.flush().unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_resolve_relative_files() {
+        assert_eq!(resolve_file(&"file:///a/b/c.pc".to_string(), &"../d/./e.pc".to_string()), "file:///a/d/e.pc");
+        assert_eq!(resolve_file(&"/a/b/c.pc".to_string(), &"./d.css".to_string()), "/a/b/d.css");
+    }
+
+    #[test]
+    fn can_load_files_and_drain_events() {
+        let mut server = Server::new(Engine::new(
+            Box::new(|_| "<div>{message}</div>".to_string()),
+            Box::new(|_| true),
+            Box::new(|_, uri| uri.to_string())
+        ));
+
+        let response: Value = serde_json::from_str(&server.handle_message(r#"{"jsonrpc":"2.0","id":1,"method":"load","params":{"uri":"file.pc"}}"#).unwrap()).unwrap();
+        assert_eq!(response, json!({ "jsonrpc": "2.0", "id": 1, "result": "ok" }));
+
+        let response: Value = serde_json::from_str(&server.handle_message(r#"{"jsonrpc":"2.0","id":2,"method":"drain_events"}"#).unwrap()).unwrap();
+        let kinds: Vec<&Value> = response["result"].as_array().unwrap().iter().map(|event| &event["kind"]).collect();
+        assert_eq!(kinds, vec!["NodeParsed", "Evaluated"]);
+
+        let response: Value = serde_json::from_str(&server.handle_message(r#"{"jsonrpc":"2.0","id":3,"method":"drain_events"}"#).unwrap()).unwrap();
+        assert_eq!(response["result"], json!([]));
+    }
+
+    #[test]
+    fn can_evaluate_parts_with_props() {
+        let mut server = Server::new(Engine::new(
+            Box::new(|_| "<part id=\"a\"><span>{b.c}</span></part>".to_string()),
+            Box::new(|_| true),
+            Box::new(|_, uri| uri.to_string())
+        ));
+
+        let response: Value = serde_json::from_str(&server.handle_message(r#"{"jsonrpc":"2.0","id":1,"method":"evaluate_part","params":{"uri":"file.pc","part":"a","props":{"b":{"c":"d"}}}}"#).unwrap()).unwrap();
+        assert_eq!(response["result"]["kind"], "Element");
+        assert_eq!(response["result"]["children"][1]["value"], "d");
+    }
+
+    #[test]
+    fn can_list_and_evaluate_named_previews() {
+        let mut server = Server::new(Engine::new(
+            Box::new(|_| "<preview name=\"mobile\" width=\"375\"><span>a</span></preview><preview name=\"desktop\"><span>b</span></preview>".to_string()),
+            Box::new(|_| true),
+            Box::new(|_, uri| uri.to_string())
+        ));
+
+        let response: Value = serde_json::from_str(&server.handle_message(r#"{"jsonrpc":"2.0","id":1,"method":"get_file_previews","params":{"uri":"file.pc"}}"#).unwrap()).unwrap();
+        assert_eq!(response["result"], json!([{ "name": "mobile", "width": "375", "height": null }, { "name": "desktop", "width": null, "height": null }]));
+
+        let response: Value = serde_json::from_str(&server.handle_message(r#"{"jsonrpc":"2.0","id":2,"method":"evaluate_preview","params":{"uri":"file.pc","name":"desktop"}}"#).unwrap()).unwrap();
+        assert_eq!(response["result"]["children"][1]["value"], "b");
+    }
+
+    #[test]
+    fn can_render_files_as_html() {
+        let mut server = Server::new(Engine::new(
+            Box::new(|_| "<h1>{'Fish & Chips'}</h1>".to_string()),
+            Box::new(|_| true),
+            Box::new(|_, uri| uri.to_string())
+        ));
+
+        let response: Value = serde_json::from_str(&server.handle_message(r#"{"jsonrpc":"2.0","id":1,"method":"render_file_html","params":{"uri":"file.pc","options":{"pretty":true}}}"#).unwrap()).unwrap();
+        let html = response["result"].as_str().unwrap();
+        assert!(html.starts_with("<!DOCTYPE html>\n<html>\n"), "{}", html);
+        assert!(html.contains(">Fish &amp; Chips</h1>\n  </body>"), "{}", html);
+    }
+
+    #[test]
+    fn returns_errors_for_bad_requests() {
+        let mut server = Server::new(Engine::new(
+            Box::new(|_| "".to_string()),
+            Box::new(|_| true),
+            Box::new(|_, uri| uri.to_string())
+        ));
+
+        let response: Value = serde_json::from_str(&server.handle_message("{").unwrap()).unwrap();
+        assert_eq!(response["error"]["code"], PARSE_ERROR);
+
+        let response: Value = serde_json::from_str(&server.handle_message(r#"{"jsonrpc":"2.0","id":1,"method":"nope"}"#).unwrap()).unwrap();
+        assert_eq!(response["error"]["code"], METHOD_NOT_FOUND);
+
+        let response: Value = serde_json::from_str(&server.handle_message(r#"{"jsonrpc":"2.0","id":2,"method":"load","params":{}}"#).unwrap()).unwrap();
+        assert_eq!(response["error"]["code"], INVALID_PARAMS);
+
+        let response: Value = serde_json::from_str(&server.handle_message(r#"{"jsonrpc":"2.0","id":3,"method":"parse_content","params":{"content":"<div"}}"#).unwrap()).unwrap();
+        assert_eq!(response["error"]["code"], ENGINE_ERROR);
+        assert_eq!(response["error"]["data"]["kind"], "EndOfFile");
+
+        assert_eq!(server.handle_message(r#"{"jsonrpc":"2.0","method":"unload","params":{"uri":"file.pc"}}"#), None);
+    }
 }
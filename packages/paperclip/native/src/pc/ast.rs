@@ -46,7 +46,22 @@ pub struct Slot {
   // !{slot}
   #[serde(rename = "omitFromCompilation")]
   pub omit_from_compilation: bool,
-  pub script: js_ast::Statement
+  pub script: js_ast::Statement,
+  pub location: Location
+}
+
+impl Node {
+  pub fn get_location(&self) -> &Location {
+    match self {
+      Node::Text(value) => &value.location,
+      Node::Comment(value) => &value.location,
+      Node::Element(value) => &value.location,
+      Node::Fragment(value) => &value.location,
+      Node::StyleElement(value) => &value.location,
+      Node::Slot(value) => &value.location,
+      Node::Block(value) => value.get_location()
+    }
+  }
 }
 
 impl fmt::Display for Node {
@@ -70,6 +85,15 @@ pub enum Block {
   Each(EachBlock)
 }
 
+impl Block {
+  pub fn get_location(&self) -> &Location {
+    match self {
+      Block::Conditional(block) => block.get_location(),
+      Block::Each(block) => &block.location
+    }
+  }
+}
+
 #[derive(Debug, PartialEq, Serialize, Clone)]
 #[serde(tag = "conditionalBlockKind")]
 pub enum ConditionalBlock {
@@ -77,17 +101,28 @@ pub enum ConditionalBlock {
   FinalBlock(FinalBlock)
 }
 
+impl ConditionalBlock {
+  pub fn get_location(&self) -> &Location {
+    match self {
+      ConditionalBlock::PassFailBlock(block) => &block.location,
+      ConditionalBlock::FinalBlock(block) => &block.location
+    }
+  }
+}
+
 
 #[derive(Debug, PartialEq, Serialize, Clone)]
 pub struct PassFailBlock {
   pub condition: js_ast::Statement,
   pub body: Option<Box<Node>>,
-  pub fail: Option<Box<ConditionalBlock>>
+  pub fail: Option<Box<ConditionalBlock>>,
+  pub location: Location
 }
 
 #[derive(Debug, PartialEq, Serialize, Clone)]
 pub struct FinalBlock {
   pub body: Option<Box<Node>>,
+  pub location: Location
 }
 
 #[derive(Debug, PartialEq, Serialize, Clone)]
@@ -102,6 +137,7 @@ pub struct EachBlock {
   #[serde(rename = "keyName")]
   pub key_name: Option<String>,
   pub body: Option<Box<Node>>,
+  pub location: Location
 }
 
 #[derive(Debug, PartialEq, Serialize, Clone)]
@@ -116,6 +152,33 @@ impl fmt::Display for AttributeStringValue {
   }
 }
 
+// class="btn {variant}"
+#[derive(Debug, PartialEq, Serialize, Clone)]
+pub struct AttributeDynamicStringValue {
+  pub values: Vec<AttributeDynamicStringPart>,
+  pub location: Location
+}
+
+impl fmt::Display for AttributeDynamicStringValue {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "\"")?;
+    for value in &self.values {
+      match value {
+        AttributeDynamicStringPart::Literal(value) => write!(f, "{}", value.value)?,
+        AttributeDynamicStringPart::Slot(script) => write!(f, "{{{}}}", script.to_string())?
+      };
+    }
+    write!(f, "\"")
+  }
+}
+
+#[derive(Debug, PartialEq, Serialize, Clone)]
+#[serde(tag = "partKind")]
+pub enum AttributeDynamicStringPart {
+  Literal(AttributeStringValue),
+  Slot(js_ast::Statement)
+}
+
 pub fn fmt_attributes(attributes: &Vec<Attribute>, f: &mut fmt::Formatter) -> fmt::Result {
   for attribute in attributes {
     write!(f, " {}", attribute.to_string())?;
@@ -154,6 +217,16 @@ pub enum Attribute {
   KeyValueAttribute(KeyValueAttribute)
 }
 
+impl Attribute {
+  pub fn get_location(&self) -> &Location {
+    match self {
+      Attribute::ShorthandAttribute(attr) => &attr.location,
+      Attribute::KeyValueAttribute(attr) => &attr.location,
+      Attribute::SpreadAttribute(attr) => &attr.location
+    }
+  }
+}
+
 impl fmt::Display for Attribute {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
     match self {
@@ -171,6 +244,7 @@ pub struct SpreadAttribute {
   #[serde(rename = "omitFromCompilation")]
   pub omit_from_compilation: bool,
   pub script: js_ast::Statement,
+  pub location: Location
 }
 
 impl fmt::Display for SpreadAttribute {
@@ -183,6 +257,7 @@ impl fmt::Display for SpreadAttribute {
 #[derive(Debug, PartialEq, Serialize, Clone)]
 pub struct ShorthandAttribute {
   pub reference: js_ast::Statement,
+  pub location: Location
 }
 
 impl ShorthandAttribute {
@@ -212,6 +287,7 @@ impl fmt::Display for ShorthandAttribute {
 pub struct KeyValueAttribute {
   pub name: String,
   pub value: Option<AttributeValue>,
+  pub location: Location
 }
 
 impl fmt::Display for KeyValueAttribute {
@@ -229,6 +305,7 @@ impl fmt::Display for KeyValueAttribute {
 #[serde(tag = "attrValueKind")]
 pub enum AttributeValue {
   String(AttributeStringValue),
+  DynamicString(AttributeDynamicStringValue),
   Slot(js_ast::Statement)
 }
 
@@ -236,6 +313,7 @@ impl fmt::Display for AttributeValue {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
     match &self {
       AttributeValue::String(value) => { write!(f, "{}", value.to_string()) },
+      AttributeValue::DynamicString(value) => { write!(f, "{}", value.to_string()) },
       AttributeValue::Slot(script) => { write!(f, "{{{}}}", script.to_string()) },
     }
   }
@@ -245,6 +323,7 @@ impl fmt::Display for AttributeValue {
 pub struct StyleElement {
   pub attributes: Vec<Attribute>,
   pub sheet: css_ast::Sheet,
+  pub location: Location
 }
 
 impl fmt::Display for StyleElement {
@@ -258,7 +337,8 @@ impl fmt::Display for StyleElement {
 
 #[derive(Debug, PartialEq, Serialize, Clone)]
 pub struct Fragment {
-  pub children: Vec<Node>
+  pub children: Vec<Node>,
+  pub location: Location
 }
 
 impl fmt::Display for Fragment {
@@ -315,21 +395,31 @@ pub fn get_tag_namespace<'a>(element: &'a Element) -> Option<String> {
 }
 
 pub fn get_parts<'a>(root_expr: &'a Node) -> Vec<&'a Element> {
-  let mut parts = vec![];
+  match root_expr {
+
+    // documents that only contain a part are parsed without a fragment
+    Node::Element(element) if element.tag_name == "part" => vec![element],
+    _ => get_children(root_expr).map(|children| {
+      children.iter().filter_map(|child| match child {
+        Node::Element(element) if element.tag_name == "part" => Some(element),
+        _ => None
+      }).collect()
+    }).unwrap_or_default()
+  }
+}
 
-  let children = get_children(root_expr);
+pub fn get_previews<'a>(root_expr: &'a Node) -> Vec<&'a Element> {
+  match root_expr {
 
-  if children != None {
-    for child in children.unwrap() {
-      if let Node::Element(element) = &child {
-        if element.tag_name == "part" {
-          parts.push(element);
-        }
-      }
-    }
+    // documents that only contain a preview are parsed without a fragment
+    Node::Element(element) if element.tag_name == "preview" => vec![element],
+    _ => get_children(root_expr).map(|children| {
+      children.iter().filter_map(|child| match child {
+        Node::Element(element) if element.tag_name == "preview" => Some(element),
+        _ => None
+      }).collect()
+    }).unwrap_or_default()
   }
-
-  parts
 }
 
 pub fn get_part_by_id<'a>(id: &String, root_expr: &'a Node) -> Option<&'a Element> {
@@ -412,3 +502,36 @@ pub fn get_part_ids<'a>(root_expr: &'a Node) -> Vec<&'a String> {
   }
   ids
 }
+
+// elements without children or a close tag
+pub fn is_void_tag_name<'a>(tag_name: &'a str) -> bool {
+  match tag_name {
+    "area" | 
+    "base" | 
+    "basefont" | 
+    "bgsound" | 
+    "br" | 
+    "col" | 
+    "command" | 
+    "embed" | 
+    "frame" |
+    "hr" |
+    "image" |
+    "import" |
+    "img" |
+    "input" |
+    "isindex" |
+    "keygen" |
+    "link" |
+    "menuitem" |
+    "meta" |
+    "property" |
+    "logic" |
+    "nextid" |
+    "param" |
+    "source" |
+    "track" |
+    "wbr" => true,
+    _ => false,
+  }
+}
@@ -0,0 +1,197 @@
+// Generates TypeScript declarations (.d.ts) for the React module that a .pc
+// file is compiled to. Each <part /> is exported as a component, and the
+// default part (or the document itself) is the default export.
+
+use super::ast;
+use super::infer::{infer, ShapeInference, Inference};
+
+const DEFAULT_PART_ID: &str = "default";
+const NO_COMPILE_ATTRIBUTE_NAME: &str = "no-compile";
+const DEFAULT_PROP_TYPE: &str = "String | boolean | Number | Object | ReactNode";
+
+pub fn generate_definition(root: &ast::Node) -> String {
+  let mut buffer = String::new();
+
+  buffer.push_str("import {ReactNode, ReactHTML, Factory, InputHTMLAttributes, ClassAttributes} from \"react\";\n\n");
+  buffer.push_str("type ElementProps = InputHTMLAttributes<HTMLInputElement> & ClassAttributes<HTMLInputElement>;\n\n");
+  buffer.push_str("export declare const styled: (tag: keyof ReactHTML | Factory<ElementProps>, defaultProps?: ElementProps) => Factory<ElementProps>;\n\n");
+
+  let parts = get_parts(root);
+  let mut default_part_option = None;
+
+  for part in &parts {
+    let id = ast::get_attribute_value("id", part).unwrap();
+    if id == DEFAULT_PART_ID {
+      default_part_option = Some(*part);
+      continue;
+    }
+    if ast::get_attribute(NO_COMPILE_ATTRIBUTE_NAME, part) != None {
+      continue;
+    }
+    let component_name = pascal_case(id);
+    let props_name = format!("{}Props", component_name);
+    write_props_type(&props_name, &infer(&ast::Node::Element((*part).clone())), &mut buffer);
+    buffer.push_str(&format!("export declare const {}: Factory<{}>;\n\n", component_name, props_name));
+  }
+
+  let default_props = match default_part_option {
+    Some(part) => Some(infer(&ast::Node::Element(part.clone()))),
+
+    // the document is only renderable if it contains something other than parts & previews
+    None if get_root_children(root).iter().any(|child| is_visible_node(child)) => Some(infer(root)),
+    None => None
+  };
+
+  if let Some(props) = default_props {
+    write_props_type("Props", &props, &mut buffer);
+    buffer.push_str("declare const View: Factory<Props>;\n");
+    buffer.push_str("export default View;\n");
+  }
+
+  buffer
+}
+
+fn write_props_type(name: &str, props: &ShapeInference, buffer: &mut String) {
+  buffer.push_str(&format!("export type {} = {{\n", name));
+  for (key, inference) in &props.properties {
+
+    // children are always optional
+    if key == "children" {
+      continue;
+    }
+    buffer.push_str(&format!("  {}: ", get_property_name(key)));
+    write_inference(inference, key, 1, buffer);
+    buffer.push_str(";\n");
+  }
+  buffer.push_str("  children?: ReactNode;\n");
+  buffer.push_str("};\n\n");
+}
+
+fn write_inference(inference: &Inference, property: &str, depth: usize, buffer: &mut String) {
+  match inference {
+    Inference::Any => {
+      buffer.push_str(if is_event_handler(property) { "Function" } else { DEFAULT_PROP_TYPE });
+    },
+    Inference::Array(array) => {
+      buffer.push_str("Array<");
+      write_inference(&array.value, property, depth, buffer);
+      buffer.push_str(">");
+    },
+    Inference::Shape(shape) if shape.properties.len() == 0 => {
+      buffer.push_str("{}");
+      if shape.from_spread {
+        buffer.push_str(" & ElementProps");
+      }
+    },
+    Inference::Shape(shape) => {
+      let indent = "  ".repeat(depth);
+      buffer.push_str("{\n");
+      for (key, value) in &shape.properties {
+        buffer.push_str(&format!("{}  {}: ", indent, get_property_name(key)));
+        write_inference(value, key, depth + 1, buffer);
+        buffer.push_str(";\n");
+      }
+      buffer.push_str(&format!("{}}}", indent));
+      if shape.from_spread {
+        buffer.push_str(" & ElementProps");
+      }
+    }
+  }
+}
+
+// onClick, onChange, ...
+fn is_event_handler(property: &str) -> bool {
+  property.len() > 2 && property.starts_with("on")
+}
+
+fn get_property_name(key: &String) -> String {
+  let is_identifier = key.chars().enumerate().all(|(i, c)| {
+    c == '_' || c == '$' || c.is_ascii_alphabetic() || (i > 0 && c.is_ascii_digit())
+  });
+
+  if is_identifier {
+    key.to_string()
+  } else {
+    format!("\"{}\"", key)
+  }
+}
+
+fn get_parts<'a>(root: &'a ast::Node) -> Vec<&'a ast::Element> {
+  ast::get_parts(root).into_iter().filter(|part| ast::get_attribute_value("id", part) != None).collect()
+}
+
+fn get_root_children<'a>(root: &'a ast::Node) -> Vec<&'a ast::Node> {
+  match root {
+    ast::Node::Fragment(fragment) => fragment.children.iter().collect(),
+    _ => vec![root]
+  }
+}
+
+fn is_visible_node(node: &ast::Node) -> bool {
+  match node {
+    ast::Node::Element(element) => match element.tag_name.as_str() {
+      "import" | "logic" | "meta" | "part" | "preview" => false,
+      _ => true
+    },
+    ast::Node::Text(_) | ast::Node::Fragment(_) | ast::Node::Slot(_) | ast::Node::Block(_) => true,
+    _ => false
+  }
+}
+
+// my-button -> MyButton
+fn pascal_case(value: &String) -> String {
+  value.split(|c: char| !c.is_ascii_alphanumeric()).map(|word| {
+    let mut chars = word.chars();
+    match chars.next() {
+      Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+      None => "".to_string()
+    }
+  }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::pc::parser::*;
+
+  #[test]
+  fn can_generate_a_component_for_each_part() {
+    let definition = generate_definition(&parse("
+      <part id=\"my-button\">
+        <button onClick={onClick} {...buttonProps}>{label}{children}</button>
+      </part>
+      <part id=\"default\">
+        {#each items as item}{item.name}{/}
+      </part>
+      <part id=\"hidden\" no-compile></part>
+      <preview><my-button /></preview>
+    ").unwrap());
+
+    assert!(definition.ends_with("\n\nexport type MyButtonProps = {
+  buttonProps: {} & ElementProps;
+  label: String | boolean | Number | Object | ReactNode;
+  onClick: Function;
+  children?: ReactNode;
+};
+
+export declare const MyButton: Factory<MyButtonProps>;
+
+export type Props = {
+  items: Array<{
+    name: String | boolean | Number | Object | ReactNode;
+  }>;
+  children?: ReactNode;
+};
+
+declare const View: Factory<Props>;
+export default View;
+"), "{}", definition);
+  }
+
+  #[test]
+  fn uses_the_document_as_the_default_export() {
+    assert!(generate_definition(&parse("<div>{e}</div>").unwrap()).contains("export type Props = {\n  e: "));
+    assert!(!generate_definition(&parse("<part id=\"a\">{b}</part><preview><a /></preview>").unwrap()).contains("export default"));
+    assert!(generate_definition(&parse("<part id=\"a\">{b}</part>").unwrap()).contains("export declare const A: Factory<AProps>;"));
+  }
+}
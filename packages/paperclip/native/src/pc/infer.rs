@@ -0,0 +1,368 @@
+// Infers the shape of the props that a part (or document) expects based on how
+// they're referenced in the template.
+
+use std::collections::{BTreeMap, HashMap};
+use serde::{Serialize};
+use crate::js::ast as js_ast;
+use super::ast;
+
+#[derive(Debug, PartialEq, Serialize, Clone, Default)]
+pub struct ShapeInference {
+
+  // true if the props are spread onto an element
+  #[serde(rename = "fromSpread")]
+  pub from_spread: bool,
+  pub properties: BTreeMap<String, Inference>
+}
+
+#[derive(Debug, PartialEq, Serialize, Clone)]
+pub struct ArrayInference {
+  pub value: Box<Inference>
+}
+
+#[derive(Debug, PartialEq, Serialize, Clone)]
+#[serde(tag = "kind")]
+pub enum Inference {
+  Shape(ShapeInference),
+  Array(ArrayInference),
+  Any
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct PartInference {
+  pub id: String,
+  pub props: ShapeInference
+}
+
+/*
+{#each items as item, i}
+  {#each item.friends as friend, j}
+    {friend.name}
+  {/}
+{/}
+
+scope: {
+  item: ["items"],
+  i: [],
+  friend: ["items", "friends"],
+  j: []
+}
+*/
+
+struct Context {
+
+  // paths that local variables point to. An empty path means that the
+  // variable is created within the template (each keys).
+  scope: HashMap<String, Vec<String>>,
+  inference: Inference
+}
+
+pub fn infer(node: &ast::Node) -> ShapeInference {
+  let mut context = Context {
+    scope: HashMap::new(),
+    inference: Inference::Shape(ShapeInference::default())
+  };
+
+  infer_node(node, true, &mut context);
+
+  match context.inference {
+    Inference::Shape(shape) => shape,
+    _ => ShapeInference::default()
+  }
+}
+
+pub fn infer_parts(root: &ast::Node) -> Vec<PartInference> {
+  ast::get_parts(root).into_iter().filter_map(|part| {
+    let id = ast::get_attribute_value("id", part)?;
+    Some(PartInference {
+      id: id.to_string(),
+      props: infer(&ast::Node::Element(part.clone()))
+    })
+  }).collect()
+}
+
+fn infer_node(node: &ast::Node, is_root: bool, context: &mut Context) {
+  match node {
+    ast::Node::Element(element) => infer_element(element, is_root, context),
+    ast::Node::Slot(slot) => infer_statement(&slot.script, Inference::Any, context),
+    ast::Node::Fragment(fragment) => infer_children(&fragment.children, context),
+    ast::Node::Block(ast::Block::Each(block)) => infer_each_block(block, context),
+    ast::Node::Block(ast::Block::Conditional(block)) => infer_conditional_block(block, context),
+    _ => {}
+  }
+}
+
+fn infer_element(element: &ast::Element, is_root: bool, context: &mut Context) {
+
+  // previews & other parts provide their own props
+  if element.tag_name == "preview" || (element.tag_name == "part" && !is_root) {
+    return;
+  }
+
+  for attribute in &element.attributes {
+    match attribute {
+      ast::Attribute::KeyValueAttribute(attr) => {
+        match &attr.value {
+          Some(ast::AttributeValue::Slot(script)) => infer_statement(script, Inference::Any, context),
+          Some(ast::AttributeValue::DynamicString(value)) => {
+            for part in &value.values {
+              if let ast::AttributeDynamicStringPart::Slot(script) = part {
+                infer_statement(script, Inference::Any, context);
+              }
+            }
+          },
+          _ => {}
+        }
+      },
+      ast::Attribute::ShorthandAttribute(attr) => {
+        infer_statement(&attr.reference, Inference::Any, context);
+      },
+      ast::Attribute::SpreadAttribute(attr) => {
+        infer_statement(&attr.script, Inference::Shape(ShapeInference {
+          from_spread: true,
+          properties: BTreeMap::new()
+        }), context);
+      }
+    }
+  }
+
+  infer_children(&element.children, context);
+}
+
+fn infer_children(children: &Vec<ast::Node>, context: &mut Context) {
+  for child in children {
+    infer_node(child, false, context);
+  }
+}
+
+fn infer_each_block(block: &ast::EachBlock, context: &mut Context) {
+  infer_statement(&block.source, Inference::Array(ArrayInference {
+    value: Box::new(Inference::Any)
+  }), context);
+
+  if let Some(body) = &block.body {
+    let value_path = match &block.source {
+      js_ast::Statement::Reference(reference) => unfurl_scope_path(&reference.path, context),
+      _ => vec![]
+    };
+
+    let prev_value_path = context.scope.insert(block.value_name.to_string(), value_path);
+    let prev_key_path = block.key_name.as_ref().map(|key_name| {
+      context.scope.insert(key_name.to_string(), vec![])
+    });
+
+    infer_node(body, false, context);
+
+    restore_scope(&block.value_name, prev_value_path, context);
+    if let (Some(key_name), Some(prev_key_path)) = (&block.key_name, prev_key_path) {
+      restore_scope(key_name, prev_key_path, context);
+    }
+  }
+}
+
+fn restore_scope(name: &String, prev_path: Option<Vec<String>>, context: &mut Context) {
+  match prev_path {
+    Some(path) => context.scope.insert(name.to_string(), path),
+    None => context.scope.remove(name)
+  };
+}
+
+fn infer_conditional_block(block: &ast::ConditionalBlock, context: &mut Context) {
+  match block {
+    ast::ConditionalBlock::PassFailBlock(block) => {
+      infer_statement(&block.condition, Inference::Any, context);
+      if let Some(body) = &block.body {
+        infer_node(body, false, context);
+      }
+      if let Some(fail) = &block.fail {
+        infer_conditional_block(fail, context);
+      }
+    },
+    ast::ConditionalBlock::FinalBlock(block) => {
+      if let Some(body) = &block.body {
+        infer_node(body, false, context);
+      }
+    }
+  }
+}
+
+fn infer_statement(statement: &js_ast::Statement, default_inference: Inference, context: &mut Context) {
+  match statement {
+    js_ast::Statement::Reference(reference) => {
+      let path = unfurl_scope_path(&reference.path, context);
+      let inference = std::mem::replace(&mut context.inference, Inference::Any);
+      context.inference = add_inference_property(&path, default_inference, inference);
+    },
+    js_ast::Statement::Node(node) => {
+      infer_node(node, false, context);
+    },
+    js_ast::Statement::Array(array) => {
+      for value in &array.values {
+        infer_statement(value, Inference::Any, context);
+      }
+    },
+    js_ast::Statement::Object(object) => {
+      for property in &object.properties {
+        infer_statement(&property.value, Inference::Any, context);
+      }
+    },
+    js_ast::Statement::Member(member) => {
+      infer_statement(&member.object, Inference::Any, context);
+      if member.computed {
+        infer_statement(&member.property, Inference::Any, context);
+      }
+    },
+    js_ast::Statement::Call(call) => {
+      for argument in &call.arguments {
+        infer_statement(argument, Inference::Any, context);
+      }
+    },
+    js_ast::Statement::Template(template) => {
+      for part in &template.parts {
+        infer_statement(part, Inference::Any, context);
+      }
+    },
+    js_ast::Statement::Group(group) => {
+      infer_statement(&group.expression, default_inference, context);
+    },
+    js_ast::Statement::Unary(expr) => {
+      infer_statement(&expr.expression, Inference::Any, context);
+    },
+    js_ast::Statement::Binary(expr) => {
+      infer_statement(&expr.left, Inference::Any, context);
+      infer_statement(&expr.right, Inference::Any, context);
+    },
+    js_ast::Statement::Conditional(expr) => {
+      infer_statement(&expr.test, Inference::Any, context);
+      infer_statement(&expr.consequent, default_inference.clone(), context);
+      infer_statement(&expr.alternate, default_inference, context);
+    },
+    _ => {}
+  }
+}
+
+fn unfurl_scope_path(path: &Vec<String>, context: &Context) -> Vec<String> {
+  match context.scope.get(&path[0]) {
+    Some(scope_path) if scope_path.len() == 0 => vec![],
+    Some(scope_path) => scope_path.iter().chain(path[1..].iter()).cloned().collect(),
+    None => path.clone()
+  }
+}
+
+fn add_inference_property(path: &[String], value: Inference, inference: Inference) -> Inference {
+  if path.len() == 0 {
+    return inference;
+  }
+
+  match inference {
+    Inference::Any => add_inference_property(path, value, Inference::Shape(ShapeInference::default())),
+    Inference::Shape(mut shape) => {
+      let name = &path[0];
+      let existing = shape.properties.remove(name);
+      let property = if path.len() > 1 {
+        add_inference_property(&path[1..], value, existing.unwrap_or(Inference::Shape(ShapeInference::default())))
+      } else {
+        match existing {
+          Some(existing) => merge_inference(existing, value),
+          None => value
+        }
+      };
+      shape.properties.insert(name.to_string(), property);
+      Inference::Shape(shape)
+    },
+
+    // properties of array items, e.g: {#each items as item}{item.name}{/}
+    Inference::Array(array) => Inference::Array(ArrayInference {
+      value: Box::new(add_inference_property(path, value, *array.value))
+    })
+  }
+}
+
+fn merge_inference(existing: Inference, extended: Inference) -> Inference {
+  match (existing, extended) {
+    (Inference::Any, extended) => extended,
+    (Inference::Shape(mut existing), Inference::Shape(extended)) => {
+      existing.from_spread = existing.from_spread || extended.from_spread;
+      for (name, value) in extended.properties {
+        let property = match existing.properties.remove(&name) {
+          Some(existing_value) => merge_inference(existing_value, value),
+          None => value
+        };
+        existing.properties.insert(name, property);
+      }
+      Inference::Shape(existing)
+    },
+    (Inference::Array(existing), Inference::Array(extended)) => Inference::Array(ArrayInference {
+      value: Box::new(merge_inference(*existing.value, *extended.value))
+    }),
+
+    // Any, or a conflict (using an array as a shape), so keep what's known
+    (existing, _) => existing
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::pc::parser::*;
+
+  #[test]
+  fn can_infer_shapes_from_nested_references() {
+    let props = infer(&parse("<div a={b.c} {d}>{e.f.g}{e.h}</div>").unwrap());
+    assert_eq!(props, shape(vec![
+      ("b", Inference::Shape(shape(vec![("c", Inference::Any)]))),
+      ("d", Inference::Any),
+      ("e", Inference::Shape(shape(vec![
+        ("f", Inference::Shape(shape(vec![("g", Inference::Any)]))),
+        ("h", Inference::Any)
+      ])))
+    ]));
+  }
+
+  #[test]
+  fn can_infer_arrays_from_each_blocks() {
+    let props = infer(&parse("{#each items as item, i}{item.name}{i}{#each item.friends as friend}{friend.name}{/}{/}{items}").unwrap());
+    assert_eq!(props, shape(vec![
+      ("items", array(Inference::Shape(shape(vec![
+        ("friends", array(Inference::Shape(shape(vec![("name", Inference::Any)])))),
+        ("name", Inference::Any)
+      ]))))
+    ]));
+  }
+
+  #[test]
+  fn can_infer_spreads_and_nodes_used_as_children() {
+    let props = infer(&parse("<div {...a}>{<span>{b}</span>}{[c, {d: e}]}{#if f}{g}{/else}{h}{/}</div>").unwrap());
+    let names: Vec<&String> = props.properties.keys().collect();
+    assert_eq!(names, vec!["a", "b", "c", "e", "f", "g", "h"]);
+    assert_eq!(props.properties["a"], Inference::Shape(ShapeInference {
+      from_spread: true,
+      properties: BTreeMap::new()
+    }));
+  }
+
+  #[test]
+  fn can_infer_props_for_each_part() {
+    let parts = infer_parts(&parse("<part id=\"a\">{a}</part><part id=\"b\"><span>{b}{children}</span></part><preview>{c}</preview>").unwrap());
+    assert_eq!(parts, vec![
+      PartInference { id: "a".to_string(), props: shape(vec![("a", Inference::Any)]) },
+      PartInference { id: "b".to_string(), props: shape(vec![("b", Inference::Any), ("children", Inference::Any)]) }
+    ]);
+
+    let parts = infer_parts(&parse("<part id=\"a\">{a}</part>").unwrap());
+    assert_eq!(parts, vec![PartInference { id: "a".to_string(), props: shape(vec![("a", Inference::Any)]) }]);
+  }
+
+  fn shape(properties: Vec<(&str, Inference)>) -> ShapeInference {
+    ShapeInference {
+      from_spread: false,
+      properties: properties.into_iter().map(|(name, value)| (name.to_string(), value)).collect()
+    }
+  }
+
+  fn array(value: Inference) -> Inference {
+    Inference::Array(ArrayInference {
+      value: Box::new(value)
+    })
+  }
+}
@@ -1 +1,717 @@
-// TODO
\ No newline at end of file
+// Static checks that run over a parsed document and the dependency graph. Rules
+// can be turned off or have their severity changed with the "lint" section of
+// pcconfig.json, e.g. { "lint": { "rules": { "no-nested-styles": "error" } } }.
+
+use std::collections::{HashMap, HashSet};
+use serde::{Serialize, Deserialize};
+use crate::base::ast::{Location, Position};
+use crate::css::ast as css_ast;
+use crate::js::ast as js_ast;
+use super::ast;
+use super::infer::{infer};
+use super::runtime::graph::{DependencyGraph, DependencyContent};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+  Warning,
+  Error
+}
+
+#[derive(Debug, PartialEq, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleSetting {
+  Off,
+  Warning,
+  Error
+}
+
+#[derive(Debug, PartialEq, Deserialize, Default)]
+pub struct LintOptions {
+  #[serde(default)]
+  pub rules: HashMap<String, RuleSetting>
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct ProjectConfig {
+  #[serde(default)]
+  lint: LintOptions
+}
+
+impl LintOptions {
+
+  // Picks the lint options out of the contents of pcconfig.json
+  pub fn from_project_config(source: &str) -> Result<LintOptions, serde_json::Error> {
+    serde_json::from_str::<ProjectConfig>(source).map(|config| config.lint)
+  }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct LintDiagnostic {
+  #[serde(rename = "ruleId")]
+  pub rule_id: String,
+  pub severity: Severity,
+  pub message: String,
+  pub uri: String,
+  pub location: Location
+}
+
+pub struct Context<'a> {
+  pub uri: &'a String,
+  pub graph: &'a DependencyGraph,
+  pub node: &'a ast::Node,
+
+  // sheets from <style /> elements that are evaluated for the document
+  pub sheets: Vec<&'a css_ast::Sheet>,
+
+  // CSS files imported by the document
+  pub imported_sheets: Vec<&'a css_ast::Sheet>
+}
+
+struct Violation {
+  message: String,
+  location: Location
+}
+
+struct Rule {
+  id: &'static str,
+  default_severity: Severity,
+  check: fn(&Context) -> Vec<Violation>
+}
+
+const RULES: [Rule; 6] = [
+  Rule {
+    id: "no-unused-imports",
+    default_severity: Severity::Warning,
+    check: check_unused_imports
+  },
+  Rule {
+    id: "no-unknown-parts",
+    default_severity: Severity::Error,
+    check: check_unknown_parts
+  },
+  Rule {
+    id: "no-duplicate-part-ids",
+    default_severity: Severity::Error,
+    check: check_duplicate_part_ids
+  },
+  Rule {
+    id: "no-nested-styles",
+    default_severity: Severity::Warning,
+    check: check_nested_styles
+  },
+  Rule {
+    id: "no-unused-classes",
+    default_severity: Severity::Warning,
+    check: check_unused_classes
+  },
+  Rule {
+    id: "no-undefined-classes",
+    default_severity: Severity::Warning,
+    check: check_undefined_classes
+  }
+];
+
+pub fn lint(uri: &String, graph: &DependencyGraph, options: &LintOptions) -> Vec<LintDiagnostic> {
+  let mut diagnostics = vec![];
+
+  let node = match graph.dependencies.get(uri).map(|dep| &dep.content) {
+    Some(DependencyContent::Node(node)) => node,
+    _ => return diagnostics
+  };
+
+  let mut imported_sheets = vec![];
+  collect_imported_sheets(uri, graph, &mut vec![], &mut imported_sheets);
+
+  let context = Context {
+    uri,
+    graph,
+    node,
+    sheets: get_document_sheets(node),
+    imported_sheets
+  };
+
+  for rule in RULES.iter() {
+    let severity = match options.rules.get(rule.id) {
+      Some(RuleSetting::Off) => continue,
+      Some(RuleSetting::Warning) => Severity::Warning,
+      Some(RuleSetting::Error) => Severity::Error,
+      None => rule.default_severity
+    };
+
+    for violation in (rule.check)(&context) {
+      diagnostics.push(LintDiagnostic {
+        rule_id: rule.id.to_string(),
+        severity,
+        message: violation.message,
+        uri: uri.to_string(),
+        location: violation.location
+      });
+    }
+  }
+
+  diagnostics
+}
+
+// includes sheets imported by other sheets, e.g: @import url("./theme.css");
+fn collect_imported_sheets<'a>(uri: &'a String, graph: &'a DependencyGraph, visited: &mut Vec<&'a String>, sheets: &mut Vec<&'a css_ast::Sheet>) {
+  for dep_uri in graph.dependencies.get(uri).unwrap().dependencies.values() {
+    if visited.contains(&dep_uri) {
+      continue;
+    }
+    visited.push(dep_uri);
+    if let Some(DependencyContent::StyleSheet(sheet)) = graph.dependencies.get(dep_uri).map(|dep| &dep.content) {
+      sheets.push(sheet);
+      collect_imported_sheets(dep_uri, graph, visited, sheets);
+    }
+  }
+}
+
+fn check_unused_imports(context: &Context) -> Vec<Violation> {
+  let used_tag_names: HashSet<String> = get_elements(context.node).iter().map(|element| {
+    ast::get_tag_name(element)
+  }).collect();
+
+  // JSON fixtures are referenced by previews, e.g: {fixtures.label}
+  let preview_references: HashSet<String> = get_elements(context.node).into_iter().filter(|element| {
+    element.tag_name == "preview"
+  }).flat_map(|preview| {
+    infer(&ast::Node::Fragment(ast::Fragment {
+      children: preview.children.clone(),
+      location: preview.location.clone()
+    })).properties.into_iter().map(|(key, _)| key)
+  }).collect();
+
+  ast::get_imports(context.node).into_iter().filter_map(|import| {
+    let id = ast::get_attribute_value("id", import)?;
+    let src = ast::get_attribute_value("src", import)?;
+
+    // style sheets are applied without being referenced
+    if src.ends_with(".css") || used_tag_names.contains(id) || (src.ends_with(".json") && preview_references.contains(id)) {
+      return None;
+    }
+
+    Some(Violation {
+      message: format!("Import \"{}\" is never used.", id),
+      location: import.open_tag_location.clone()
+    })
+  }).collect()
+}
+
+fn check_unknown_parts(context: &Context) -> Vec<Violation> {
+  let dep = context.graph.dependencies.get(context.uri).unwrap();
+
+  get_elements(context.node).into_iter().filter_map(|element| {
+    let part_id = ast::get_tag_namespace(element)?;
+    let dep_uri = dep.dependencies.get(&ast::get_tag_name(element))?;
+
+    // missing imports are reported by the graph
+    if let DependencyContent::Node(imported_node) = &context.graph.dependencies.get(dep_uri)?.content {
+      if ast::get_part_by_id(&part_id, imported_node) == None {
+        return Some(Violation {
+          message: format!("Part \"{}\" does not exist in \"{}\".", part_id, dep_uri),
+          location: element.tag_name_location.clone()
+        });
+      }
+    }
+    None
+  }).collect()
+}
+
+fn check_duplicate_part_ids(context: &Context) -> Vec<Violation> {
+  let mut ids = HashSet::new();
+
+  ast::get_parts(context.node).into_iter().filter_map(|part| {
+    let id = ast::get_attribute_value("id", part)?;
+    if ids.insert(id) {
+      return None;
+    }
+    Some(Violation {
+      message: format!("Part \"{}\" is already defined.", id),
+      location: ast::get_attribute("id", part).unwrap().get_location().clone()
+    })
+  }).collect()
+}
+
+fn check_nested_styles(context: &Context) -> Vec<Violation> {
+  let mut violations = vec![];
+
+  // documents with a single root element aren't parsed into a fragment, so styles
+  // within that element are nested too
+  let max_depth = if let ast::Node::Fragment(_) = context.node { 1 } else { 0 };
+
+  walk_node(context.node, 0, &mut |node, depth| {
+    if let ast::Node::StyleElement(style) = node {
+      if depth > max_depth {
+        violations.push(Violation {
+          message: "Style elements must be defined at the root of the document.".to_string(),
+          location: style.location.clone()
+        });
+      }
+    }
+  });
+
+  violations
+}
+
+fn check_unused_classes(context: &Context) -> Vec<Violation> {
+  let mut markup_classes = get_markup_classes(context.node);
+
+  // classes given to instances are applied to elements in this document, e.g: <tabs:tab class="active" />.
+  // Only importers that are loaded in the graph are known.
+  for instance in get_importer_instances(context) {
+    add_element_classes(instance, &mut markup_classes);
+  }
+
+  // any class could be applied at runtime
+  if markup_classes.dynamic {
+    return vec![];
+  }
+
+  let used_class_names: HashSet<&str> = markup_classes.references.iter().map(|reference| reference.name).collect();
+  let mut violations = vec![];
+
+  for sheet in &context.sheets {
+    for rule in get_style_rules(sheet) {
+      let selectors = match &rule.selector {
+        css_ast::Selector::Group(group) => group.selectors.iter().collect(),
+        selector => vec![selector]
+      };
+      for selector in selectors {
+        let mut class_names = vec![];
+        collect_class_names(selector, false, &mut class_names);
+        if let Some(class_name) = class_names.into_iter().find(|class_name| !used_class_names.contains(class_name.as_str())) {
+          violations.push(Violation {
+            message: format!("Selector \"{}\" doesn't match any element since class \"{}\" is never used.", selector, class_name),
+            location: selector.get_location().clone()
+          });
+        }
+      }
+    }
+  }
+
+  violations
+}
+
+fn check_undefined_classes(context: &Context) -> Vec<Violation> {
+  let mut defined_class_names = HashSet::new();
+  for sheet in context.sheets.iter().chain(context.imported_sheets.iter()) {
+    for rule in get_style_rules(sheet) {
+      let mut class_names = vec![];
+      collect_class_names(&rule.selector, true, &mut class_names);
+      defined_class_names.extend(class_names.into_iter().map(|class_name| class_name.as_str()));
+    }
+  }
+
+  get_markup_classes(context.node).references.into_iter().filter(|reference| {
+    !defined_class_names.contains(reference.name)
+  }).map(|reference| {
+    Violation {
+      message: format!("Class \"{}\" isn't defined in any style sheet.", reference.name),
+      location: reference.location
+    }
+  }).collect()
+}
+
+// Only the root and its direct children are evaluated for styles
+fn get_document_sheets<'a>(root: &'a ast::Node) -> Vec<&'a css_ast::Sheet> {
+  let mut sheets = vec![];
+  walk_node(root, 0, &mut |node, depth| {
+    if let ast::Node::StyleElement(style) = node {
+      if depth <= 1 {
+        sheets.push(&style.sheet);
+      }
+    }
+  });
+  sheets
+}
+
+fn get_style_rules<'a>(sheet: &'a css_ast::Sheet) -> Vec<&'a css_ast::StyleRule> {
+  let mut style_rules = vec![];
+  collect_style_rules(&sheet.rules, &mut style_rules);
+  style_rules
+}
+
+// includes nested rules
+fn collect_style_rules<'a>(rules: &'a Vec<css_ast::Rule>, style_rules: &mut Vec<&'a css_ast::StyleRule>) {
+  for rule in rules {
+    match rule {
+      css_ast::Rule::Style(rule) => collect_style_rule(rule, style_rules),
+      css_ast::Rule::Media(rule) | css_ast::Rule::Supports(rule) | css_ast::Rule::Page(rule) | css_ast::Rule::Document(rule) => {
+        collect_style_rules(&rule.rules, style_rules);
+      },
+      _ => {}
+    }
+  }
+}
+
+fn collect_style_rule<'a>(rule: &'a css_ast::StyleRule, style_rules: &mut Vec<&'a css_ast::StyleRule>) {
+  style_rules.push(rule);
+  collect_style_rules(&rule.children, style_rules);
+}
+
+// Negated (:not(.a)) & global (:global(.a)) classes don't need to be in the document for a selector to match
+fn collect_class_names<'a>(selector: &'a css_ast::Selector, include_negated: bool, class_names: &mut Vec<&'a String>) {
+  match selector {
+    css_ast::Selector::Class(selector) => class_names.push(&selector.class_name),
+    css_ast::Selector::Group(selector) => for child in &selector.selectors {
+      collect_class_names(child, include_negated, class_names);
+    },
+    css_ast::Selector::Combo(selector) => for child in &selector.selectors {
+      collect_class_names(child, include_negated, class_names);
+    },
+    css_ast::Selector::Descendent(selector) => {
+      collect_class_names(&selector.parent, include_negated, class_names);
+      collect_class_names(&selector.descendent, include_negated, class_names);
+    },
+    css_ast::Selector::Child(selector) => {
+      collect_class_names(&selector.parent, include_negated, class_names);
+      collect_class_names(&selector.child, include_negated, class_names);
+    },
+    css_ast::Selector::Adjacent(selector) => {
+      collect_class_names(&selector.selector, include_negated, class_names);
+      collect_class_names(&selector.next_sibling_selector, include_negated, class_names);
+    },
+    css_ast::Selector::Sibling(selector) => {
+      collect_class_names(&selector.selector, include_negated, class_names);
+      collect_class_names(&selector.sibling_selector, include_negated, class_names);
+    },
+    css_ast::Selector::PseudoElement(css_ast::PseudoElementSelector { target: Some(target), .. }) |
+    css_ast::Selector::PseudoParamElement(css_ast::PseudoParamElementSelector { target: Some(target), .. }) => {
+      collect_class_names(target, include_negated, class_names);
+    },
+    css_ast::Selector::Not(selector) => if include_negated {
+      collect_class_names(&selector.selector, include_negated, class_names);
+    },
+    css_ast::Selector::Global(selector) => if include_negated {
+      collect_class_names(&selector.selector, include_negated, class_names);
+    },
+    css_ast::Selector::This(css_ast::SelfSelector { selector: Some(selector), .. }) => {
+      collect_class_names(selector, include_negated, class_names);
+    },
+    _ => {}
+  }
+}
+
+struct ClassReference<'a> {
+  name: &'a str,
+  location: Location
+}
+
+struct MarkupClasses<'a> {
+  references: Vec<ClassReference<'a>>,
+
+  // true if a class is computed at runtime
+  dynamic: bool
+}
+
+fn get_markup_classes<'a>(root: &'a ast::Node) -> MarkupClasses<'a> {
+  let mut classes = MarkupClasses {
+    references: vec![],
+    dynamic: false
+  };
+
+  for element in get_elements(root) {
+    add_element_classes(element, &mut classes);
+  }
+
+  classes
+}
+
+// instances of the document's parts in the documents that import it
+fn get_importer_instances<'a>(context: &Context<'a>) -> Vec<&'a ast::Element> {
+  let mut instances = vec![];
+  for dep in context.graph.dependencies.values() {
+    if let DependencyContent::Node(node) = &dep.content {
+      instances.extend(get_elements(node).into_iter().filter(|element| {
+        dep.dependencies.get(&ast::get_tag_name(element)) == Some(context.uri)
+      }));
+    }
+  }
+  instances
+}
+
+fn add_element_classes<'a>(element: &'a ast::Element, classes: &mut MarkupClasses<'a>) {
+  for attribute in &element.attributes {
+    match attribute {
+      ast::Attribute::KeyValueAttribute(attr) if attr.name == "class" => match &attr.value {
+        Some(ast::AttributeValue::String(value)) => {
+          add_class_references(&value.value, value.location.start, &value.location.start_position, &mut classes.references);
+        },
+        Some(ast::AttributeValue::Slot(js_ast::Statement::String(value))) => {
+          // skip the opening quote
+          add_class_references(&value.value, value.location.start + 1, &value.location.start_position.advance("\""), &mut classes.references);
+        },
+        Some(ast::AttributeValue::DynamicString(value)) => {
+          add_dynamic_class_references(value, &mut classes.references);
+          classes.dynamic = true;
+        },
+        Some(ast::AttributeValue::Slot(_)) => {
+          classes.dynamic = true;
+        },
+        None => {}
+      },
+      ast::Attribute::ShorthandAttribute(attr) if attr.get_name() == Ok(&"class".to_string()) => {
+        classes.dynamic = true;
+      },
+      ast::Attribute::SpreadAttribute(_) => {
+        classes.dynamic = true;
+      },
+      _ => {}
+    }
+  }
+}
+
+fn add_class_references<'a>(value: &'a String, start: usize, start_position: &Position, references: &mut Vec<ClassReference<'a>>) {
+  let mut offset = 0;
+  for name in value.split(|c: char| c.is_ascii_whitespace()) {
+    if !name.is_empty() {
+      let name_position = start_position.advance(&value[..offset]);
+      references.push(ClassReference {
+        name,
+        location: Location {
+          start: start + offset,
+          end: start + offset + name.len(),
+          end_position: name_position.advance(name),
+          start_position: name_position
+        }
+      });
+    }
+    offset += name.len() + 1;
+  }
+}
+
+// class="btn btn-{size}". Names touching a slot (btn-) are only part of a class.
+fn add_dynamic_class_references<'a>(value: &'a ast::AttributeDynamicStringValue, references: &mut Vec<ClassReference<'a>>) {
+  for (i, part) in value.values.iter().enumerate() {
+    if let ast::AttributeDynamicStringPart::Literal(literal) = part {
+      let mut literal_references = vec![];
+      add_class_references(&literal.value, literal.location.start, &literal.location.start_position, &mut literal_references);
+
+      let starts_with_name = !literal.value.starts_with(|c: char| c.is_ascii_whitespace());
+      let ends_with_name = !literal.value.ends_with(|c: char| c.is_ascii_whitespace());
+      if i < value.values.len() - 1 && ends_with_name {
+        literal_references.pop();
+      }
+      if i > 0 && starts_with_name && literal_references.len() > 0 {
+        literal_references.remove(0);
+      }
+      references.extend(literal_references);
+    }
+  }
+}
+
+fn get_elements<'a>(root: &'a ast::Node) -> Vec<&'a ast::Element> {
+  let mut elements = vec![];
+  walk_node(root, 0, &mut |node, _| {
+    if let ast::Node::Element(element) = node {
+      elements.push(element);
+    }
+  });
+  elements
+}
+
+fn walk_node<'a>(node: &'a ast::Node, depth: usize, visit: &mut dyn FnMut(&'a ast::Node, usize)) {
+  visit(node, depth);
+  match node {
+    ast::Node::Element(element) => walk_children(&element.children, depth + 1, visit),
+    ast::Node::Fragment(fragment) => walk_children(&fragment.children, depth + 1, visit),
+    ast::Node::Block(ast::Block::Each(block)) => walk_body(&block.body, depth + 1, visit),
+    ast::Node::Block(ast::Block::Conditional(block)) => walk_conditional_block(block, depth + 1, visit),
+    _ => {}
+  }
+}
+
+fn walk_children<'a>(children: &'a Vec<ast::Node>, depth: usize, visit: &mut dyn FnMut(&'a ast::Node, usize)) {
+  for child in children {
+    walk_node(child, depth, visit);
+  }
+}
+
+fn walk_body<'a>(body: &'a Option<Box<ast::Node>>, depth: usize, visit: &mut dyn FnMut(&'a ast::Node, usize)) {
+  if let Some(node) = body {
+    walk_node(node, depth, visit);
+  }
+}
+
+fn walk_conditional_block<'a>(block: &'a ast::ConditionalBlock, depth: usize, visit: &mut dyn FnMut(&'a ast::Node, usize)) {
+  match block {
+    ast::ConditionalBlock::PassFailBlock(block) => {
+      walk_body(&block.body, depth, visit);
+      if let Some(fail) = &block.fail {
+        walk_conditional_block(fail, depth, visit);
+      }
+    },
+    ast::ConditionalBlock::FinalBlock(block) => walk_body(&block.body, depth, visit)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::pc::runtime::vfs::{VirtualFileSystem};
+  use ::futures::executor::block_on;
+  use crate::base::ast::{LineIndex};
+
+  #[test]
+  fn reports_unused_imports() {
+    let diagnostics = lint_files(vec![
+      ("entry.pc", "<import id=\"a\" src=\"a.pc\" /><import id=\"b\" src=\"b.pc\" /><import id=\"c\" src=\"c.css\" /><a />"),
+      ("a.pc", ""),
+      ("b.pc", ""),
+      ("c.css", "")
+    ], &LintOptions::default());
+
+    assert_eq!(get_rule_ids(&diagnostics), vec!["no-unused-imports"]);
+    assert_eq!(diagnostics[0].message, "Import \"b\" is never used.");
+    assert_eq!(diagnostics[0].severity, Severity::Warning);
+  }
+
+  #[test]
+  fn counts_fixtures_referenced_by_previews_as_used() {
+    let diagnostics = lint_files(vec![
+      ("entry.pc", "<import id=\"a\" src=\"a.json\" /><import id=\"b\" src=\"b.json\" /><div>{b}</div><preview><span>{a.label}</span></preview>"),
+      ("a.json", "{\"label\": \"a\"}"),
+      ("b.json", "{}")
+    ], &LintOptions::default());
+
+    assert_eq!(diagnostics.iter().map(|diagnostic| diagnostic.message.as_str()).collect::<Vec<&str>>(), vec!["Import \"b\" is never used."]);
+  }
+
+  #[test]
+  fn reports_unknown_parts() {
+    let source = "<import id=\"a\" src=\"a.pc\" /><div><a:b /><a:c /></div>";
+    let diagnostics = lint_files(vec![
+      ("entry.pc", source),
+      ("a.pc", "<part id=\"b\"></part>")
+    ], &LintOptions::default());
+
+    assert_eq!(get_rule_ids(&diagnostics), vec!["no-unknown-parts"]);
+    assert_eq!(diagnostics[0].message, "Part \"c\" does not exist in \"a.pc\".");
+    assert_eq!(diagnostics[0].location, Location::new(41, 44).with_positions(&LineIndex::new(source)));
+  }
+
+  #[test]
+  fn reports_duplicate_part_ids() {
+    let source = "<part id=\"a\"></part><part id=\"b\"></part><part id=\"a\"></part>";
+    let diagnostics = lint_files(vec![
+      ("entry.pc", source)
+    ], &LintOptions::default());
+
+    assert_eq!(get_rule_ids(&diagnostics), vec!["no-duplicate-part-ids"]);
+    assert_eq!(diagnostics[0].location, Location::new(46, 52).with_positions(&LineIndex::new(source)));
+  }
+
+  #[test]
+  fn reports_nested_styles() {
+    let diagnostics = lint_files(vec![
+      ("entry.pc", "<style></style><div><style></style></div>{#if a}<style></style>{/}")
+    ], &LintOptions::default());
+
+    assert_eq!(get_rule_ids(&diagnostics), vec!["no-nested-styles", "no-nested-styles"]);
+  }
+
+  #[test]
+  fn reports_nested_styles_in_documents_with_a_single_root() {
+    let diagnostics = lint_files(vec![
+      ("entry.pc", "<div><style></style></div>")
+    ], &LintOptions::default());
+
+    assert_eq!(get_rule_ids(&diagnostics), vec!["no-nested-styles"]);
+  }
+
+  #[test]
+  fn reports_unused_and_undefined_classes() {
+    let diagnostics = lint_files(vec![
+      ("entry.pc", "<import src=\"styles.css\" /><style>.a, .b .c:hover { } .d { } :not(.e) { } @media screen { .f { } }</style><div class=\"a  b\"><span class={\"c g\"} /></div><div class=\"h d\" />"),
+      ("styles.css", ".h { }")
+    ], &LintOptions::default());
+
+    let messages: Vec<(&str, &str, usize)> = diagnostics.iter().map(|diagnostic| {
+      (diagnostic.rule_id.as_str(), diagnostic.message.as_str(), diagnostic.location.start)
+    }).collect();
+
+    assert_eq!(messages, vec![
+      ("no-unused-classes", "Selector \".f\" doesn't match any element since class \"f\" is never used.", 90),
+      ("no-undefined-classes", "Class \"g\" isn't defined in any style sheet.", 140)
+    ]);
+  }
+
+  #[test]
+  fn counts_classes_given_to_instances_by_importers_as_used() {
+    let graph = load_files(vec![
+      ("entry.pc", "<import id=\"tabs\" src=\"tabs.pc\" /><tabs:tab class=\"active\" />"),
+      ("tabs.pc", "<style>.tab { } .active { } .unused { }</style><part id=\"tab\"><div class=\"tab\" /></part>")
+    ]);
+    let diagnostics = lint(&"tabs.pc".to_string(), &graph, &LintOptions::default());
+
+    assert_eq!(diagnostics.iter().map(|diagnostic| diagnostic.message.as_str()).collect::<Vec<&str>>(), vec![
+      "Selector \".unused\" doesn't match any element since class \"unused\" is never used."
+    ]);
+  }
+
+  #[test]
+  fn skips_unused_classes_if_a_class_is_dynamic() {
+    let diagnostics = lint_files(vec![
+      ("entry.pc", "<style>.a { }</style><div class={b} />")
+    ], &LintOptions::default());
+
+    assert_eq!(get_rule_ids(&diagnostics), Vec::<&str>::new());
+  }
+
+  #[test]
+  fn checks_the_static_classes_of_interpolated_attributes() {
+    let diagnostics = lint_files(vec![
+      ("entry.pc", "<style>.a { }</style><div class=\"a btn-{b} {c}-d e\" />")
+    ], &LintOptions::default());
+
+    let messages: Vec<(&str, usize)> = diagnostics.iter().map(|diagnostic| {
+      (diagnostic.message.as_str(), diagnostic.location.start)
+    }).collect();
+
+    assert_eq!(messages, vec![("Class \"e\" isn't defined in any style sheet.", 49)]);
+  }
+
+  #[test]
+  fn reports_the_line_and_column_of_classes() {
+    let diagnostics = lint_files(vec![
+      ("entry.pc", "<style>.a { }</style>\n<div class=\"a\n  b\" />")
+    ], &LintOptions::default());
+
+    let location = &diagnostics[0].location;
+    assert_eq!((location.start_position.line, location.start_position.column), (2, 2));
+    assert_eq!((location.end_position.line, location.end_position.column), (2, 3));
+  }
+
+  #[test]
+  fn can_configure_rules_from_the_project_config() {
+    let options = LintOptions::from_project_config("{ \"moduleDirectories\": [], \"lint\": { \"rules\": { \"no-nested-styles\": \"off\", \"no-unused-imports\": \"error\" } } }").unwrap();
+    let diagnostics = lint_files(vec![
+      ("entry.pc", "<import id=\"a\" src=\"a.pc\" /><div><style></style></div>"),
+      ("a.pc", "")
+    ], &options);
+
+    assert_eq!(get_rule_ids(&diagnostics), vec!["no-unused-imports"]);
+    assert_eq!(diagnostics[0].severity, Severity::Error);
+    assert_eq!(LintOptions::from_project_config("{}").unwrap(), LintOptions::default());
+  }
+
+  fn get_rule_ids(diagnostics: &Vec<LintDiagnostic>) -> Vec<&str> {
+    diagnostics.iter().map(|diagnostic| diagnostic.rule_id.as_str()).collect()
+  }
+
+  fn lint_files(files: Vec<(&'static str, &'static str)>, options: &LintOptions) -> Vec<LintDiagnostic> {
+    let uri = files[0].0.to_string();
+    lint(&uri, &load_files(files), options)
+  }
+
+  // loads the first file & its imports
+  fn load_files(files: Vec<(&'static str, &'static str)>) -> DependencyGraph {
+    let contents: HashMap<String, String> = files.iter().map(|(uri, content)| (uri.to_string(), content.to_string())).collect();
+    let uri = files[0].0.to_string();
+    let mut vfs = VirtualFileSystem::new(Box::new(move |uri| contents.get(uri).unwrap().to_string()), Box::new(|_| true), Box::new(|_, uri| uri.to_string()));
+    let mut graph = DependencyGraph::new();
+    block_on(graph.load_dependency(&uri, &mut vfs)).unwrap();
+    graph
+  }
+}
@@ -1,3 +1,6 @@
 pub mod ast;
+pub mod definition;
+pub mod infer;
+pub mod lint;
 pub mod parser;
 pub mod runtime;
\ No newline at end of file
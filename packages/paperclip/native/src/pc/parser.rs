@@ -2,11 +2,10 @@
 
 use super::ast as pc_ast;
 use crate::base::parser::{get_buffer, ParseError};
-use crate::base::ast::{Location};
 use crate::js::parser::parse_with_tokenizer as parse_js_with_tokenizer;
 use crate::js::ast as js_ast;
 use crate::base::tokenizer::{Token, Tokenizer};
-use crate::css::parser::parse_with_tokenizer as parse_css_with_tokenizer;
+use crate::css::parser::{parse_with_tokenizer as parse_css_with_tokenizer, parse_with_tokenizer_and_recovery as parse_css_with_tokenizer_and_recovery};
 
 /*
 
@@ -35,71 +34,151 @@ void elements: [ 'area',
   'wbr' ]
 */
 
+pub struct Context<'a, 'b> {
+  tokenizer: &'b mut Tokenizer<'a>,
+
+  // Some if errors should be collected instead of bailing on the first one
+  errors: Option<Vec<ParseError>>
+}
+
+impl<'a, 'b> Context<'a, 'b> {
+
+  // records the error & skips to the next sane boundary (next tag, block, or close tag)
+  fn recover(&mut self, error: ParseError, start: usize) -> Result<(), ParseError> {
+    let pos = self.tokenizer.pos;
+    self.ignore(error, pos)?;
+
+    // ensure that the tokenizer always moves forward to prevent infinite loops
+    if self.tokenizer.pos == start && !self.tokenizer.is_eof() {
+      self.tokenizer.next()?;
+    }
+
+    while !self.tokenizer.is_eof() {
+      match self.tokenizer.peek(1)? {
+        Token::LessThan | Token::TagClose | Token::HtmlCommentOpen | Token::BlockOpen | Token::BlockClose => break,
+        _ => { self.tokenizer.next()?; }
+      }
+    }
+    Ok(())
+  }
+
+  // records the error & resets the tokenizer so that parsing can continue from pos
+  fn ignore(&mut self, error: ParseError, pos: usize) -> Result<(), ParseError> {
+    if let Some(errors) = &mut self.errors {
+      errors.push(error);
+      self.tokenizer.pos = pos;
+      Ok(())
+    } else {
+      Err(error)
+    }
+  }
+}
+
 pub fn parse<'a>(source: &'a str) -> Result<pc_ast::Node, ParseError> {
-  parse_fragment(&mut Tokenizer::new(source))
+  let mut tokenizer = Tokenizer::new(source);
+  let mut context = Context {
+    tokenizer: &mut tokenizer,
+    errors: None
+  };
+  parse_fragment(&mut context).map_err(|err| err.with_positions(&tokenizer.line_index))
 }
 
-fn parse_fragment<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<pc_ast::Node, ParseError> {
+// Returns a best-effort AST along with all errors encountered while parsing.
+pub fn parse_with_recovery<'a>(source: &'a str) -> (pc_ast::Node, Vec<ParseError>) {
+  let mut tokenizer = Tokenizer::new(source);
+  let mut context = Context {
+    tokenizer: &mut tokenizer,
+    errors: Some(vec![])
+  };
+
+  let result = parse_fragment(&mut context);
+  let mut errors = context.errors.unwrap();
+
+  let node = match result {
+    Ok(node) => node,
+    Err(err) => {
+      errors.push(err);
+      pc_ast::Node::Fragment(pc_ast::Fragment { children: vec![], location: tokenizer.location(0, 0) })
+    }
+  };
+
+  (node, errors.into_iter().map(|err| err.with_positions(&tokenizer.line_index)).collect())
+}
+
+fn parse_fragment<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<pc_ast::Node, ParseError> {
   let mut children: Vec<pc_ast::Node> = vec![];
 
-  while !tokenizer.is_eof() {
-    children.push(parse_node(tokenizer)?);
+  while !context.tokenizer.is_eof() {
+    if let Some(child) = parse_child_node(context)? {
+      children.push(child);
+    }
   }
 
   if children.len() == 1 {
     Ok(children.pop().unwrap())
   } else {
-    Ok(pc_ast::Node::Fragment(pc_ast::Fragment { children }))
+    Ok(pc_ast::Node::Fragment(pc_ast::Fragment {
+      children,
+      location: context.tokenizer.location(0, context.tokenizer.pos)
+    }))
+  }
+}
+
+fn parse_child_node<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<Option<pc_ast::Node>, ParseError> {
+  let start = context.tokenizer.pos;
+  match parse_node(context) {
+    Ok(node) => Ok(Some(node)),
+    Err(err) => {
+      context.recover(err, start)?;
+      Ok(None)
+    }
   }
 }
 
-fn parse_node<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<pc_ast::Node, ParseError> {
-  let start = tokenizer.pos;
-  tokenizer.eat_whitespace();
+fn parse_node<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<pc_ast::Node, ParseError> {
+  let start = context.tokenizer.pos;
+  context.tokenizer.eat_whitespace();
 
   // Kinda ick, but cover case where last node is whitespace.
-  let token = tokenizer.peek_eat_whitespace(1).or_else(|_| {
-    tokenizer.pos = start;
-    tokenizer.peek(1)
+  let token = context.tokenizer.peek_eat_whitespace(1).or_else(|_| {
+    context.tokenizer.pos = start;
+    context.tokenizer.peek(1)
   })?;
 
   match token {
-    Token::CurlyOpen => { parse_slot(tokenizer) },
-    Token::LessThan => { parse_tag(tokenizer) },
+    Token::CurlyOpen => { parse_slot(context) },
+    Token::LessThan => { parse_tag_in_context(context) },
     Token::HtmlCommentOpen => { 
-      tokenizer.next()?; // eat HTML comment open
-      let buffer = get_buffer(tokenizer, |tokenizer| {
+      context.tokenizer.next()?; // eat HTML comment open
+      let buffer = get_buffer(context.tokenizer, |tokenizer| {
         let tok = tokenizer.peek(1)?;
         Ok(tok != Token::HtmlCommentClose)
       })?.to_string();
-      tokenizer.next()?; // eat -->
-      Ok(pc_ast::Node::Comment(pc_ast::ValueObject { value: buffer.clone(), location: Location {
-        start,
-        end: start + &buffer.len()
-      } }))
+      context.tokenizer.next()?; // eat -->
+      Ok(pc_ast::Node::Comment(pc_ast::ValueObject { value: buffer.clone(), location: context.tokenizer.location(start, start + &buffer.len()) }))
     },
     Token::BlockOpen => {
-      parse_block(tokenizer)
+      parse_block(context)
     }
     Token::TagClose => {
-      let start = tokenizer.pos;
-      tokenizer.next_expect(Token::TagClose)?;
-      let tag_name = parse_tag_name(tokenizer)?;
-      tokenizer.next_expect(Token::GreaterThan)?;
+      let start = context.tokenizer.pos;
+      context.tokenizer.next_expect(Token::TagClose)?;
+      let tag_name = parse_tag_name(context)?;
+      context.tokenizer.next_expect(Token::GreaterThan)?;
 
-      let message = if is_void_tag_name(tag_name.as_str()) { 
+      let message = if pc_ast::is_void_tag_name(tag_name.as_str()) { 
         "Void tag's shouldn't be closed."
       } else {
         "Closing tag doesn't have an open tag."
       };
 
-      Err(ParseError::unexpected(message.to_string(), start, tokenizer.pos))
+      Err(ParseError::unexpected(message.to_string(), start, context.tokenizer.pos))
     }
     _ => {
 
       // reset pos to ensure text doesn't get chopped (e.g: `{children} text`)
-      tokenizer.pos = start;
-      let value =  get_buffer(tokenizer, |tokenizer| {
+      context.tokenizer.pos = start;
+      let value =  get_buffer(context.tokenizer, |tokenizer| {
         let tok = tokenizer.peek(1)?;
         Ok(
           tok != Token::CurlyOpen && 
@@ -112,157 +191,132 @@ fn parse_node<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<pc_ast::Node, ParseEr
       })?.to_string();
 
       if value.len() == 0 {
-        Err(ParseError::unexpected_token(tokenizer.pos))
+        Err(ParseError::unexpected_token(context.tokenizer.pos))
       } else {
         Ok(pc_ast::Node::Text(pc_ast::ValueObject { 
           value: value.clone(),
-          location: Location {
-            start: start,
-            end: start + &value.len()
-          }
+          location: context.tokenizer.location(start, start + &value.len())
         }))
       }
     }
   }
 }
 
-fn parse_slot<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<pc_ast::Node, ParseError> {
-  let omit_from_compilation = parse_omit_from_compilation(tokenizer)?;
-  tokenizer.next_expect(Token::CurlyOpen)?;
-  let script = parse_slot_script(tokenizer)?;
-  Ok(pc_ast::Node::Slot(pc_ast::Slot { omit_from_compilation, script }))
+fn parse_slot<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<pc_ast::Node, ParseError> {
+  let start = context.tokenizer.pos;
+  let omit_from_compilation = parse_omit_from_compilation(context)?;
+  context.tokenizer.next_expect(Token::CurlyOpen)?;
+  let script = parse_slot_script(context)?;
+  Ok(pc_ast::Node::Slot(pc_ast::Slot {
+    omit_from_compilation,
+    script,
+    location: context.tokenizer.location(start, context.tokenizer.pos)
+  }))
 }
 
-fn parse_slot_script<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<js_ast::Statement, ParseError> {
-  let start = tokenizer.pos;
-  parse_js_with_tokenizer(tokenizer, |token| {
+fn parse_slot_script<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<js_ast::Statement, ParseError> {
+  let start = context.tokenizer.pos;
+  parse_js_with_tokenizer(context.tokenizer, |token| {
     token != Token::CurlyClose
   })
   .and_then(|script| {
-    tokenizer.next_expect(Token::CurlyClose)?;
+    context.tokenizer.next_expect(Token::CurlyClose)?;
     Ok(script)
   })
-  .or(Err(ParseError::unterminated("Unterminated slot.".to_string(), start, tokenizer.pos)))
+  .or(Err(ParseError::unterminated("Unterminated slot.".to_string(), start, context.tokenizer.pos)))
 }
 
 pub fn parse_tag<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<pc_ast::Node, ParseError> {
-  let start = tokenizer.pos;
+  parse_tag_in_context(&mut Context {
+    tokenizer,
+    errors: None
+  })
+}
 
-  tokenizer.next_expect(Token::LessThan)?;
-  if tokenizer.peek(1)? == Token::GreaterThan {
-    parse_fragment_tag(tokenizer)
+fn parse_tag_in_context<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<pc_ast::Node, ParseError> {
+  let start = context.tokenizer.pos;
+
+  context.tokenizer.next_expect(Token::LessThan)?;
+  if context.tokenizer.peek(1)? == Token::GreaterThan {
+    parse_fragment_tag(context, start)
   } else {
-    parse_element(tokenizer, start)
+    parse_element(context, start)
   } 
 }
 
-pub fn parse_fragment_tag<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<pc_ast::Node, ParseError> {
-  tokenizer.next_expect(Token::GreaterThan)?;
+fn parse_fragment_tag<'a, 'b>(context: &mut Context<'a, 'b>, start: usize) -> Result<pc_ast::Node, ParseError> {
+  context.tokenizer.next_expect(Token::GreaterThan)?;
 
   let mut children = vec![];
   
-  while !tokenizer.is_eof() && tokenizer.peek_eat_whitespace(1)? != Token::TagClose {
-    children.push(parse_node(tokenizer)?);
+  while !context.tokenizer.is_eof() && context.tokenizer.peek_eat_whitespace(1)? != Token::TagClose {
+    if let Some(child) = parse_child_node(context)? {
+      children.push(child);
+    }
   }
 
-  tokenizer.eat_whitespace();
+  context.tokenizer.eat_whitespace();
 
-  tokenizer.next_expect(Token::TagClose)?;
-  tokenizer.next_expect(Token::GreaterThan)?;
+  let close_start = context.tokenizer.pos;
+  if let Err(err) = context.tokenizer.next_expect(Token::TagClose).and_then(|_| context.tokenizer.next_expect(Token::GreaterThan)) {
+    context.ignore(err, close_start)?;
+  }
 
   Ok(pc_ast::Node::Fragment(pc_ast::Fragment {
-    children
+    children,
+    location: context.tokenizer.location(start, context.tokenizer.pos)
   }))
 }
 
 
-fn parse_element<'a>(tokenizer: &mut Tokenizer<'a>, start: usize) -> Result<pc_ast::Node, ParseError> {
-  let tag_name = parse_tag_name(tokenizer)?;
+fn parse_element<'a, 'b>(context: &mut Context<'a, 'b>, start: usize) -> Result<pc_ast::Node, ParseError> {
+  let tag_name = parse_tag_name(context)?;
 
-  let attributes = parse_attributes(tokenizer)?;
+  let attributes = parse_attributes(context)?;
 
   if tag_name == "style" {
-    parse_next_style_element_parts(attributes, tokenizer, start)
+    parse_next_style_element_parts(attributes, context, start)
   } else if tag_name == "script" {
-    parse_next_script_element_parts(attributes, tokenizer, start)
+    parse_next_script_element_parts(attributes, context, start)
   } else {
-    parse_next_basic_element_parts(tag_name, attributes, tokenizer, start)
-  }
-}
-
-fn is_void_tag_name<'a>(tag_name: &'a str) -> bool {
-  match tag_name {
-    "area" | 
-    "base" | 
-    "basefont" | 
-    "bgsound" | 
-    "br" | 
-    "col" | 
-    "command" | 
-    "embed" | 
-    "frame" |
-    "hr" |
-    "image" |
-    "import" |
-    "img" |
-    "input" |
-    "isindex" |
-    "keygen" |
-    "link" |
-    "menuitem" |
-    "meta" |
-    "property" |
-    "logic" |
-    "nextid" |
-    "param" |
-    "source" |
-    "track" |
-    "wbr" => true,
-    _ => false,
-  }
-}
-
-fn parse_next_basic_element_parts<'a>(tag_name: String, attributes: Vec<pc_ast::Attribute>, tokenizer: &mut Tokenizer<'a>, start: usize) -> Result<pc_ast::Node, ParseError> {
+    parse_next_basic_element_parts(tag_name, attributes, context, start)
+  }
+}
+
+fn parse_next_basic_element_parts<'a, 'b>(tag_name: String, attributes: Vec<pc_ast::Attribute>, context: &mut Context<'a, 'b>, start: usize) -> Result<pc_ast::Node, ParseError> {
   let mut children: Vec<pc_ast::Node> = vec![];
 
-  tokenizer.eat_whitespace();
-  let mut end = tokenizer.pos;
+  context.tokenizer.eat_whitespace();
   
-  match tokenizer.peek(1)? {
+  let end = match context.tokenizer.peek(1)? {
     Token::SelfTagClose => {
-      tokenizer.next()?;
-      end = tokenizer.pos;
+      context.tokenizer.next()?;
+      context.tokenizer.pos
     },
     Token::GreaterThan => {
-      tokenizer.next()?;
-      end = tokenizer.pos;
-      if !is_void_tag_name(tag_name.as_str()) {
-        tokenizer.eat_whitespace();
-        while !tokenizer.is_eof() && tokenizer.peek_eat_whitespace(1)? != Token::TagClose {
-          children.push(parse_node(tokenizer)?);
+      context.tokenizer.next()?;
+      let end = context.tokenizer.pos;
+      if !pc_ast::is_void_tag_name(tag_name.as_str()) {
+        context.tokenizer.eat_whitespace();
+        while !context.tokenizer.is_eof() && context.tokenizer.peek_eat_whitespace(1)? != Token::TagClose {
+          if let Some(child) = parse_child_node(context)? {
+            children.push(child);
+          }
         }
 
-        parse_close_tag(&tag_name.as_str(), tokenizer, start, end)?;
+        parse_close_tag(&tag_name.as_str(), context, start, end)?;
       }
+      end
     },
     _ => {
-      return Err(ParseError::unexpected_token(tokenizer.pos))
+      return Err(ParseError::unexpected_token(context.tokenizer.pos))
     }
-  }
+  };
 
   let el = pc_ast::Element {
-    tag_name_location: Location {
-      start: start + 1,
-      end: start + 1 + tag_name.len(),
-    },
-    open_tag_location: Location {
-      start,
-      end
-    },
-    location: Location {
-      start,
-      end: tokenizer.pos
-    },
+    tag_name_location: context.tokenizer.location(start + 1, start + 1 + tag_name.len()),
+    open_tag_location: context.tokenizer.location(start, end),
+    location: context.tokenizer.location(start, context.tokenizer.pos),
     tag_name,
     attributes,
     children
@@ -270,14 +324,15 @@ fn parse_next_basic_element_parts<'a>(tag_name: String, attributes: Vec<pc_ast::
   Ok(pc_ast::Node::Element(el))
 }
 
-fn parse_block<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<pc_ast::Node, ParseError> {
-  tokenizer.next_expect(Token::BlockOpen)?;
-  let pos = tokenizer.pos;
-  let token = tokenizer.next()?; // eat {# or {/
+fn parse_block<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<pc_ast::Node, ParseError> {
+  let start = context.tokenizer.pos;
+  context.tokenizer.next_expect(Token::BlockOpen)?;
+  let pos = context.tokenizer.pos;
+  let token = context.tokenizer.next()?; // eat {# or {/
   if let Token::Word(keyword) = token {
     match keyword {
-      "if" => parse_if_block(tokenizer),
-      "each" => parse_each_block(tokenizer),
+      "if" => parse_if_block(context, start),
+      "each" => parse_each_block(context, start),
       _ => {
         Err(ParseError::unexpected_token(pos))
       }
@@ -287,40 +342,52 @@ fn parse_block<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<pc_ast::Node, ParseE
   }
 }
 
-fn parse_if_block<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<pc_ast::Node, ParseError> {
+fn parse_if_block<'a, 'b>(context: &mut Context<'a, 'b>, start: usize) -> Result<pc_ast::Node, ParseError> {
   Ok(pc_ast::Node::Block(pc_ast::Block::Conditional(
-    parse_pass_fail_block(tokenizer)?
+    parse_pass_fail_block(context, start)?
   )))
 }
 
-fn parse_pass_fail_block<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<pc_ast::ConditionalBlock, ParseError> {
-  tokenizer.eat_whitespace();
-  let condition = parse_js_with_tokenizer(tokenizer, |token| {
+fn parse_pass_fail_block<'a, 'b>(context: &mut Context<'a, 'b>, start: usize) -> Result<pc_ast::ConditionalBlock, ParseError> {
+  context.tokenizer.eat_whitespace();
+  let condition = parse_js_with_tokenizer(context.tokenizer, |token| {
     token != Token::CurlyClose
   })?;
-  tokenizer.next_expect(Token::CurlyClose)?;
-  let body = parse_block_children(tokenizer)?;
-  let fail = parse_else_block(tokenizer)?;
+  context.tokenizer.next_expect(Token::CurlyClose)?;
+  let body = parse_block_children(context)?;
+  let else_start = context.tokenizer.pos;
+  let fail = match parse_else_block(context) {
+    Ok(fail) => fail,
+    Err(err) => {
+      let err = unterminated_block_error(context, err, start);
+      context.ignore(err, else_start)?;
+      None
+    }
+  };
 
   Ok(pc_ast::ConditionalBlock::PassFailBlock(
     pc_ast::PassFailBlock {
       condition,
       body,
       fail,
+      location: context.tokenizer.location(start, context.tokenizer.pos)
     }
   ))
 }
 
-fn parse_block_children<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<Option<Box<pc_ast::Node>>, ParseError> {
+fn parse_block_children<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<Option<Box<pc_ast::Node>>, ParseError> {
 
   let mut children = vec![];
 
   // TODO - we don't really want this since whitespace technically renders. Though, right
   // now it's not handled.
-  tokenizer.eat_whitespace();
+  context.tokenizer.eat_whitespace();
+  let start = context.tokenizer.pos;
 
-  while !tokenizer.is_eof() && tokenizer.peek(1)? != Token::BlockClose {
-    children.push(parse_node(tokenizer)?);
+  while !context.tokenizer.is_eof() && context.tokenizer.peek(1)? != Token::BlockClose {
+    if let Some(child) = parse_child_node(context)? {
+      children.push(child);
+    }
   }
 
   let node = if children.len() == 0 {
@@ -329,34 +396,36 @@ fn parse_block_children<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<Option<Box<
     Some(Box::new(children.pop().unwrap()))
   } else {
     Some(Box::new(pc_ast::Node::Fragment(pc_ast::Fragment {
-      children
+      children,
+      location: context.tokenizer.location(start, context.tokenizer.pos)
     })))
   };
 
   Ok(node)
 }
 
-fn parse_else_block<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<Option<Box<pc_ast::ConditionalBlock>>, ParseError> {
-  tokenizer.eat_whitespace();
-  tokenizer.next_expect(Token::BlockClose)?;
-  tokenizer.eat_whitespace();
-  let pos = tokenizer.pos;
-  match tokenizer.next()? {
+fn parse_else_block<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<Option<Box<pc_ast::ConditionalBlock>>, ParseError> {
+  context.tokenizer.eat_whitespace();
+  let start = context.tokenizer.pos;
+  context.tokenizer.next_expect(Token::BlockClose)?;
+  context.tokenizer.eat_whitespace();
+  let pos = context.tokenizer.pos;
+  match context.tokenizer.next()? {
     Token::Word(value) => {
       match value {
         "else" => {
-          tokenizer.eat_whitespace();
-          let pos = tokenizer.pos;
-          match tokenizer.next()? {
+          context.tokenizer.eat_whitespace();
+          let pos = context.tokenizer.pos;
+          match context.tokenizer.next()? {
             Token::Word(value2) => {
               if value2 == "if" {
-                Ok(Some(Box::new(parse_pass_fail_block(tokenizer)?)))
+                Ok(Some(Box::new(parse_pass_fail_block(context, start)?)))
               } else {
                 Err(ParseError::unexpected_token(pos))
               }
             },
             Token::CurlyClose => {
-              Ok(Some(Box::new(parse_final_condition_block(tokenizer)?)))
+              Ok(Some(Box::new(parse_final_condition_block(context, start)?)))
             }
             _ => {
               Err(ParseError::unexpected_token(pos))
@@ -377,141 +446,168 @@ fn parse_else_block<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<Option<Box<pc_a
   }
 }
 
-fn parse_final_condition_block<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<pc_ast::ConditionalBlock, ParseError> {
-  let body = parse_block_children(tokenizer)?;
-  tokenizer.next_expect(Token::BlockClose)?;
-  tokenizer.next_expect(Token::CurlyClose)?;
+fn parse_final_condition_block<'a, 'b>(context: &mut Context<'a, 'b>, start: usize) -> Result<pc_ast::ConditionalBlock, ParseError> {
+  let body = parse_block_children(context)?;
+  parse_block_close(context, start)?;
   Ok(pc_ast::ConditionalBlock::FinalBlock(pc_ast::FinalBlock {
-    body
+    body,
+    location: context.tokenizer.location(start, context.tokenizer.pos)
   }))
 }
 
-fn parse_each_block<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<pc_ast::Node, ParseError> {
+fn parse_block_close<'a, 'b>(context: &mut Context<'a, 'b>, start: usize) -> Result<(), ParseError> {
+  let close_start = context.tokenizer.pos;
+  if let Err(err) = context.tokenizer.next_expect(Token::BlockClose).and_then(|_| context.tokenizer.next_expect(Token::CurlyClose)) {
+    let err = unterminated_block_error(context, err, start);
+    context.ignore(err, close_start)?;
+  }
+  Ok(())
+}
+
+fn unterminated_block_error<'a, 'b>(context: &mut Context<'a, 'b>, error: ParseError, start: usize) -> ParseError {
+  if context.tokenizer.is_eof() {
+    ParseError::unterminated("Unterminated block.".to_string(), start, context.tokenizer.pos)
+  } else {
+    error
+  }
+}
+
+fn parse_each_block<'a, 'b>(context: &mut Context<'a, 'b>, start: usize) -> Result<pc_ast::Node, ParseError> {
 
   
-  tokenizer.next_expect(Token::Whitespace)?;
-  let source = parse_js_with_tokenizer(tokenizer, |token| {
+  context.tokenizer.next_expect(Token::Whitespace)?;
+  let source = parse_js_with_tokenizer(context.tokenizer, |token| {
     token != Token::Word("as")
   })?;
-  tokenizer.next_expect(Token::Word("as"))?;
-  tokenizer.eat_whitespace();
+  context.tokenizer.next_expect(Token::Word("as"))?;
+  context.tokenizer.eat_whitespace();
 
-  let value_name = tokenizer.next_word_value()?;
+  let value_name = context.tokenizer.next_word_value()?;
 
-  tokenizer.eat_whitespace();
+  context.tokenizer.eat_whitespace();
 
-  let key_name = if let Token::Comma = tokenizer.peek(1)? {
-    tokenizer.next()?;
-    tokenizer.eat_whitespace();
-    Some(tokenizer.next_word_value()?)
+  let key_name = if let Token::Comma = context.tokenizer.peek(1)? {
+    context.tokenizer.next()?;
+    context.tokenizer.eat_whitespace();
+    Some(context.tokenizer.next_word_value()?)
   } else {
     None
   };
-  tokenizer.eat_whitespace();
-  tokenizer.next_expect(Token::CurlyClose)?;
-  let body = parse_block_children(tokenizer)?;
-  tokenizer.next_expect(Token::BlockClose)?;
-  tokenizer.next_expect(Token::CurlyClose)?;
+  context.tokenizer.eat_whitespace();
+  context.tokenizer.next_expect(Token::CurlyClose)?;
+  let body = parse_block_children(context)?;
+  parse_block_close(context, start)?;
 
   Ok(pc_ast::Node::Block(pc_ast::Block::Each(pc_ast::EachBlock {
     source,
     value_name,
     key_name,
-    body
+    body,
+    location: context.tokenizer.location(start, context.tokenizer.pos)
   })))
 }
 
-fn parse_next_style_element_parts<'a>(attributes: Vec<pc_ast::Attribute>, tokenizer: &mut Tokenizer<'a>, start: usize) -> Result<pc_ast::Node, ParseError> {
-  tokenizer.next_expect(Token::GreaterThan)?; // eat >
-  let end = tokenizer.pos;
+fn parse_next_style_element_parts<'a, 'b>(attributes: Vec<pc_ast::Attribute>, context: &mut Context<'a, 'b>, start: usize) -> Result<pc_ast::Node, ParseError> {
+  context.tokenizer.next_expect(Token::GreaterThan)?; // eat >
+  let end = context.tokenizer.pos;
 
-  let sheet = parse_css_with_tokenizer(tokenizer, |tokenizer| -> Result<bool, ParseError> {
-    Ok(tokenizer.peek(1)? == Token::TagClose)
-  })?;
+  let sheet = if let Some(errors) = &mut context.errors {
+    let (sheet, css_errors) = parse_css_with_tokenizer_and_recovery(context.tokenizer, |tokenizer| -> Result<bool, ParseError> {
+      Ok(tokenizer.peek(1)? == Token::TagClose)
+    });
+    errors.extend(css_errors);
+    sheet
+  } else {
+    parse_css_with_tokenizer(context.tokenizer, |tokenizer| -> Result<bool, ParseError> {
+      Ok(tokenizer.peek(1)? == Token::TagClose)
+    })?
+  };
 
   // TODO - assert tokens equal these
-  parse_close_tag("style", tokenizer, start, end)?;
+  parse_close_tag("style", context, start, end)?;
 
   Ok(pc_ast::Node::StyleElement(pc_ast::StyleElement {
     attributes,
     sheet,
+    location: context.tokenizer.location(start, context.tokenizer.pos)
   }))
 }
 
-fn parse_close_tag<'a, 'b>(tag_name: &'a str, tokenizer: &mut Tokenizer<'b>, start: usize, end: usize) -> Result<(), ParseError> {
+fn parse_close_tag<'a, 'b, 'c>(tag_name: &'c str, context: &mut Context<'a, 'b>, start: usize, end: usize) -> Result<(), ParseError> {
+  let close_start = context.tokenizer.pos;
+  if let Err(err) = expect_close_tag(tag_name, context, start, end) {
+
+    // the close tag may belong to a parent, so leave it for them
+    context.ignore(err, close_start)?;
+  }
+  Ok(())
+}
+
+fn expect_close_tag<'a, 'b, 'c>(tag_name: &'c str, context: &mut Context<'a, 'b>, start: usize, end: usize) -> Result<(), ParseError> {
 
-  let end_tag_name_start = tokenizer.pos;
+  let end_tag_name_start = context.tokenizer.pos;
 
-  tokenizer.eat_whitespace();
+  context.tokenizer.eat_whitespace();
   
-  tokenizer
+  context.tokenizer
   .next_expect(Token::TagClose)
   .or(Err(ParseError::unterminated("Unterminated element.".to_string(), start, end)))?;
 
 
-  parse_tag_name(tokenizer)
+  parse_tag_name(context)
   // TODO - assert tag name
   .and_then(|end_tag_name| {
     if tag_name != end_tag_name {
-      Err(ParseError::unterminated(format!("Incorrect closing tag. This should be </{}>.", tag_name), end_tag_name_start, tokenizer.pos))
+      Err(ParseError::unterminated(format!("Incorrect closing tag. This should be </{}>.", tag_name), end_tag_name_start, context.tokenizer.pos))
     } else {
       Ok(())
     }
 
   })?;
 
-  tokenizer
+  context.tokenizer
   .next_expect(Token::GreaterThan)
   .or(Err(ParseError::unterminated("Unterminated element.".to_string(), start, end)))?;
 
   Ok(())
 }
 
-fn parse_next_script_element_parts<'a>(attributes: Vec<pc_ast::Attribute>, tokenizer: &mut Tokenizer<'a>, start: usize) -> Result<pc_ast::Node, ParseError> {
-  tokenizer.next_expect(Token::GreaterThan)?; // eat >
-  let end = tokenizer.pos;
+fn parse_next_script_element_parts<'a, 'b>(attributes: Vec<pc_ast::Attribute>, context: &mut Context<'a, 'b>, start: usize) -> Result<pc_ast::Node, ParseError> {
+  context.tokenizer.next_expect(Token::GreaterThan)?; // eat >
+  let end = context.tokenizer.pos;
 
-  get_buffer(tokenizer, |tokenizer| {
+  get_buffer(context.tokenizer, |tokenizer| {
     Ok(tokenizer.peek(1)? != Token::TagClose)
   })?;
 
 
 
-  parse_close_tag("script", tokenizer, start, end)?;
+  parse_close_tag("script", context, start, end)?;
 
   Ok(pc_ast::Node::Element(pc_ast::Element {
-    tag_name_location: Location {
-      start: start + 1,
-      end: start + 7
-    },
-    open_tag_location: Location {
-      start,
-      end
-    },
-    location: Location {
-      start,
-      end: tokenizer.pos
-    },
+    tag_name_location: context.tokenizer.location(start + 1, start + 7),
+    open_tag_location: context.tokenizer.location(start, end),
+    location: context.tokenizer.location(start, context.tokenizer.pos),
     tag_name: "script".to_string(),
     attributes,
     children: vec![],
   }))
 }
 
-fn parse_tag_name<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<String, ParseError> {
-  Ok(get_buffer(tokenizer, |tokenizer| { Ok(!matches!(tokenizer.peek(1)?, Token::Whitespace | Token::GreaterThan | Token::Equals | Token::SelfTagClose)) })?.to_string())
+fn parse_tag_name<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<String, ParseError> {
+  Ok(get_buffer(context.tokenizer, |tokenizer| { Ok(!matches!(tokenizer.peek(1)?, Token::Whitespace | Token::GreaterThan | Token::Equals | Token::SelfTagClose)) })?.to_string())
 }
 
-fn parse_attributes<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<Vec<pc_ast::Attribute>, ParseError> {
+fn parse_attributes<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<Vec<pc_ast::Attribute>, ParseError> {
 
   let mut attributes: Vec<pc_ast::Attribute> = vec![];
 
   loop {
-    tokenizer.eat_whitespace();
-    match tokenizer.peek(1)? {
+    context.tokenizer.eat_whitespace();
+    match context.tokenizer.peek(1)? {
       Token::SelfTagClose | Token::GreaterThan => break,
       _ => {
-        attributes.push(parse_attribute(tokenizer)?);
+        attributes.push(parse_attribute(context)?);
       }
     }
   }
@@ -519,98 +615,129 @@ fn parse_attributes<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<Vec<pc_ast::Att
   Ok(attributes)
 }
 
-fn parse_attribute<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<pc_ast::Attribute, ParseError> {
-  if tokenizer.peek(1)? == Token::CurlyOpen {
-    parse_shorthand_attribute(tokenizer)
+fn parse_attribute<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<pc_ast::Attribute, ParseError> {
+  if context.tokenizer.peek(1)? == Token::CurlyOpen {
+    parse_shorthand_attribute(context)
   } else {
-    parse_key_value_attribute(tokenizer)
+    parse_key_value_attribute(context)
   }
 }
 
-fn parse_omit_from_compilation<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<bool, ParseError> {
-  Ok(if tokenizer.peek(1)? == Token::Bang {
-    tokenizer.next()?;
+fn parse_omit_from_compilation<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<bool, ParseError> {
+  Ok(if context.tokenizer.peek(1)? == Token::Bang {
+    context.tokenizer.next()?;
     true
   } else {
     false
   })
 }
 
-fn parse_shorthand_attribute<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<pc_ast::Attribute, ParseError> {
+fn parse_shorthand_attribute<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<pc_ast::Attribute, ParseError> {
 
-  let omit_from_compilation = parse_omit_from_compilation(tokenizer)?;
+  let start = context.tokenizer.pos;
+  let omit_from_compilation = parse_omit_from_compilation(context)?;
 
-  tokenizer.next_expect(Token::CurlyOpen)?;
-  if tokenizer.peek(1)? == Token::Spread {
-    tokenizer.next_expect(Token::Spread)?;
-    let script = parse_slot_script(tokenizer)?;
+  context.tokenizer.next_expect(Token::CurlyOpen)?;
+  if context.tokenizer.peek(1)? == Token::Spread {
+    context.tokenizer.next_expect(Token::Spread)?;
+    let script = parse_slot_script(context)?;
     Ok(pc_ast::Attribute::SpreadAttribute(pc_ast::SpreadAttribute {
       omit_from_compilation,
       script,
+      location: context.tokenizer.location(start, context.tokenizer.pos)
     }))
     
   } else {
-    let reference = parse_slot_script(tokenizer)?;
+    let reference = parse_slot_script(context)?;
     Ok(pc_ast::Attribute::ShorthandAttribute(pc_ast::ShorthandAttribute {
       reference,
+      location: context.tokenizer.location(start, context.tokenizer.pos)
     }))
   }
 }
 
-fn parse_key_value_attribute<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<pc_ast::Attribute, ParseError> {
+fn parse_key_value_attribute<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<pc_ast::Attribute, ParseError> {
   
-  let name = parse_tag_name(tokenizer)?;
+  let start = context.tokenizer.pos;
+  let name = parse_tag_name(context)?;
   let mut value = None;
 
-  if tokenizer.peek(1)? == Token::Equals {
-    tokenizer.next()?; // eat =
-    value = Some(parse_attribute_value(tokenizer)?);
+  if context.tokenizer.peek(1)? == Token::Equals {
+    context.tokenizer.next()?; // eat =
+    value = Some(parse_attribute_value(context)?);
   }
 
   Ok(pc_ast::Attribute::KeyValueAttribute(pc_ast::KeyValueAttribute {
     name,
-    value
+    value,
+    location: context.tokenizer.location(start, context.tokenizer.pos)
   }))
 }
 
-fn parse_attribute_value<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<pc_ast::AttributeValue, ParseError> {
-  let pos = tokenizer.pos;
-  match tokenizer.peek(1)? {
-    Token::SingleQuote | Token::DoubleQuote => parse_string(tokenizer),
-    Token::CurlyOpen => parse_attribute_slot(tokenizer),
+fn parse_attribute_value<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<pc_ast::AttributeValue, ParseError> {
+  let pos = context.tokenizer.pos;
+  match context.tokenizer.peek(1)? {
+    Token::SingleQuote | Token::DoubleQuote => parse_string(context),
+    Token::CurlyOpen => parse_attribute_slot(context),
     _ => Err(ParseError::unexpected_token(pos))
   }
 }
 
-fn parse_attribute_slot<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<pc_ast::AttributeValue, ParseError> {
-  tokenizer.next_expect(Token::CurlyOpen)?;
-  let script = parse_slot_script(tokenizer)?;
+fn parse_attribute_slot<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<pc_ast::AttributeValue, ParseError> {
+  context.tokenizer.next_expect(Token::CurlyOpen)?;
+  let script = parse_slot_script(context)?;
   Ok(pc_ast::AttributeValue::Slot(script))
 }
 
 
-fn parse_string<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<pc_ast::AttributeValue, ParseError> {
-  let start = tokenizer.pos;
-  let quote = tokenizer.next()?;
+fn parse_string<'a, 'b>(context: &mut Context<'a, 'b>) -> Result<pc_ast::AttributeValue, ParseError> {
+  let start = context.tokenizer.pos;
+  let quote = context.tokenizer.next()?;
+  let mut values = vec![];
 
+  loop {
+    let literal_start = context.tokenizer.pos;
+    let value = get_buffer(context.tokenizer, |tokenizer| {
+      Ok(tokenizer.peek(1)? != quote && tokenizer.curr_char()? != b'{')
+    })
+    .and_then(|value| {
+      if context.tokenizer.is_eof() {
+        Err(ParseError::eof())
+      } else {
+        Ok(value)
+      }
+    })
+    .or(Err(ParseError::unterminated("Unterminated string literal.".to_string(), start, context.tokenizer.pos)))?;
+
+    if value.len() > 0 {
+      values.push(pc_ast::AttributeDynamicStringPart::Literal(pc_ast::AttributeStringValue {
+        value: value.to_string(),
+        location: context.tokenizer.location(literal_start, context.tokenizer.pos)
+      }));
+    }
 
-  get_buffer(tokenizer, |tokenizer| { Ok(tokenizer.peek(1)? != quote) })
-  .and_then(|value| {
-    tokenizer.next_expect(quote)?;
-    Ok(value)
-  })
-  .or(Err(ParseError::unterminated("Unterminated string literal.".to_string(), start, tokenizer.pos)))
-  .and_then(|value| {
-    Ok(pc_ast::AttributeValue::String(pc_ast::AttributeStringValue { 
-      value: value.to_string(),
-      location: Location::new(start + 1, tokenizer.pos - 1)
-    }))
-  })
+    if context.tokenizer.peek(1)? == quote {
+      context.tokenizer.next()?;
+      break;
+    }
+
+    context.tokenizer.pos += 1; // eat {
+    values.push(pc_ast::AttributeDynamicStringPart::Slot(parse_slot_script(context)?));
+  }
+
+  let location = context.tokenizer.location(start + 1, context.tokenizer.pos - 1);
+
+  match values.as_slice() {
+    [] => Ok(pc_ast::AttributeValue::String(pc_ast::AttributeStringValue { value: "".to_string(), location })),
+    [pc_ast::AttributeDynamicStringPart::Literal(value)] => Ok(pc_ast::AttributeValue::String(value.clone())),
+    _ => Ok(pc_ast::AttributeValue::DynamicString(pc_ast::AttributeDynamicStringValue { values, location }))
+  }
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::base::ast::{Position, LineIndex};
 
   #[test]
   fn can_smoke_parse_various_nodes() {
@@ -680,6 +807,10 @@ mod tests {
         </span>
       </div>",
 
+      // interpolated attributes
+      "<div class=\"btn {variant} btn-{size}\"></div>",
+      "<div title={`Hello ${name}!`}></div>",
+
       // mixed elements
     ];
 
@@ -698,55 +829,79 @@ mod tests {
 
   #[test]
   fn displays_error_for_unterminated_element() {
-    assert_eq!(parse("<div>"), Err(ParseError::unterminated("Unterminated element.".to_string(), 0, 5)));
+    assert_eq!(parse("<div>"), Err(ParseError::unterminated("Unterminated element.".to_string(), 0, 5).with_positions(&LineIndex::new("<div>"))));
   }
 
   #[test]
   fn displays_error_for_unterminated_style_element() {
-    assert_eq!(parse("<style>"), Err(ParseError::unterminated("Unterminated element.".to_string(), 0, 7)));
+    assert_eq!(parse("<style>"), Err(ParseError::unterminated("Unterminated element.".to_string(), 0, 7).with_positions(&LineIndex::new("<style>"))));
   }
 
   #[test]
   fn displays_error_for_unterminated_script_element() {
-    assert_eq!(parse("<script>"), Err(ParseError::unterminated("Unterminated element.".to_string(), 0, 8)));
+    assert_eq!(parse("<script>"), Err(ParseError::unterminated("Unterminated element.".to_string(), 0, 8).with_positions(&LineIndex::new("<script>"))));
   }
 
 
   #[test]
   fn displays_error_for_incorrect_close_tag() {
-    assert_eq!(parse("<style></script>"), Err(ParseError::unterminated("Incorrect closing tag. This should be </style>.".to_string(), 7, 15)));
+    assert_eq!(parse("<style></script>"), Err(ParseError::unterminated("Incorrect closing tag. This should be </style>.".to_string(), 7, 15).with_positions(&LineIndex::new("<style></script>"))));
   }
 
   #[test]
   fn displays_error_for_unterminated_attribute_string() {
-    assert_eq!(parse("<div a=\"b>"), Err(ParseError::unterminated("Unterminated string literal.".to_string(), 7, 10)));
+    assert_eq!(parse("<div a=\"b>"), Err(ParseError::unterminated("Unterminated string literal.".to_string(), 7, 10).with_positions(&LineIndex::new("<div a=\"b>"))));
   }
 
   #[test]
   fn displays_error_for_unterminated_slot() {
-    assert_eq!(parse("{ab"), Err(ParseError::unterminated("Unterminated slot.".to_string(), 1, 3)));
+    assert_eq!(parse("{ab"), Err(ParseError::unterminated("Unterminated slot.".to_string(), 1, 3).with_positions(&LineIndex::new("{ab"))));
   }
 
   #[test]
   fn displays_css_errors() {
-    assert_eq!(parse("<style>div { color: red; </style>"), Err(ParseError::unterminated("Unterminated bracket.".to_string(), 11, 27)));
+    assert_eq!(parse("<style>div { color: red; </style>"), Err(ParseError::unterminated("Unterminated bracket.".to_string(), 11, 27).with_positions(&LineIndex::new("<style>div { color: red; </style>"))));
+  }
+
+  #[test]
+  fn includes_line_and_column_positions_in_errors() {
+    let err = parse("<div>\n  <span a=\"b>").unwrap_err();
+    assert_eq!(err.location.start_position, Position { line: 1, column: 10, utf16_column: 10 });
+  }
+
+  #[test]
+  fn can_recover_from_syntax_errors() {
+    let source = "<div>\n  <span>\n</div><a b=></a>{#if x}<b />";
+    let (node, errors) = parse_with_recovery(source);
+    assert_eq!(errors.len(), 4);
+    assert_eq!(errors[0].message, "Incorrect closing tag. This should be </span>.");
+    assert_eq!(errors[0].location.start_position.line, 2);
+    assert_eq!(errors[3], ParseError::unterminated("Unterminated block.".to_string(), 31, 43).with_positions(&LineIndex::new(source)));
+    assert_eq!(node.to_string(), "<div><span></span> </div>[block]");
+  }
+
+  #[test]
+  fn can_recover_from_css_syntax_errors() {
+    let (node, errors) = parse_with_recovery("<style>div > { } span { color: red; }</style><div />");
+    assert_eq!(errors.len(), 1);
+    assert_eq!(pc_ast::get_children(&node).unwrap().len(), 2);
   }
 
   #[test]
   fn display_error_for_close_tag_without_open() {
-    assert_eq!(parse("</div>"), Err(ParseError::unexpected("Closing tag doesn't have an open tag.".to_string(), 0, 6)));
+    assert_eq!(parse("</div>"), Err(ParseError::unexpected("Closing tag doesn't have an open tag.".to_string(), 0, 6).with_positions(&LineIndex::new("</div>"))));
   }
 
 
   #[test]
   fn displays_error_if_void_close_tag_present() {
-    assert_eq!(parse("</meta>"), Err(ParseError::unexpected("Void tag's shouldn't be closed.".to_string(), 0, 7)));
+    assert_eq!(parse("</meta>"), Err(ParseError::unexpected("Void tag's shouldn't be closed.".to_string(), 0, 7).with_positions(&LineIndex::new("</meta>"))));
   }
 
   #[test]
   fn can_parse_fragments() {
     parse("<><div /></>").unwrap();
-    // assert_eq!(parse("<><div /></>"), Err(ParseError::unexpected("Void tag's shouldn't be closed.".to_string(), 0, 7)));
+    // assert_eq!(parse("<><div /></>"), Err(ParseError::unexpected("Void tag's shouldn't be closed.".to_string(), 0, 7).with_positions(&LineIndex::new("<><div /></>"))));
   }
 
   #[test]
@@ -755,6 +910,6 @@ mod tests {
       <div />
       <div />
     </>} />").unwrap();
-    // assert_eq!(parse("<><div /></>"), Err(ParseError::unexpected("Void tag's shouldn't be closed.".to_string(), 0, 7)));
+    // assert_eq!(parse("<><div /></>"), Err(ParseError::unexpected("Void tag's shouldn't be closed.".to_string(), 0, 7).with_positions(&LineIndex::new("<><div /></>"))));
   }
 }
@@ -1,6 +1,279 @@
 use super::virt;
-use mutations;
+use super::mutation::*;
 
-pub fn diff<'a>(a: virt::Node<'a>, b: virt::Node<'a>)-> Vec<mutation::Mutation<'a?> {
-  
-}
\ No newline at end of file
+// Note that IDs are ignored when diffing since they're generated from evaluation
+// order, so they shift around whenever something is added or removed.
+
+pub fn diff(a: &virt::Node, b: &virt::Node) -> Vec<Mutation> {
+  let mut mutations = vec![];
+  diff_node(a, b, &vec![], &mut mutations);
+  mutations
+}
+
+fn diff_node(a: &virt::Node, b: &virt::Node, path: &Vec<usize>, mutations: &mut Vec<Mutation>) {
+  match (a, b) {
+    (virt::Node::Element(a_el), virt::Node::Element(b_el)) => {
+      if a_el.tag_name != b_el.tag_name {
+        replace_node(b, path, mutations);
+      } else {
+        diff_attributes(&a_el.attributes, &b_el.attributes, path, mutations);
+        diff_children(&a_el.children, &b_el.children, path, mutations);
+      }
+    },
+    (virt::Node::Fragment(a_fragment), virt::Node::Fragment(b_fragment)) => {
+      diff_children(&a_fragment.children, &b_fragment.children, path, mutations);
+    },
+    (virt::Node::Text(a_text), virt::Node::Text(b_text)) => {
+      if a_text.value != b_text.value {
+        mutations.push(Mutation::new(path.clone(), Action::SetText(SetText {
+          value: b_text.value.to_string()
+        })));
+      }
+    },
+    (virt::Node::StyleElement(a_style), virt::Node::StyleElement(b_style)) => {
+      if a_style.sheet != b_style.sheet {
+        mutations.push(Mutation::new(path.clone(), Action::ReplaceSheet(ReplaceSheet {
+          sheet: b_style.sheet.clone()
+        })));
+      }
+    },
+    _ => {
+      replace_node(b, path, mutations);
+    }
+  }
+}
+
+fn replace_node(replacement: &virt::Node, path: &Vec<usize>, mutations: &mut Vec<Mutation>) {
+  mutations.push(Mutation::new(path.clone(), Action::ReplaceNode(ReplaceNode {
+    replacement: replacement.clone()
+  })));
+}
+
+fn diff_attributes(a: &Vec<virt::Attribute>, b: &Vec<virt::Attribute>, path: &Vec<usize>, mutations: &mut Vec<Mutation>) {
+  for b_attr in b {
+    let a_attr_option = a.iter().find(|a_attr| a_attr.name == b_attr.name);
+    let changed = match a_attr_option {
+      Some(a_attr) => a_attr.value != b_attr.value,
+      None => true
+    };
+    if changed {
+      mutations.push(Mutation::new(path.clone(), Action::SetAttribute(SetAttribute {
+        name: b_attr.name.to_string(),
+        value: b_attr.value.clone()
+      })));
+    }
+  }
+
+  for a_attr in a {
+    if !b.iter().any(|b_attr| b_attr.name == a_attr.name) {
+      mutations.push(Mutation::new(path.clone(), Action::RemoveAttribute(RemoveAttribute {
+        name: a_attr.name.to_string()
+      })));
+    }
+  }
+}
+
+fn diff_children(a: &Vec<virt::Node>, b: &Vec<virt::Node>, path: &Vec<usize>, mutations: &mut Vec<Mutation>) {
+
+  // mirror of the children as mutations are applied
+  let mut curr: Vec<&virt::Node> = a.iter().collect();
+
+  for (index, b_child) in b.iter().enumerate() {
+
+    // remove old children that don't exist anymore if the new child is further down
+    while index < curr.len()
+      && !nodes_equal(curr[index], b_child)
+      && !contains_node(&b[index..], curr[index])
+      && curr[index + 1..].iter().any(|child| nodes_equal(child, b_child)) {
+      curr.remove(index);
+      mutations.push(Mutation::new(path.clone(), Action::DeleteChild(DeleteChild {
+        index
+      })));
+    }
+
+    if index < curr.len() && nodes_equal(curr[index], b_child) {
+      continue;
+    }
+
+    let moved_index_option = (index + 1..curr.len()).find(|i| nodes_equal(curr[*i], b_child));
+
+    if let Some(moved_index) = moved_index_option {
+      let child = curr.remove(moved_index);
+      curr.insert(index, child);
+      mutations.push(Mutation::new(path.clone(), Action::MoveChild(MoveChild {
+        old_index: moved_index,
+        new_index: index
+      })));
+    } else if index < curr.len() && can_patch(curr[index], b_child) && !contains_node(&b[index + 1..], curr[index]) {
+      let mut child_path = path.clone();
+      child_path.push(index);
+      diff_node(curr[index], b_child, &child_path, mutations);
+      curr[index] = b_child;
+    } else {
+      curr.insert(index, b_child);
+      mutations.push(Mutation::new(path.clone(), Action::InsertChild(InsertChild {
+        child: b_child.clone(),
+        index
+      })));
+    }
+  }
+
+  while curr.len() > b.len() {
+    curr.pop();
+    mutations.push(Mutation::new(path.clone(), Action::DeleteChild(DeleteChild {
+      index: curr.len()
+    })));
+  }
+}
+
+fn contains_node(nodes: &[virt::Node], node: &virt::Node) -> bool {
+  nodes.iter().any(|child| nodes_equal(child, node))
+}
+
+fn can_patch(a: &virt::Node, b: &virt::Node) -> bool {
+  match (a, b) {
+    (virt::Node::Element(a_el), virt::Node::Element(b_el)) => a_el.tag_name == b_el.tag_name,
+    (virt::Node::Fragment(_), virt::Node::Fragment(_)) => true,
+    (virt::Node::Text(_), virt::Node::Text(_)) => true,
+    (virt::Node::StyleElement(_), virt::Node::StyleElement(_)) => true,
+    _ => false
+  }
+}
+
+fn nodes_equal(a: &virt::Node, b: &virt::Node) -> bool {
+  match (a, b) {
+    (virt::Node::Element(a_el), virt::Node::Element(b_el)) => {
+      a_el.tag_name == b_el.tag_name &&
+      a_el.attributes.len() == b_el.attributes.len() &&
+      a_el.attributes.iter().all(|a_attr| {
+        b_el.attributes.iter().any(|b_attr| a_attr.name == b_attr.name && a_attr.value == b_attr.value)
+      }) &&
+      children_equal(&a_el.children, &b_el.children)
+    },
+    (virt::Node::Fragment(a_fragment), virt::Node::Fragment(b_fragment)) => {
+      children_equal(&a_fragment.children, &b_fragment.children)
+    },
+    (virt::Node::Text(a_text), virt::Node::Text(b_text)) => a_text.value == b_text.value,
+    (virt::Node::StyleElement(a_style), virt::Node::StyleElement(b_style)) => a_style.sheet == b_style.sheet,
+    _ => false
+  }
+}
+
+fn children_equal(a: &Vec<virt::Node>, b: &Vec<virt::Node>) -> bool {
+  a.len() == b.len() && a.iter().zip(b.iter()).all(|(a_child, b_child)| nodes_equal(a_child, b_child))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::pc::runtime::graph::{DependencyGraph, Dependency};
+  use crate::pc::runtime::vfs::{VirtualFileSystem};
+  use crate::pc::runtime::evaluator::{evaluate};
+  use crate::js::runtime::helpers::{Helpers};
+  use crate::js::runtime::virt as js_virt;
+
+  #[test]
+  fn doesnt_emit_mutations_for_identical_trees() {
+    let a = evaluate_source("<div a=\"b\">hello</div>");
+    let b = evaluate_source("<div a=\"b\">hello</div>");
+    assert_eq!(diff(&a, &b), vec![]);
+  }
+
+  #[test]
+  fn can_diff_various_trees() {
+    let cases = [
+      ("<div>a</div>", "<div>b</div>"),
+      ("<div a=\"b\"></div>", "<div a=\"c\" d></div>"),
+      ("<div a=\"b\"></div>", "<div></div>"),
+      ("<div><a /><b /></div>", "<div><b /><a /></div>"),
+      ("<div><a /><b /><c /></div>", "<div><c /></div>"),
+      ("<div><a /></div>", "<div><b /><a /><c /></div>"),
+      ("<div><a /></div>", "<span><a /></span>"),
+      ("<style>div { color: red; }</style><div />", "<style>div { color: blue; }</style><div />"),
+      ("a<div />b", "<div />b<span />a"),
+      ("<div>{#if true}<a />{/}<b /></div>", "<div>{#if false}<a />{/}<b /></div>"),
+    ];
+
+    for (a_source, b_source) in cases.iter() {
+      let a = evaluate_source(a_source);
+      let b = evaluate_source(b_source);
+      let mutations = diff(&a, &b);
+      assert_ne!(mutations, vec![]);
+      assert!(nodes_equal(&patch(&a, &mutations), &b), "{} -> {} ({:?})", a_source, b_source, mutations);
+    }
+  }
+
+  #[test]
+  fn moves_children_instead_of_replacing_them() {
+    let a = evaluate_source("<div><a /><b /></div>");
+    let b = evaluate_source("<div><b /><a /></div>");
+    let mutations = diff(&a, &b);
+
+    assert_eq!(mutations.len(), 1);
+    assert_eq!(mutations[0].action, Action::MoveChild(MoveChild { old_index: 2, new_index: 1 }));
+  }
+
+  fn patch(node: &virt::Node, mutations: &Vec<Mutation>) -> virt::Node {
+    let mut root = node.clone();
+    for mutation in mutations {
+      let mut target = &mut root;
+      for index in &mutation.node_path {
+        target = match target {
+          virt::Node::Element(el) => &mut el.children[*index],
+          virt::Node::Fragment(fragment) => &mut fragment.children[*index],
+          _ => panic!("Invalid node path")
+        };
+      }
+      let children = match target {
+        virt::Node::Element(el) => Some(&mut el.children),
+        virt::Node::Fragment(fragment) => Some(&mut fragment.children),
+        _ => None
+      };
+      match &mutation.action {
+        Action::InsertChild(action) => { children.unwrap().insert(action.index, action.child.clone()); },
+        Action::DeleteChild(action) => { children.unwrap().remove(action.index); },
+        Action::MoveChild(action) => {
+          let children = children.unwrap();
+          let child = children.remove(action.old_index);
+          children.insert(action.new_index, child);
+        },
+        Action::SetAttribute(action) => {
+          if let virt::Node::Element(el) = target {
+            match el.attributes.iter_mut().find(|attr| attr.name == action.name) {
+              Some(attr) => { attr.value = action.value.clone(); },
+              None => { el.attributes.push(virt::Attribute { id: "".to_string(), name: action.name.to_string(), value: action.value.clone() }); }
+            };
+          }
+        },
+        Action::RemoveAttribute(action) => {
+          if let virt::Node::Element(el) = target {
+            el.attributes.retain(|attr| attr.name != action.name);
+          }
+        },
+        Action::SetText(action) => {
+          if let virt::Node::Text(text) = target {
+            text.value = action.value.to_string();
+          }
+        },
+        Action::ReplaceSheet(action) => {
+          if let virt::Node::StyleElement(style) = target {
+            style.sheet = action.sheet.clone();
+          }
+        },
+        Action::ReplaceNode(action) => {
+          *target = action.replacement.clone();
+        }
+      };
+    }
+    root
+  }
+
+  fn evaluate_source<'a>(code: &'a str) -> virt::Node {
+    let mut graph = DependencyGraph::new();
+    let uri = "some-file.pc".to_string();
+    let vfs = VirtualFileSystem::new(Box::new(|_| "".to_string()), Box::new(|_| true), Box::new(|_,uri| uri.to_string()));
+    graph.dependencies.insert(uri.clone(), Dependency::from_source(code.to_string(), &uri, &vfs).unwrap());
+    let data = js_virt::JsValue::JsObject(js_virt::JsObject::new());
+    evaluate(&uri, &graph, &vfs, &Helpers::new(), &data, None).unwrap().unwrap()
+  }
+}
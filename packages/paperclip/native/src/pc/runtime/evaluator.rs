@@ -2,23 +2,31 @@ use super::super::ast;
 use crate::base::runtime::{RuntimeError};
 use crate::base::ast::{Location};
 use super::virt;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::iter::FromIterator;
+use std::rc::Rc;
+use std::cell::RefCell;
 use super::graph::{DependencyGraph, DependencyContent, Dependency};
 use super::vfs::{VirtualFileSystem};
-use crate::css::runtime::evaluator::{evaluate as evaluate_css};
+use crate::css::runtime::evaluator::{evaluate as evaluate_css, collect_keyframes_names};
 use crate::js::runtime::evaluator::{evaluate as evaluate_js};
 use crate::js::runtime::virt as js_virt;
+use crate::js::runtime::helpers::{Helpers};
 use crate::js::ast as js_ast;
 use crate::css::runtime::virt as css_virt;
+use crate::css::ast as css_ast;
 use crate::base::utils::{get_document_style_scope, is_relative_path};
+use crate::pc::infer::{infer};
 use crc::{crc32};
 
+const NO_FALLTHROUGH_ATTRIBUTE_NAME: &str = "no-fallthrough";
+
 
 #[derive(Clone)]
 pub struct Context<'a> {
   pub graph: &'a DependencyGraph,
   pub vfs: &'a VirtualFileSystem,
+  pub helpers: &'a Helpers,
   pub uri: &'a String,  
   pub import_ids: HashSet<&'a String>,
   pub part_ids: HashSet<&'a String>,
@@ -26,14 +34,19 @@ pub struct Context<'a> {
   pub data: &'a js_virt::JsValue,
   pub render_call_stack: Vec<(String, RenderStrategy)>,
   pub id_seed: String,
-  pub id_count: i32
+  pub id_count: i32,
+
+  // properties referenced by each rendered part, shared across the whole render
+  pub part_properties: Rc<RefCell<HashMap<String, Rc<HashSet<String>>>>>
 }
 
 #[derive(Clone, PartialEq, Debug)] 
 pub enum RenderStrategy {
   Instance,
   Part(String),
-  Preview
+
+  // the first preview if a name isn't provided
+  Preview(Option<String>)
 }
 
 impl<'a> Context<'a> {
@@ -43,17 +56,26 @@ impl<'a> Context<'a> {
   }
 }
 
-pub fn evaluate<'a>(uri: &String, graph: &'a DependencyGraph, vfs: &'a VirtualFileSystem, data: &js_virt::JsValue, part_option: Option<String>) -> Result<Option<virt::Node>, RuntimeError>  {
+pub fn evaluate<'a>(uri: &String, graph: &'a DependencyGraph, vfs: &'a VirtualFileSystem, helpers: &'a Helpers, data: &js_virt::JsValue, part_option: Option<String>) -> Result<Option<virt::Node>, RuntimeError>  {
+  evaluate_root(uri, graph, vfs, helpers, data, if let Some(part) = part_option {
+    RenderStrategy::Part(part)
+  } else {
+    RenderStrategy::Preview(None)
+  })
+}
+
+// <preview name="mobile" width="375" height="667">
+pub fn evaluate_preview<'a>(uri: &String, graph: &'a DependencyGraph, vfs: &'a VirtualFileSystem, helpers: &'a Helpers, data: &js_virt::JsValue, name: &String) -> Result<Option<virt::Node>, RuntimeError>  {
+  evaluate_root(uri, graph, vfs, helpers, data, RenderStrategy::Preview(Some(name.to_string())))
+}
+
+fn evaluate_root<'a>(uri: &String, graph: &'a DependencyGraph, vfs: &'a VirtualFileSystem, helpers: &'a Helpers, data: &js_virt::JsValue, render_strategy: RenderStrategy) -> Result<Option<virt::Node>, RuntimeError>  {
 
   let dep = graph.dependencies.get(uri).unwrap();
   if let DependencyContent::Node(node_expr) = &dep.content {
 
-    let mut context = create_context(node_expr, uri, graph, vfs, data, None);
-    let mut root_option = evaluate_instance_node(node_expr, &mut context, if let Some(part) = part_option {
-      RenderStrategy::Part(part)
-    } else {
-      RenderStrategy::Preview
-    })?;
+    let mut context = create_context(node_expr, uri, graph, vfs, helpers, data, None);
+    let mut root_option = evaluate_instance_node(node_expr, &mut context, render_strategy)?;
 
     match root_option {
       Some(ref mut root) => {
@@ -65,7 +87,7 @@ pub fn evaluate<'a>(uri: &String, graph: &'a DependencyGraph, vfs: &'a VirtualFi
 
     Ok(root_option)
   } else {
-    Err(RuntimeError::new("Incorrect file type".to_string(), uri, &Location { start: 0, end: 0 }))
+    Err(RuntimeError::new("Incorrect file type".to_string(), uri, &Location::start_of_file()))
   }
 }
 
@@ -81,8 +103,8 @@ pub fn get_instance_target_node<'a>(node_expr: &ast::Node, render_strategy: Rend
     RenderStrategy::Part(id) => find_child(node_expr, |child|  {
       child.tag_name == "part" && ast::get_attribute_value("id", child) == Some(&id)
     }),
-    RenderStrategy::Preview => find_child(node_expr, |child|  {
-      child.tag_name == "preview"
+    RenderStrategy::Preview(name_option) => find_child(node_expr, |child|  {
+      child.tag_name == "preview" && (name_option == None || ast::get_attribute_value("name", child) == name_option.as_ref())
     })
   };
 
@@ -108,24 +130,65 @@ where TTest: Fn(&ast::Element) -> bool {
   }
 }
 
-pub fn evaluate_document_styles<'a>(node_expr: &ast::Node, uri: &String, vfs: &'a VirtualFileSystem) -> Result<css_virt::CSSSheet, RuntimeError>  {
+pub fn evaluate_document_styles<'a>(node_expr: &ast::Node, uri: &String, keyframes_names: &HashSet<String>, vfs: &'a VirtualFileSystem) -> Result<css_virt::CSSSheet, RuntimeError>  {
   let mut sheet = css_virt::CSSSheet {
     rules: vec![] 
   };
-  let children_option = ast::get_children(&node_expr);
   let scope = get_document_style_scope(uri);
-  if let Some(children) = children_option {
-    // style elements are only allowed in root, so no need to traverse
-    for child in children {
-      if let ast::Node::StyleElement(style_element) = &child {
-        sheet.extend(evaluate_css(&style_element.sheet, uri, &scope, vfs)?);
-      }
-    }
+  for style_sheet in get_document_sheets(node_expr) {
+    sheet.extend(evaluate_css(style_sheet, uri, &scope, keyframes_names, vfs)?);
   }
 
   Ok(sheet)
 }
 
+// style elements are only allowed in root, so no need to traverse
+fn get_document_sheets<'a>(node_expr: &'a ast::Node) -> Vec<&'a css_ast::Sheet> {
+  match ast::get_children(&node_expr) {
+    Some(children) => children.iter().filter_map(|child| match child {
+      ast::Node::StyleElement(style_element) => Some(&style_element.sheet),
+      _ => None
+    }).collect(),
+    None => vec![]
+  }
+}
+
+pub fn get_document_keyframes_names(node_expr: &ast::Node) -> HashSet<String> {
+  let mut keyframes_names = HashSet::new();
+  for sheet in get_document_sheets(node_expr) {
+    collect_keyframes_names(sheet, &mut keyframes_names);
+  }
+  keyframes_names
+}
+
+// @keyframes are scoped to a document, so their names are shared between the document's
+// style elements & the sheets it imports
+fn get_scope_keyframes_names(uri: &String, graph: &DependencyGraph) -> HashSet<String> {
+  let mut keyframes_names = HashSet::new();
+  collect_scope_keyframes_names(uri, graph, &mut HashSet::new(), &mut keyframes_names);
+  keyframes_names
+}
+
+fn collect_scope_keyframes_names(uri: &String, graph: &DependencyGraph, visited: &mut HashSet<String>, keyframes_names: &mut HashSet<String>) {
+  if !visited.insert(uri.to_string()) {
+    return;
+  }
+  if let Some(dependency) = graph.dependencies.get(uri) {
+    match &dependency.content {
+      DependencyContent::Node(node) => keyframes_names.extend(get_document_keyframes_names(node)),
+      DependencyContent::StyleSheet(sheet) => collect_keyframes_names(sheet, keyframes_names),
+      DependencyContent::Data(_) => {}
+    }
+
+    // imported documents have their own scope
+    for dep_uri in dependency.dependencies.values() {
+      if let Some(Dependency { content: DependencyContent::StyleSheet(_), .. }) = graph.dependencies.get(dep_uri) {
+        collect_scope_keyframes_names(dep_uri, graph, visited, keyframes_names);
+      }
+    }
+  }
+}
+
 pub fn evaluate_jumbo_style<'a>(entry_expr: &ast::Node, context: &'a mut Context) -> Result<virt::Node, RuntimeError>  {
 
   let mut sheet = css_virt::CSSSheet {
@@ -144,24 +207,33 @@ pub fn evaluate_jumbo_style<'a>(entry_expr: &ast::Node, context: &'a mut Context
     }
     let dep_sheet = match &dependency.content {
       DependencyContent::Node(node) => {
-        evaluate_document_styles(node, &dependency.uri, context.vfs)?
+        evaluate_document_styles(node, &dependency.uri, &get_scope_keyframes_names(&dependency.uri, context.graph), context.vfs)?
       },
-      DependencyContent::StyleSheet(sheet) => {
-        let scope = if let Some(dependent) = dependent_option {
-          get_document_style_scope(&dependent.uri)
+      DependencyContent::StyleSheet(_) => {
+
+        // inlined by the importing sheet
+        if let Some(Dependency { content: DependencyContent::StyleSheet(_), .. }) = dependent_option {
+          continue;
+        }
+
+        let scope_uri = if let Some(dependent) = dependent_option {
+          &dependent.uri
         } else {
-          get_document_style_scope(&dependency.uri)
+          &dependency.uri
         };
+        let scope = get_document_style_scope(scope_uri);
+        let keyframes_names = get_scope_keyframes_names(scope_uri, context.graph);
         
-        evaluate_css(&sheet, &dependency.uri, &scope, context.vfs)?
-      }
+        evaluate_sheet_with_imports(dependency, &scope, &keyframes_names, context, &mut HashSet::new())?
+      },
+      DependencyContent::Data(_) => continue
     };
 
     sheet.extend(dep_sheet);
   }
 
   // this element styles always get priority.
-  sheet.extend(evaluate_document_styles(&entry_expr, &uri, context.vfs)?);
+  sheet.extend(evaluate_document_styles(&entry_expr, &uri, &get_scope_keyframes_names(uri, context.graph), context.vfs)?);
 
   
   Ok(virt::Node::StyleElement(virt::StyleElement {
@@ -170,17 +242,41 @@ pub fn evaluate_jumbo_style<'a>(entry_expr: &ast::Node, context: &'a mut Context
   }))
 }
 
+// @import-ed sheets are inlined, in order, before the rules of the sheet that imports them
+fn evaluate_sheet_with_imports<'a>(dependency: &Dependency, scope: &String, keyframes_names: &HashSet<String>, context: &Context, visited: &mut HashSet<String>) -> Result<css_virt::CSSSheet, RuntimeError> {
+  let mut sheet = css_virt::CSSSheet {
+    rules: vec![]
+  };
+
+  // skip circular imports
+  if !visited.insert(dependency.uri.to_string()) {
+    return Ok(sheet);
+  }
+
+  if let DependencyContent::StyleSheet(ast_sheet) = &dependency.content {
+    for import in css_ast::get_imports(ast_sheet) {
+      let imported_option = dependency.dependencies.get(&import.uri).and_then(|dep_uri| context.graph.dependencies.get(dep_uri));
+      if let Some(imported) = imported_option {
+        sheet.extend(evaluate_sheet_with_imports(imported, scope, keyframes_names, context, visited)?);
+      }
+    }
+    sheet.extend(evaluate_css(ast_sheet, &dependency.uri, scope, keyframes_names, context.vfs)?);
+  }
+
+  Ok(sheet)
+}
+
 pub fn evaluate_instance_node<'a>(node_expr: &ast::Node, context: &'a mut Context, render_strategy: RenderStrategy) -> Result<Option<virt::Node>, RuntimeError>  {
   context.render_call_stack.push((context.uri.to_string(), render_strategy.clone()));
   evaluate_node(get_instance_target_node(node_expr, render_strategy), true, context)
 }
 
-fn create_context<'a>(node_expr: &'a ast::Node, uri: &'a String, graph: &'a DependencyGraph, vfs: &'a VirtualFileSystem, data: &'a js_virt::JsValue,  parent_option: Option<&'a Context>) -> Context<'a> {
+fn create_context<'a>(node_expr: &'a ast::Node, uri: &'a String, graph: &'a DependencyGraph, vfs: &'a VirtualFileSystem, helpers: &'a Helpers, data: &'a js_virt::JsValue,  parent_option: Option<&'a Context>) -> Context<'a> {
 
-  let (render_call_stack, curr_id_count) = if let Some(parent) = parent_option {
-    (parent.render_call_stack.clone(), parent.id_count)
+  let (render_call_stack, curr_id_count, part_properties) = if let Some(parent) = parent_option {
+    (parent.render_call_stack.clone(), parent.id_count, parent.part_properties.clone())
   } else {
-    (vec![], 0)
+    (vec![], 0, Rc::new(RefCell::new(HashMap::new())))
   };
 
   let scope = get_document_style_scope(uri);
@@ -190,13 +286,31 @@ fn create_context<'a>(node_expr: &'a ast::Node, uri: &'a String, graph: &'a Depe
     graph,
     uri,
     vfs,
+    helpers,
     render_call_stack,
-    import_ids: HashSet::from_iter(ast::get_import_ids(node_expr)),
+    import_ids: HashSet::from_iter(get_component_import_ids(node_expr, uri, graph)),
     part_ids: HashSet::from_iter(ast::get_part_ids(node_expr)),
     scope,
     data,
     id_seed,
-    id_count: 0
+    id_count: 0,
+    part_properties
+  }
+}
+
+// JSON imports are exposed as data, so they can't be rendered as components
+fn get_component_import_ids<'a>(node_expr: &'a ast::Node, uri: &String, graph: &'a DependencyGraph) -> Vec<&'a String> {
+  let dep_option = graph.dependencies.get(uri);
+  ast::get_import_ids(node_expr).into_iter().filter(|id| {
+    get_data_import(id, dep_option, graph) == None
+  }).collect()
+}
+
+fn get_data_import<'a>(id: &String, dep_option: Option<&Dependency>, graph: &'a DependencyGraph) -> Option<&'a serde_json::Value> {
+  let dep_uri = dep_option?.dependencies.get(id)?;
+  match &graph.dependencies.get(dep_uri)?.content {
+    DependencyContent::Data(value) => Some(value),
+    _ => None
   }
 }
 
@@ -256,7 +370,22 @@ fn evaluate_element<'a>(element: &ast::Element, is_root: bool, context: &'a mut
 
 fn evaluate_preview_element<'a>(element: &ast::Element, is_root: bool, context: &'a mut Context) -> Result<Option<virt::Node>, RuntimeError> {
   if is_root {
-    evaluate_children_as_fragment(&element.children, context)
+
+    // fixtures are available under their import id, e.g: {fixtures.label}
+    let mut data = context.data.clone();
+    if let js_virt::JsValue::JsObject(ref mut data) = data {
+      let dep_option = context.graph.dependencies.get(context.uri);
+      for (id, _) in dep_option.map(|dep| dep.dependencies.iter()).into_iter().flatten() {
+        if let Some(value) = get_data_import(id, dep_option, context.graph) {
+          data.values.insert(id.to_string(), js_virt::JsValue::from_json(value));
+        }
+      }
+    }
+    let mut child_context = context.clone();
+    child_context.data = &data;
+    let result = evaluate_children_as_fragment(&element.children, &mut child_context);
+    context.id_count = child_context.id_count;
+    result
   } else {
     Ok(None)
   }
@@ -316,7 +445,7 @@ fn check_instance_loop<'a>(strategy: &RenderStrategy, element: &ast::Element, co
   let tag = match strategy {
     RenderStrategy::Instance => "self".to_string(),
     RenderStrategy::Part(id) => id.to_string(),
-    RenderStrategy::Preview => "preview".to_string(),
+    RenderStrategy::Preview(_) => "preview".to_string(),
   };
   
   if in_render_stack(strategy, context) {
@@ -349,7 +478,7 @@ fn evaluate_part_instance_element<'a>(element: &ast::Element, context: &'a mut C
   } else {
 
     // This should _never_ happen
-    Err(RuntimeError::unknown(context.uri))
+    Err(RuntimeError::unknown(context.uri, &element.location))
   }
 }
 
@@ -379,7 +508,7 @@ fn create_component_instance_data<'a>(instance_element: &ast::Element, context:
             }
           },
           _ => {
-            return Err(RuntimeError::new("Spread value must be an object.".to_string(), context.uri, &instance_element.location));
+            return Err(RuntimeError::new("Spread value must be an object.".to_string(), context.uri, &attr.location));
           }
         };
       },
@@ -388,10 +517,7 @@ fn create_component_instance_data<'a>(instance_element: &ast::Element, context:
           RuntimeError {
             uri: context.uri.to_string(),
             message: message.to_string(),
-            location: Location { 
-              start: 0,
-              end: 0
-            }
+            location: sh_attr.location.clone()
           }
         })?;
 
@@ -419,13 +545,127 @@ fn evaluate_component_instance<'a>(instance_element: &ast::Element, render_strat
   let data = create_component_instance_data(instance_element, context)?;
   
   if let DependencyContent::Node(node) = &dep.content {
-    let mut instance_context = create_context(&node, dep_uri, context.graph, context.vfs, &data, Some(&context));
+    let mut instance_context = create_context(&node, dep_uri, context.graph, context.vfs, context.helpers, &data, Some(&context));
     check_instance_loop(&render_strategy, instance_element, &mut instance_context)?;
 
-    // TODO: if fragment, then wrap in span. If not, then copy these attributes to root element
-    evaluate_instance_node(&node, &mut instance_context, render_strategy)
+    let target_node = get_instance_target_node(&node, render_strategy.clone());
+    let referenced = get_part_properties(target_node, dep_uri, &render_strategy, context);
+    let mut root_option = evaluate_instance_node(&node, &mut instance_context, render_strategy)?;
+    let instance_scope = instance_context.scope.to_string();
+    if let Some(root) = &mut root_option {
+
+      // targeted by :self selectors
+      if let Some(root_element) = get_root_element(root) {
+        root_element.attributes.push(virt::Attribute {
+          id: context.get_next_id(),
+          name: format!("data-pc-self-{}", instance_scope),
+          value: None
+        });
+      }
+      forward_instance_attributes(instance_element, target_node, &referenced, &data, root, context);
+    }
+    Ok(root_option)
   } else {
-    Err(RuntimeError::unknown(context.uri))
+    Err(RuntimeError::unknown(context.uri, &instance_element.location))
+  }
+}
+
+fn get_part_properties(target_node: &ast::Node, dep_uri: &String, render_strategy: &RenderStrategy, context: &Context) -> Rc<HashSet<String>> {
+  let key = format!("{}:{:?}", dep_uri, render_strategy);
+  if let Some(properties) = context.part_properties.borrow().get(&key) {
+    return properties.clone();
+  }
+  let properties = Rc::new(HashSet::from_iter(infer(target_node).properties.into_iter().map(|(name, _)| name)));
+  context.part_properties.borrow_mut().insert(key, properties.clone());
+  properties
+}
+
+// Copies class, style, data-* & aria-* attributes from <Button class="x" /> onto the
+// root element of the part, unless the part references them or opts out with `no-fallthrough`.
+// The caller's scope isn't forwarded, so only the part's own (or global) styles match a forwarded class.
+fn forward_instance_attributes<'a>(instance_element: &ast::Element, target_node: &ast::Node, referenced: &HashSet<String>, data: &js_virt::JsValue, root: &mut virt::Node, context: &'a mut Context) {
+  if let ast::Node::Element(part) = target_node {
+    if ast::get_attribute(NO_FALLTHROUGH_ATTRIBUTE_NAME, part) != None {
+      return;
+    }
+  }
+
+  // fragments don't have a single element to forward attributes to
+  let root_element = match get_root_element(root) {
+    Some(element) => element,
+    None => return
+  };
+
+  let values = match data {
+    js_virt::JsValue::JsObject(object) => &object.values,
+    _ => return
+  };
+
+  for attr in &instance_element.attributes {
+    let name = match attr {
+      ast::Attribute::KeyValueAttribute(kv_attr) => &kv_attr.name,
+      ast::Attribute::ShorthandAttribute(sh_attr) => match sh_attr.get_name() {
+        Ok(name) => name,
+        Err(_) => continue
+      },
+      ast::Attribute::SpreadAttribute(_) => continue
+    };
+
+    if !is_fallthrough_attribute(name) || referenced.contains(name) {
+      continue;
+    }
+
+    let value_option = match values.get(name) {
+      Some(js_virt::JsValue::JsBoolean(true)) => None,
+      None | Some(js_virt::JsValue::JsBoolean(false)) | Some(js_virt::JsValue::JsUndefined()) => continue,
+      Some(value) => Some(value.to_string())
+    };
+
+    let existing_option = root_element.attributes.iter_mut().find(|attr| &attr.name == name);
+
+    match (existing_option, name.as_str()) {
+      (Some(existing), "class") | (Some(existing), "style") => {
+        let separator = if name == "class" { " " } else { "; " };
+        existing.value = match (&existing.value, value_option) {
+          (Some(existing_value), Some(value)) => Some(format!("{}{}{}", existing_value.trim_end_matches(|c| c == ';' || c == ' '), separator, value)),
+          (existing_value, value) => value.or(existing_value.clone())
+        };
+      },
+      (Some(existing), _) => {
+        existing.value = value_option;
+      },
+      (None, _) => {
+        root_element.attributes.push(virt::Attribute {
+          id: context.get_next_id(),
+          name: name.to_string(),
+          value: value_option
+        });
+      }
+    }
+  }
+}
+
+fn is_fallthrough_attribute(name: &String) -> bool {
+  name == "class" || name == "style" || name.starts_with("data-") || name.starts_with("aria-")
+}
+
+fn get_root_element(node: &mut virt::Node) -> Option<&mut virt::Element> {
+  match node {
+    virt::Node::Element(element) => Some(element),
+    virt::Node::Fragment(fragment) => {
+      let mut elements = fragment.children.iter_mut().filter(|child| {
+        match child {
+          virt::Node::Text(text) => text.value.trim().len() > 0,
+          virt::Node::StyleElement(_) => false,
+          _ => true
+        }
+      });
+      match (elements.next(), elements.next()) {
+        (Some(virt::Node::Element(element)), None) => Some(element),
+        _ => None
+      }
+    },
+    _ => None
   }
 }
 
@@ -481,7 +721,7 @@ fn evaluate_basic_element<'a>(element: &ast::Element, context: &'a mut Context)
             }
           },
           _ => {
-            return Err(RuntimeError::new("Spread value must be an object.".to_string(), context.uri, &element.location));
+            return Err(RuntimeError::new("Spread value must be an object.".to_string(), context.uri, &attr.location));
           }
         };
       },
@@ -490,10 +730,7 @@ fn evaluate_basic_element<'a>(element: &ast::Element, context: &'a mut Context)
           RuntimeError {
             uri: context.uri.to_string(),
             message: message.to_string(),
-            location: Location { 
-              start: 0,
-              end: 0
-            }
+            location: sh_attr.location.clone()
           }
         })?;
         let js_value = evaluate_attribute_slot(&sh_attr.reference, context)?;
@@ -671,12 +908,30 @@ fn evaluate_attribute_value<'a>(value: &ast::AttributeValue, context: &mut Conte
     ast::AttributeValue::String(st) => {
       Ok(js_virt::JsValue::JsString(st.value.clone()))
     }
+    ast::AttributeValue::DynamicString(value) => {
+      evaluate_attribute_dynamic_string(value, context)
+    }
     ast::AttributeValue::Slot(script) => {
       evaluate_attribute_slot(script, context)
     }
   }
 }
 
+// class="btn {variant}". Unlike template literals, undefined values are omitted.
+fn evaluate_attribute_dynamic_string<'a>(value: &ast::AttributeDynamicStringValue, context: &'a mut Context) -> Result<js_virt::JsValue, RuntimeError> {
+  let mut buffer = String::new();
+  for part in &value.values {
+    match part {
+      ast::AttributeDynamicStringPart::Literal(literal) => buffer.push_str(&literal.value),
+      ast::AttributeDynamicStringPart::Slot(script) => match evaluate_attribute_slot(script, context)? {
+        js_virt::JsValue::JsUndefined() => {},
+        value => buffer.push_str(&value.to_string())
+      }
+    };
+  }
+  Ok(js_virt::JsValue::JsString(buffer))
+}
+
 fn evaluate_attribute_slot<'a>(script: &js_ast::Statement, context: &'a mut Context) -> Result<js_virt::JsValue, RuntimeError> {
   evaluate_js(script, context)
 }
@@ -685,6 +940,9 @@ fn evaluate_attribute_slot<'a>(script: &js_ast::Statement, context: &'a mut Cont
 mod tests {
   use super::*;
   use super::super::super::parser::*;
+  use std::collections::HashMap;
+  use ::futures::executor::block_on;
+  use crate::base::ast::{LineIndex};
 
   #[test]
   fn can_evaluate_a_style() {
@@ -745,7 +1003,7 @@ mod tests {
   }
   #[test]
   fn catches_infinite_part_loop() {
-    let result = evaluate_source("
+    let source = "
       <part id='test'>
         <div>
           <test a />          
@@ -754,17 +1012,15 @@ mod tests {
       <preview>
         <test />
       </preview>
-    ");
+    ";
+    let result = evaluate_source(source);
     
-    assert_eq!(result, Err(RuntimeError::new("Can't call <test /> here since this causes an infinite loop!".to_string(), &"some-file.pc".to_string(), &Location {
-      start: 48,
-      end: 58
-    })));
+    assert_eq!(result, Err(RuntimeError::new("Can't call <test /> here since this causes an infinite loop!".to_string(), &"some-file.pc".to_string(), &Location::new(48, 58).with_positions(&LineIndex::new(source)))));
   }
 
   #[test]
   fn catches_recursion_in_multiple_parts() {
-    let result = evaluate_source("
+    let source = "
       <part id='test2'>
         <div>
           <test />
@@ -778,25 +1034,21 @@ mod tests {
       <preview>
         <test />
       </preview>
-    ");
+    ";
+    let result = evaluate_source(source);
     
-    assert_eq!(result, Err(RuntimeError::new("Can't call <test /> here since this causes an infinite loop!".to_string(), &"some-file.pc".to_string(), &Location {
-      start: 49,
-      end: 57
-    })))
+    assert_eq!(result, Err(RuntimeError::new("Can't call <test /> here since this causes an infinite loop!".to_string(), &"some-file.pc".to_string(), &Location::new(49, 57).with_positions(&LineIndex::new(source)))))
   }
 
   #[test]
   fn catches_recursion_for_self_element() {
-    let result = evaluate_source("
+    let source = "
       Hello world
       <self />
-    ");
+    ";
+    let result = evaluate_source(source);
     
-    assert_eq!(result, Err(RuntimeError::new("Can't call <self /> here since this causes an infinite loop!".to_string(), &"some-file.pc".to_string(), &Location {
-      start: 25,
-      end: 33
-    })))
+    assert_eq!(result, Err(RuntimeError::new("Can't call <self /> here since this causes an infinite loop!".to_string(), &"some-file.pc".to_string(), &Location::new(25, 33).with_positions(&LineIndex::new(source)))))
   }
 
   #[test]
@@ -809,6 +1061,204 @@ mod tests {
     ").unwrap();
   }
 
+  #[test]
+  fn includes_the_reference_location_in_property_errors() {
+    let source = "<div>{a.b.c}</div>";
+    let result = evaluate_source(source);
+    assert_eq!(result, Err(RuntimeError::new("Cannot access property of undefined".to_string(), &"some-file.pc".to_string(), &Location::new(6, 11).with_positions(&LineIndex::new(source)))));
+  }
+
+  #[test]
+  fn includes_the_attribute_location_in_spread_errors() {
+    let source = "<div {...a} />";
+    let result = evaluate_source(source);
+    assert_eq!(result, Err(RuntimeError::new("Spread value must be an object.".to_string(), &"some-file.pc".to_string(), &Location::new(5, 11).with_positions(&LineIndex::new(source)))));
+  }
+
+  #[test]
+  fn can_evaluate_operators() {
+    let result = evaluate_source("<div>{!a}{1 + 2 * 3}{(1 + 2) * 3}{'a' + 1}{0 && 'b'}{0 || 'c'}{1 == '1'}{1 === '1'}{3 % 2 - -1}{a ? 'd' : 'e'}{#if !a && 'f' != 'g'}pass{/}</div>").unwrap().unwrap();
+    assert!(result.to_string().ends_with(" true 7 9 a1 0 c true false 2 e pass</div>"), "{}", result);
+  }
+
+  #[test]
+  fn converts_values_to_strings_like_js() {
+    let result = evaluate_source("<div title={'' + [1, [2, 3]] + {} + 1 / 0 + -1 / 0 + 0 / 0 + 1.5 + -0} alt={`${[1, 2]}|${1 / 0}`} />").unwrap().unwrap();
+    assert!(result.to_string().contains("title=\"1,2,3[object Object]Infinity-InfinityNaN1.50\" alt=\"1,2|Infinity\""), "{}", result);
+  }
+
+  #[test]
+  fn converts_slot_and_attribute_values_to_strings_like_js() {
+    let result = evaluate_source("<div title={1 / 0}>{1 / 0} {'' + 1 / 0} {-0}</div>").unwrap().unwrap();
+    assert!(result.to_string().contains("title=\"Infinity\""), "{}", result);
+    assert!(result.to_string().ends_with(" Infinity Infinity 0</div>"), "{}", result);
+  }
+
+  #[test]
+  fn can_evaluate_computed_member_access() {
+    let result = evaluate_source("<div>{[{label: 'a'}, {label: 'b'}][1].label}{{a: 'c'}['a']}{[1, 2, 3].length}{'de'.length}{[1][2]}</div>").unwrap().unwrap();
+    assert!(result.to_string().ends_with(" b c 3 2 undefined</div>"), "{}", result);
+
+    let source = "<div>{[1][2].label}</div>";
+    let result = evaluate_source(source);
+    assert_eq!(result, Err(RuntimeError::new("Cannot access property of undefined".to_string(), &"some-file.pc".to_string(), &Location::new(6, 18).with_positions(&LineIndex::new(source)))));
+  }
+
+  #[test]
+  fn can_call_helpers() {
+    let result = evaluate_source("<div>{uppercase('a')}{1234.5 | currency}{['b', 'c'] | join('-') | uppercase}</div>").unwrap().unwrap();
+    assert!(result.to_string().ends_with(" A $1,234.50 B-C</div>"), "{}", result);
+
+    let source = "<div>{nothing(1)}</div>";
+    let result = evaluate_source(source);
+    assert_eq!(result, Err(RuntimeError::new("Unknown helper \"nothing\".".to_string(), &"some-file.pc".to_string(), &Location::new(6, 16).with_positions(&LineIndex::new(source)))));
+  }
+
+  #[test]
+  fn can_evaluate_interpolated_attributes() {
+    let result = evaluate_source("<part id=\"btn\"><button class=\"btn btn-{variant} {missing}\" title={`${label}: ${missing}`} /></part><preview><btn variant=\"primary\" label=\"{'a' | uppercase}-b\" /></preview>").unwrap().unwrap();
+    assert!(result.to_string().contains("<button class=\"btn btn-primary \" title=\"A-b: undefined\""), "{}", result);
+  }
+
+  #[test]
+  fn forwards_instance_attributes_onto_the_part_root() {
+    let result = evaluate_source("<part id=\"btn\"><button class=\"btn\" style=\"color: red;\" title={title}>{label}</button></part><part id=\"plain\" no-fallthrough><span /></part><preview><btn class=\"x\" style=\"color: blue\" data-id=\"a\" aria-hidden title=\"b\" label=\"c\" /><plain class=\"y\" /></preview>").unwrap().unwrap();
+    let html = result.to_string();
+    assert!(html.contains("<button class=\"btn x\" style=\"color: red; color: blue\" title=\"b\" data-pc-"), "{}", html);
+    assert!(html.contains(" data-id=\"a\" aria-hidden>"), "{}", html);
+    assert!(!html.contains("label=") && !html.contains("class=\"y\""), "{}", html);
+  }
+
+  #[test]
+  fn does_not_forward_the_calling_files_scope() {
+    let files: HashMap<String, String> = vec![
+      ("entry.pc", "<import id=\"btn\" src=\"button.pc\" /><style>button { color: red; }</style><btn class=\"x\" />"),
+      ("button.pc", "<style>.x { color: blue; }</style><part id=\"default\"><button /></part>")
+    ].into_iter().map(|(uri, content)| (uri.to_string(), content.to_string())).collect();
+    let mut vfs = VirtualFileSystem::new(Box::new(move |uri| files.get(uri).unwrap().to_string()), Box::new(|_| true), Box::new(|_, uri| uri.to_string()));
+    let mut graph = DependencyGraph::new();
+    let uri = "entry.pc".to_string();
+    block_on(graph.load_dependency(&uri, &mut vfs)).unwrap();
+
+    let data = js_virt::JsValue::JsObject(js_virt::JsObject::new());
+    let html = evaluate(&uri, &graph, &vfs, &Helpers::new(), &data, None).unwrap().unwrap().to_string();
+    let button_scope = get_document_style_scope(&"button.pc".to_string());
+    assert!(html.contains(&format!("<button data-pc-{} data-pc-self-{} class=\"x\">", button_scope, button_scope)), "{}", html);
+  }
+
+  #[test]
+  fn forwards_falsy_attribute_values() {
+    let html = evaluate_source("<part id=\"btn\"><button /></part><btn data-count={0} aria-label=\"\" data-hidden={false} />").unwrap().unwrap().to_string();
+    assert!(html.contains("data-count=\"0\""), "{}", html);
+    assert!(html.contains("aria-label=\"\""), "{}", html);
+    assert!(!html.contains("data-hidden"), "{}", html);
+  }
+
+  #[test]
+  fn can_evaluate_global_and_self_selectors() {
+    let html = evaluate_source("<style>:global(body) .a { color: red; } :self > span, :self(.b) { color: blue; }</style><span class=\"a\" />").unwrap().unwrap().to_string();
+    let scope = get_document_style_scope(&"some-file.pc".to_string());
+    assert!(html.contains(&format!("body .a[data-pc-{}] {{", scope)), "{}", html);
+    assert!(html.contains(&format!("[data-pc-self-{}] > span[data-pc-{}], [data-pc-self-{}].b[data-pc-{}] {{", scope, scope, scope, scope)), "{}", html);
+  }
+
+  #[test]
+  fn scopes_keyframes_names() {
+    let html = evaluate_source("<style>@keyframes fade { from { opacity: 0; } } @keyframes :global(spin) { to { opacity: 1; } } div { animation: fade 1s, spin 2s; animation-name: fade-in; }</style><div />").unwrap().unwrap().to_string();
+    let scope = get_document_style_scope(&"some-file.pc".to_string());
+    assert!(html.contains(&format!("@keyframes _{}_fade {{", scope)), "{}", html);
+    assert!(html.contains("@keyframes spin {"), "{}", html);
+    assert!(html.contains(&format!("animation: _{}_fade 1s, spin 2s;", scope)), "{}", html);
+    assert!(html.contains("animation-name: fade-in;"), "{}", html);
+  }
+
+  #[test]
+  fn flattens_nested_style_rules() {
+    let html = evaluate_source("<style>.a, b { color: red; &:hover { color: blue; } &.c > span, .d { color: green; } @media print { color: black; } }</style><div />").unwrap().unwrap().to_string();
+    let scope = get_document_style_scope(&"some-file.pc".to_string());
+    let s = |selector: &str| format!("{}[data-pc-{}]", selector, scope);
+    assert!(html.contains(&format!("{}, {} {{color: red;}}", s(".a"), s("b"))), "{}", html);
+    assert!(html.contains(&format!("{}:hover, {}:hover {{color: blue;}}", s(".a"), s("b"))), "{}", html);
+    assert!(html.contains(&format!("{}.c{} > {}, {}.c{} > {}, {} {}, {} {} {{color: green;}}", s(".a"), s(""), s("span"), s("b"), s(""), s("span"), s(".a"), s(".d"), s("b"), s(".d"))), "{}", html);
+    assert!(html.contains(&format!("{{\n {}, {} {{color: black;}}", s(".a"), s("b"))), "{}", html);
+  }
+
+  #[test]
+  fn resolves_each_nesting_selector_against_every_parent() {
+    let html = evaluate_source("<style>.a, .b { [title='&'] & { color: red; } & + & { color: blue; } }</style><div />").unwrap().unwrap().to_string();
+    let scope = get_document_style_scope(&"some-file.pc".to_string());
+    let s = |selector: &str| format!("{}[data-pc-{}]", selector, scope);
+    assert!(html.contains(&format!("[title=\"&\"]{} {}, [title=\"&\"]{} {} {{color: red;}}", s(""), s(".a"), s(""), s(".b"))), "{}", html);
+    assert!(html.contains(&format!("{} + {}, {} + {}, {} + {}, {} + {} {{color: blue;}}", s(".a"), s(".a"), s(".a"), s(".b"), s(".b"), s(".a"), s(".b"), s(".b"))), "{}", html);
+  }
+
+  #[test]
+  fn scopes_rules_nested_in_condition_rules() {
+    let html = evaluate_source("<style>@media screen { @supports (display: grid) { .a { animation: fade 1s; @media print { color: red; } } } @keyframes fade { to { opacity: 0; } } }</style><div />").unwrap().unwrap().to_string();
+    let scope = get_document_style_scope(&"some-file.pc".to_string());
+    assert!(html.contains(&format!("@supports (display: grid)  {{\n .a[data-pc-{}] {{animation: _{}_fade 1s;}}\n@media print  {{\n .a[data-pc-{}] {{color: red;}}", scope, scope, scope)), "{}", html);
+    assert!(html.contains(&format!("@keyframes _{}_fade {{", scope)), "{}", html);
+  }
+
+  #[test]
+  fn scopes_keyframes_names_across_style_elements() {
+    let html = evaluate_source("<style>@keyframes fade { from { opacity: 0; } }</style><style>.a { animation: fade 1s; }</style><div />").unwrap().unwrap().to_string();
+    let scope = get_document_style_scope(&"some-file.pc".to_string());
+    assert!(html.contains(&format!("animation: _{}_fade 1s;", scope)), "{}", html);
+  }
+
+  #[test]
+  fn scopes_keyframes_names_from_imported_sheets() {
+    let files: HashMap<String, String> = vec![
+      ("entry.pc", "<import src=\"theme.css\" /><style>.a { animation: fade 1s; }</style><div />"),
+      ("theme.css", "@import url(keyframes.css);"),
+      ("keyframes.css", "@keyframes fade { from { opacity: 0; } }")
+    ].into_iter().map(|(uri, content)| (uri.to_string(), content.to_string())).collect();
+    let mut vfs = VirtualFileSystem::new(Box::new(move |uri| files.get(uri).unwrap().to_string()), Box::new(|_| true), Box::new(|_, uri| uri.to_string()));
+    let mut graph = DependencyGraph::new();
+    let uri = "entry.pc".to_string();
+    block_on(graph.load_dependency(&uri, &mut vfs)).unwrap();
+
+    let data = js_virt::JsValue::JsObject(js_virt::JsObject::new());
+    let html = evaluate(&uri, &graph, &vfs, &Helpers::new(), &data, None).unwrap().unwrap().to_string();
+    let scope = get_document_style_scope(&uri);
+    assert!(html.contains(&format!("@keyframes _{}_fade {{", scope)), "{}", html);
+    assert!(html.contains(&format!("animation: _{}_fade 1s;", scope)), "{}", html);
+  }
+
+  #[test]
+  fn inlines_imported_sheets_in_order() {
+    let files: HashMap<String, String> = vec![
+      ("entry.pc", "<import src=\"a.css\" /><div />"),
+      ("a.css", "@import url(b.css); @import url(c.css); .a { color: red; }"),
+      ("b.css", "@import url(a.css); .b { color: red; }"),
+      ("c.css", ".c { color: red; }")
+    ].into_iter().map(|(uri, content)| (uri.to_string(), content.to_string())).collect();
+    let mut vfs = VirtualFileSystem::new(Box::new(move |uri| files.get(uri).unwrap().to_string()), Box::new(|_| true), Box::new(|_, uri| uri.to_string()));
+    let mut graph = DependencyGraph::new();
+    let uri = "entry.pc".to_string();
+    block_on(graph.load_dependency(&uri, &mut vfs)).unwrap();
+
+    let data = js_virt::JsValue::JsObject(js_virt::JsObject::new());
+    let html = evaluate(&uri, &graph, &vfs, &Helpers::new(), &data, None).unwrap().unwrap().to_string();
+    let scope = get_document_style_scope(&uri);
+    assert!(html.contains(&format!(".b[data-pc-{}] {{color: red;}} .c[data-pc-{}] {{color: red;}} .a[data-pc-{}] {{color: red;}}", scope, scope, scope)), "{}", html);
+  }
+
+  #[test]
+  fn resolves_urls_in_declaration_values() {
+    let mut graph = DependencyGraph::new();
+    let uri = "some-file.pc".to_string();
+    let vfs = VirtualFileSystem::new(Box::new(|_| "".to_string()), Box::new(|_| true), Box::new(|_, uri| format!("file:///{}", uri)));
+    let code = "<style>div { background: url(a.png), image-set(url('b.png') 1x); mask: url(http://x.com/c.png); }</style><div />";
+    graph.dependencies.insert(uri.clone(), Dependency::from_source(code.to_string(), &uri, &vfs).unwrap());
+
+    let data = js_virt::JsValue::JsObject(js_virt::JsObject::new());
+    let html = evaluate(&uri, &graph, &vfs, &Helpers::new(), &data, None).unwrap().unwrap().to_string();
+    assert!(html.contains("background: url(\"file:///a.png\"), image-set(url(\"file:///b.png\") 1x);"), "{}", html);
+    assert!(html.contains("mask: url(http://x.com/c.png);"), "{}", html);
+  }
+
   fn evaluate_source<'a>(code: &'a str) -> Result<Option<virt::Node>, RuntimeError>{
     let mut graph = DependencyGraph::new(); 
     let uri = "some-file.pc".to_string();
@@ -816,6 +1266,6 @@ mod tests {
     graph.dependencies.insert(uri.clone(), Dependency::from_source(code.to_string(), &uri, &vfs).unwrap());
 
     let data = js_virt::JsValue::JsObject(js_virt::JsObject::new());
-    evaluate(&uri, &graph, &vfs, &data, None)
+    evaluate(&uri, &graph, &vfs, &Helpers::new(), &data, None)
   }
 }
@@ -2,8 +2,8 @@ use super::vfs::{VirtualFileSystem};
 use crate::pc::{ast as pc_ast, parser as pc_parser};
 use crate::css::{ast as css_ast, parser as css_parser};
 use crate::base::parser::{ParseError};
-use crate::base::ast::{Location};
-use std::collections::{HashMap};
+use crate::base::ast::{Location, LineIndex};
+use std::collections::{HashMap, HashSet};
 use serde::{Serialize};
 
 
@@ -28,8 +28,8 @@ pub struct IncludeNodeFoundError {
 
 #[derive(Debug, PartialEq, Serialize, Clone)]
 pub struct GraphError {
-  uri: String,
-  info: GraphErrorInfo
+  pub uri: String,
+  pub info: GraphErrorInfo
 }
 
 pub struct DependencyGraph {
@@ -53,13 +53,19 @@ impl DependencyGraph {
     deps.push((entry, None));
     
     let mut dependents = vec![entry];
+    let mut visited = HashSet::new();
+    visited.insert(entry_uri);
 
     while dependents.len() > 0 {
       let dependent = dependents.pop().unwrap();
       for (_, dep_uri) in &dependent.dependencies {
         let dep = self.dependencies.get(dep_uri).unwrap();
         deps.push((dep, Some(dependent)));
-        dependents.push(dep);
+
+        // sheets may import each other
+        if visited.insert(dep_uri) {
+          dependents.push(dep);
+        }
       }
     }
     return deps;
@@ -67,26 +73,31 @@ impl DependencyGraph {
 
   pub fn flatten_dependents<'a>(&'a self, entry_uri: &String) -> Vec<&Dependency> {
     let mut deps = vec![];
-    let entry_option = self.dependencies.get(entry_uri);
+    self.collect_dependents(entry_uri, &mut deps);
+    return deps;
+  }
 
-    if let None = entry_option {
-      return deps;
+  fn collect_dependents<'a>(&'a self, entry_uri: &String, deps: &mut Vec<&'a Dependency>) {
+    if !self.dependencies.contains_key(entry_uri) {
+      return;
     }
 
     for (dep_uri, dep) in &self.dependencies {
       if dep.dependencies.values().any(|uri| { &uri == &entry_uri }) {
+
+        // skip circular imports
+        if deps.iter().any(|existing| &existing.uri == dep_uri) {
+          continue;
+        }
         deps.push(dep);
-        deps.extend(self.flatten_dependents(dep_uri));
+        self.collect_dependents(dep_uri, deps);
       }
     }
-    
-    return deps;
   }
 
   pub async fn load_dependency<'a>(&mut self, uri: &String, vfs: &mut VirtualFileSystem) -> Result<Vec<String>, GraphError> {
 
     let mut loaded_deps = vec![];
-    loaded_deps.push(uri.to_string());
 
     let mut to_load: Vec<(String, Option<(String, String)>)> = vec![(uri.to_string(), None)];
     
@@ -102,9 +113,11 @@ impl DependencyGraph {
               DependencyContent::Node(node) => {
                 pc_ast::get_import_by_src(&relative_uri, node).unwrap().open_tag_location.clone()
               }
-              DependencyContent::StyleSheet(_) => {
-                // TODO once imports are working in CSS sheets
-                Location { start: 0, end: 0 }
+              DependencyContent::StyleSheet(sheet) => {
+                css_ast::get_imports(sheet).into_iter().find(|import| import.uri == relative_uri).unwrap().location.clone()
+              },
+              DependencyContent::Data(_) => {
+                Location::start_of_file()
               }
             };
 
@@ -131,12 +144,8 @@ impl DependencyGraph {
       })?.to_string();
 
       // TODO - check if content matches old content.
-      let dependency = Dependency::from_source(source, &curr_uri, vfs).or_else(|error| {
-        Err(GraphError {
-          uri: curr_uri.to_string(),
-          info: GraphErrorInfo::Syntax(error)
-        })
-      })?;
+      // Syntax errors are kept on the dependency so that the rest of the file can still be used.
+      let dependency = Dependency::from_source_with_recovery(source, &curr_uri, vfs);
 
       loaded_deps.push(curr_uri.to_string());
 
@@ -161,7 +170,10 @@ impl DependencyGraph {
 #[derive(Debug)]
 pub enum DependencyContent {
   Node(pc_ast::Node),
-  StyleSheet(css_ast::Sheet)
+  StyleSheet(css_ast::Sheet),
+
+  // JSON fixtures, e.g: <import id="fixtures" src="./button.fixtures.json" />
+  Data(serde_json::Value)
 }
 
 #[derive(Debug)]
@@ -169,42 +181,70 @@ pub struct Dependency {
   pub uri: String,
   pub dependencies: HashMap<String, String>,
   pub dependency_uri_maps: HashMap<String, String>,
-  pub content: DependencyContent
+  pub content: DependencyContent,
+  pub syntax_errors: Vec<ParseError>
 }
 
 impl<'a> Dependency {
+
+  // used by tests that don't need error recovery
+  #[cfg(test)]
   pub fn from_source(source: String, uri: &String, vfs: &VirtualFileSystem) -> Result<Dependency, ParseError> {
+    let dependency = Dependency::from_source_with_recovery(source, uri, vfs);
+    if let Some(err) = dependency.syntax_errors.first() {
+      return Err(err.clone());
+    }
+    Ok(dependency)
+  }
+
+  // Always returns a dependency. Any syntax errors are stored in `syntax_errors`.
+  pub fn from_source_with_recovery(source: String, uri: &String, vfs: &VirtualFileSystem) -> Dependency {
     if uri.ends_with(".css") {
-      Dependency::from_css_source(source, uri)
+      Dependency::from_css_source(source, uri, vfs)
+    } else if uri.ends_with(".json") {
+      Dependency::from_json_source(source, uri)
     } else {
       Dependency::from_pc_source(source, uri, vfs)
     }
   }
 
-  fn from_css_source(source: String, uri: &String) -> Result<Dependency, ParseError> {
-    let expression_result = css_parser::parse(source.as_str());
-    if let Err(err) = expression_result {
-      return Err(err);
+  fn from_css_source(source: String, uri: &String, vfs: &VirtualFileSystem) -> Dependency {
+    let (expression, syntax_errors) = css_parser::parse_with_recovery(source.as_str());
+
+    let mut dependencies = HashMap::new();
+    let mut dependency_uri_maps = HashMap::new();
+    for import in css_ast::get_imports(&expression) {
+      dependencies.insert(import.uri.to_string(), vfs.resolve(uri, &import.uri));
+      dependency_uri_maps.insert(import.uri.to_string(), vfs.resolve(uri, &import.uri));
     }
-    let expression = expression_result.unwrap();
 
-    Ok(Dependency {
+    Dependency {
       uri: uri.to_string(),
       content: DependencyContent::StyleSheet(expression),
-      dependencies: HashMap::new(),
-      dependency_uri_maps: HashMap::new()
-    })
+      dependencies,
+      dependency_uri_maps,
+      syntax_errors
+    }
   }
 
-  fn from_pc_source(source: String, uri: &String, vfs: &VirtualFileSystem) -> Result<Dependency, ParseError> {
+  fn from_json_source(source: String, uri: &String) -> Dependency {
+    let (value, syntax_errors) = match serde_json::from_str(source.as_str()) {
+      Ok(value) => (value, vec![]),
+      Err(err) => (serde_json::Value::Null, vec![get_json_syntax_error(&source, &err)])
+    };
 
-    let expression_result = pc_parser::parse(source.as_str());
-
-    if let Err(err) = expression_result {
-      return Err(err);
+    Dependency {
+      uri: uri.to_string(),
+      content: DependencyContent::Data(value),
+      dependencies: HashMap::new(),
+      dependency_uri_maps: HashMap::new(),
+      syntax_errors
     }
+  }
 
-    let expression = expression_result.unwrap();
+  fn from_pc_source(source: String, uri: &String, vfs: &VirtualFileSystem) -> Dependency {
+
+    let (expression, syntax_errors) = pc_parser::parse_with_recovery(source.as_str());
     
     let imports = pc_ast::get_imports(&expression);
 
@@ -223,11 +263,22 @@ impl<'a> Dependency {
       );
     }
 
-    Ok(Dependency {
+    Dependency {
       uri: uri.to_string(),
       content: DependencyContent::Node(expression),
       dependencies,
-      dependency_uri_maps
-    })
+      dependency_uri_maps,
+      syntax_errors
+    }
   }
-}
\ No newline at end of file
+}
+pub fn get_json_syntax_error(source: &String, err: &serde_json::Error) -> ParseError {
+  let pos = get_json_error_pos(source, err.line(), err.column());
+  ParseError::unexpected(format!("Unable to parse JSON: {}", err), pos, pos + 1).with_positions(&LineIndex::new(source))
+}
+
+// serde_json reports 1-based lines & columns
+fn get_json_error_pos(source: &String, line: usize, column: usize) -> usize {
+  let line_start: usize = source.split('\n').take(line.max(1) - 1).map(|line| line.len() + 1).sum();
+  (line_start + column.max(1) - 1).min(source.len())
+}
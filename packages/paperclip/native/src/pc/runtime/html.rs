@@ -0,0 +1,347 @@
+// Renders an evaluated document as a standalone HTML page. Style elements are hoisted into the
+// <head>, so the output can be served as is.
+
+use serde::{Deserialize};
+use super::virt;
+use crate::pc::ast::{is_void_tag_name};
+
+#[derive(Debug, PartialEq, Deserialize, Clone, Default)]
+pub struct HtmlOptions {
+
+  // puts each element on its own line
+  #[serde(default)]
+  pub pretty: bool
+}
+
+struct Context<'a> {
+  options: &'a HtmlOptions,
+  styles: Vec<&'a virt::StyleElement>,
+
+  // top-level nodes around the document's <html> element, rendered into its <body>
+  before_html: Vec<&'a virt::Node>,
+  after_html: Vec<&'a virt::Node>,
+  buffer: String,
+  depth: usize
+}
+
+pub fn render_html(root: &virt::Node, options: &HtmlOptions) -> String {
+  let mut context = Context {
+    options,
+    styles: vec![],
+    before_html: vec![],
+    after_html: vec![],
+    buffer: String::new(),
+    depth: 0
+  };
+
+  collect_styles(root, &mut context.styles);
+
+  context.buffer.push_str("<!DOCTYPE html>");
+
+  // documents may define their own <html> element
+  match find_html_element(root) {
+    Some(html) => {
+      collect_html_siblings(root, html, &mut false, &mut context);
+      render_element(html, &mut context);
+    },
+    None => {
+      open_tag("html", &mut context);
+      render_head(&mut context);
+      open_tag("body", &mut context);
+      render_node(root, &mut context);
+      close_tag("body", &mut context);
+      close_tag("html", &mut context);
+    }
+  };
+
+  if options.pretty {
+    context.buffer.push('\n');
+  }
+
+  context.buffer
+}
+
+fn collect_styles<'a>(node: &'a virt::Node, styles: &mut Vec<&'a virt::StyleElement>) {
+  match node {
+    virt::Node::StyleElement(style) => styles.push(style),
+    virt::Node::Element(element) => for child in &element.children {
+      collect_styles(child, styles);
+    },
+    virt::Node::Fragment(fragment) => for child in &fragment.children {
+      collect_styles(child, styles);
+    },
+    virt::Node::Text(_) => {}
+  }
+}
+
+fn find_html_element<'a>(node: &'a virt::Node) -> Option<&'a virt::Element> {
+  match node {
+    virt::Node::Element(element) if element.tag_name == "html" => Some(element),
+    virt::Node::Fragment(fragment) => fragment.children.iter().find_map(find_html_element),
+    _ => None
+  }
+}
+
+fn collect_html_siblings<'a>(node: &'a virt::Node, html: &virt::Element, found: &mut bool, context: &mut Context<'a>) {
+  match node {
+    virt::Node::Element(element) if std::ptr::eq(element, html) => *found = true,
+    virt::Node::Fragment(fragment) => for child in &fragment.children {
+      collect_html_siblings(child, html, found, context);
+    },
+
+    // hoisted into the <head>
+    virt::Node::StyleElement(_) => {},
+    _ => if *found {
+      context.after_html.push(node);
+    } else {
+      context.before_html.push(node);
+    }
+  }
+}
+
+fn render_node(node: &virt::Node, context: &mut Context) {
+  match node {
+    virt::Node::Element(element) => render_element(element, context),
+    virt::Node::Fragment(fragment) => for child in &fragment.children {
+      render_node(child, context);
+    },
+    virt::Node::Text(text) => render_text(&text.value, context),
+
+    // hoisted into the <head>
+    virt::Node::StyleElement(_) => {}
+  }
+}
+
+fn render_element(element: &virt::Element, context: &mut Context) {
+  start_line(context);
+  context.buffer.push('<');
+  context.buffer.push_str(&element.tag_name);
+  for attribute in &element.attributes {
+    context.buffer.push(' ');
+    context.buffer.push_str(&attribute.name);
+    if let Some(value) = &attribute.value {
+      context.buffer.push_str("=\"");
+      context.buffer.push_str(&escape_attribute_value(value));
+      context.buffer.push('"');
+    }
+  }
+  context.buffer.push('>');
+
+  if is_void_tag_name(element.tag_name.as_str()) {
+    return;
+  }
+
+  context.depth += 1;
+
+  if element.tag_name == "head" {
+    if !element.children.iter().any(is_meta_charset) {
+      render_meta_charset(context);
+    }
+    render_styles(context);
+  } else if element.tag_name == "html" && !has_child_element("head", element) {
+
+    // otherwise there's nowhere for styles to go
+    render_head(context);
+  }
+
+  // text-only elements stay on one line, e.g: <h1>Title</h1>
+  let is_raw_text = element.tag_name == "script" || element.tag_name == "style";
+  let is_body = element.tag_name == "body";
+  let has_element_children = element.children.iter().any(|child| !matches!(child, virt::Node::Text(_) | virt::Node::StyleElement(_))) ||
+    (is_body && context.before_html.len() + context.after_html.len() > 0);
+
+  if is_body {
+    render_nodes(std::mem::take(&mut context.before_html), context);
+  }
+
+  for child in &element.children {
+    match child {
+
+      // </script> in the text would end the element early
+      virt::Node::Text(text) if is_raw_text => context.buffer.push_str(&text.value.replace("</", "<\\/")),
+      virt::Node::Text(text) if !has_element_children => {
+        let value = if context.options.pretty { text.value.trim() } else { text.value.as_str() };
+        context.buffer.push_str(&escape_text(value));
+      },
+      _ => render_node(child, context)
+    }
+  }
+
+  if is_body {
+    render_nodes(std::mem::take(&mut context.after_html), context);
+  } else if element.tag_name == "html" && !has_child_element("body", element) && context.before_html.len() + context.after_html.len() > 0 {
+    open_tag("body", context);
+    render_nodes(std::mem::take(&mut context.before_html), context);
+    render_nodes(std::mem::take(&mut context.after_html), context);
+    close_tag("body", context);
+  }
+
+  context.depth -= 1;
+
+  if has_element_children || element.tag_name == "head" {
+    start_line(context);
+  }
+  context.buffer.push_str(&format!("</{}>", element.tag_name));
+}
+
+fn render_nodes(nodes: Vec<&virt::Node>, context: &mut Context) {
+  for node in nodes {
+    render_node(node, context);
+  }
+}
+
+fn render_text(value: &String, context: &mut Context) {
+  if context.options.pretty {
+    let value = value.trim();
+    if value.len() == 0 {
+      return;
+    }
+    start_line(context);
+    context.buffer.push_str(&escape_text(value));
+  } else {
+    context.buffer.push_str(&escape_text(value));
+  }
+}
+
+fn has_child_element(tag_name: &str, element: &virt::Element) -> bool {
+  element.children.iter().any(|child| matches!(child, virt::Node::Element(child) if child.tag_name == tag_name))
+}
+
+fn is_meta_charset(node: &virt::Node) -> bool {
+  matches!(node, virt::Node::Element(element) if element.tag_name == "meta" && element.attributes.iter().any(|attr| attr.name == "charset"))
+}
+
+fn render_head(context: &mut Context) {
+  open_tag("head", context);
+  render_meta_charset(context);
+  render_styles(context);
+  close_tag("head", context);
+}
+
+fn render_meta_charset(context: &mut Context) {
+  start_line(context);
+  context.buffer.push_str("<meta charset=\"utf-8\">");
+}
+
+fn render_styles(context: &mut Context) {
+  let styles = std::mem::replace(&mut context.styles, vec![]);
+  for style in styles {
+    start_line(context);
+
+    // </style> in the sheet would end the element early
+    context.buffer.push_str(&format!("<style>{}</style>", style.sheet.to_string().replace("</", "<\\/")));
+  }
+}
+
+fn open_tag(tag_name: &str, context: &mut Context) {
+  start_line(context);
+  context.buffer.push_str(&format!("<{}>", tag_name));
+  context.depth += 1;
+}
+
+fn close_tag(tag_name: &str, context: &mut Context) {
+  context.depth -= 1;
+  start_line(context);
+  context.buffer.push_str(&format!("</{}>", tag_name));
+}
+
+fn start_line(context: &mut Context) {
+  if context.options.pretty && context.buffer.len() > 0 {
+    context.buffer.push('\n');
+    context.buffer.push_str(&"  ".repeat(context.depth));
+  }
+}
+
+pub fn escape_text(value: &str) -> String {
+  value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+pub fn escape_attribute_value(value: &str) -> String {
+  value.replace('&', "&amp;").replace('"', "&quot;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::pc::runtime::evaluator::{evaluate};
+  use crate::pc::runtime::graph::{DependencyGraph, Dependency};
+  use crate::pc::runtime::vfs::{VirtualFileSystem};
+  use crate::js::runtime::helpers::{Helpers};
+  use crate::js::runtime::virt as js_virt;
+
+  fn render_source(source: &str, options: &HtmlOptions) -> String {
+    render_html(&evaluate_source(source), options)
+  }
+
+  fn evaluate_source(source: &str) -> virt::Node {
+    let mut graph = DependencyGraph::new();
+    let uri = "some-file.pc".to_string();
+    let vfs = VirtualFileSystem::new(Box::new(|_| "".to_string()), Box::new(|_| true), Box::new(|_, uri| uri.to_string()));
+    graph.dependencies.insert(uri.clone(), Dependency::from_source(source.to_string(), &uri, &vfs).unwrap());
+    let data = js_virt::JsValue::JsObject(js_virt::JsObject::new());
+    evaluate(&uri, &graph, &vfs, &Helpers::new(), &data, None).unwrap().unwrap()
+  }
+
+  #[test]
+  fn escapes_text_and_attributes() {
+    let html = render_source("<style>div { color: red; }</style><div title={'\"a\" & <b>'}>{'1 < 2 & 3'}<br><img src=\"http://a.com/b.png\"></div>", &HtmlOptions::default());
+    let body = &html[html.find("<body>").unwrap()..];
+    assert!(html.starts_with("<!DOCTYPE html><html><head><meta charset=\"utf-8\"><style>"), "{}", html);
+    assert!(body.contains(" title=\"&quot;a&quot; &amp; &lt;b&gt;\""), "{}", body);
+    assert!(body.contains(">1 &lt; 2 &amp; 3<br data-pc-"), "{}", body);
+    assert!(!body.contains("</br>") && !body.contains("</img>") && !body.contains("<style>"), "{}", body);
+  }
+
+  #[test]
+  fn can_pretty_print() {
+    let html = render_source("<div>\n  <span>a</span>  b\n</div>", &HtmlOptions { pretty: true });
+    let lines: Vec<&str> = html.lines().collect();
+    assert_eq!(lines[0..4].to_vec(), vec!["<!DOCTYPE html>", "<html>", "  <head>", "    <meta charset=\"utf-8\">"]);
+    assert!(html.ends_with("  <body>\n    <div data-pc-acb5fc82>\n      <span data-pc-acb5fc82>a</span>\n      b\n    </div>\n  </body>\n</html>\n"), "{}", html);
+  }
+
+  #[test]
+  fn uses_the_documents_html_element() {
+    let html = render_source("<html><head><title>a</title></head><body>b</body></html>", &HtmlOptions::default());
+    assert!(html.starts_with("<!DOCTYPE html><html data-pc-"), "{}", html);
+    assert!(html.contains("><meta charset=\"utf-8\"><style></style><title data-pc-"), "{}", html);
+    assert_eq!(html.matches("<body").count(), 1);
+  }
+
+  #[test]
+  fn adds_a_head_to_html_elements_without_one() {
+    let html = render_source("<style>div { color: red; }</style><html><body>b<wbr><track></body></html>", &HtmlOptions::default());
+    assert!(html.contains("><head><meta charset=\"utf-8\"><style>"), "{}", html);
+    assert!(html.contains("div[data-pc-"), "{}", html);
+    assert!(!html.contains("</wbr>") && !html.contains("</track>"), "{}", html);
+  }
+
+  #[test]
+  fn renders_siblings_of_the_html_element_into_its_body() {
+    let html = render_source("<div>a</div><html><body><span>b</span></body></html>c", &HtmlOptions::default());
+    let body = &html[html.find("<body").unwrap()..];
+    assert!(body.contains(">a</div><span data-pc-"), "{}", body);
+    assert!(body.ends_with(">b</span>c</body></html>"), "{}", body);
+
+    let html = render_source("<div>a</div><html><head></head></html>", &HtmlOptions::default());
+    assert!(html.ends_with("</head><body><div data-pc-acb5fc82>a</div></body></html>"), "{}", html);
+  }
+
+  #[test]
+  fn keeps_the_documents_meta_charset() {
+    let html = render_source("<html><head><meta charset=\"iso-8859-1\"></head><body></body></html>", &HtmlOptions::default());
+    assert_eq!(html.matches("<meta").count(), 1, "{}", html);
+    assert!(html.contains("<meta charset=\"iso-8859-1\""), "{}", html);
+  }
+
+  #[test]
+  fn escapes_closing_tags_in_scripts() {
+    // script bodies aren't parsed, so the text is added here as if it came from a slot
+    let mut node = evaluate_source("<div>{'</script><b>x</b>'}</div>");
+    if let virt::Node::Element(element) = &mut node {
+      element.tag_name = "script".to_string();
+    }
+    let html = render_html(&node, &HtmlOptions::default());
+    assert!(html.contains("<script data-pc-acb5fc82><\\/script><b>x<\\/b></script>"), "{}", html);
+  }
+}
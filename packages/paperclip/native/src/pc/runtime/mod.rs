@@ -2,3 +2,6 @@ pub mod evaluator;
 pub mod virt;
 pub mod graph;
 pub mod vfs;
+pub mod diff;
+pub mod mutation;
+pub mod html;
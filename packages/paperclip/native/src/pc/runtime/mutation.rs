@@ -1,26 +1,81 @@
 use super::virt;
+use crate::css::runtime::virt as css_virt;
+use serde::{Serialize};
 
-pub struct InsertChild<'a> {
-  node: virt::Node<'a>,
-  index: usize
+#[derive(Debug, PartialEq, Serialize, Clone)]
+pub struct InsertChild {
+  pub child: virt::Node,
+  pub index: usize
 }
 
-pub struct DeleteChild<'a> {
-  index: usize
+#[derive(Debug, PartialEq, Serialize, Clone)]
+pub struct DeleteChild {
+  pub index: usize
 }
 
-pub struct SetAttribute<'a> {
-  name: &'a str,
-  value: &'a str
+#[derive(Debug, PartialEq, Serialize, Clone)]
+pub struct MoveChild {
+  #[serde(rename = "oldIndex")]
+  pub old_index: usize,
+
+  #[serde(rename = "newIndex")]
+  pub new_index: usize
+}
+
+#[derive(Debug, PartialEq, Serialize, Clone)]
+pub struct SetAttribute {
+  pub name: String,
+  pub value: Option<String>
+}
+
+#[derive(Debug, PartialEq, Serialize, Clone)]
+pub struct RemoveAttribute {
+  pub name: String
+}
+
+#[derive(Debug, PartialEq, Serialize, Clone)]
+pub struct SetText {
+  pub value: String
 }
 
+#[derive(Debug, PartialEq, Serialize, Clone)]
+pub struct ReplaceNode {
+  pub replacement: virt::Node
+}
+
+#[derive(Debug, PartialEq, Serialize, Clone)]
+pub struct ReplaceSheet {
+  pub sheet: css_virt::CSSSheet
+}
+
+#[derive(Debug, PartialEq, Serialize, Clone)]
+#[serde(tag = "kind")]
 pub enum Action {
-  InsertChild(InsertChild<'a>),
-  DeleteChild(InsertChild<'a>),
-  SetAttribute(SetAttribute<'a>)
+  InsertChild(InsertChild),
+  DeleteChild(DeleteChild),
+  MoveChild(MoveChild),
+  SetAttribute(SetAttribute),
+  RemoveAttribute(RemoveAttribute),
+  SetText(SetText),
+  ReplaceNode(ReplaceNode),
+  ReplaceSheet(ReplaceSheet)
 }
 
-pub enum Mutation<'a> {
-  nodePath: Vec<usize>,
-  action: Action<'a>
-}
\ No newline at end of file
+// Mutations are applied in order. `node_path` is the list of child indices
+// from the root (fragments included) to the target, and refers to the tree as it
+// is _after_ all prior mutations have been applied.
+#[derive(Debug, PartialEq, Serialize, Clone)]
+pub struct Mutation {
+  #[serde(rename = "nodePath")]
+  pub node_path: Vec<usize>,
+  pub action: Action
+}
+
+impl Mutation {
+  pub fn new(node_path: Vec<usize>, action: Action) -> Mutation {
+    Mutation {
+      node_path,
+      action
+    }
+  }
+}
@@ -22,6 +22,7 @@ export enum NodeKind {
 
 export type BaseNode<TKind extends NodeKind> = {
   kind: TKind;
+  location: SourceLocation;
 };
 
 export type Text = {
@@ -50,6 +51,7 @@ export enum AttributeKind {
 
 type BaseAttribute<TKind extends AttributeKind> = {
   kind: TKind;
+  location: SourceLocation;
 };
 
 type ShorthandAttribute = {
@@ -72,6 +74,7 @@ export type Attribute =
 
 export enum AttributeValueKind {
   String = "String",
+  DynamicString = "DynamicString",
   Slot = "Slot"
 }
 
@@ -87,7 +90,27 @@ export type StringAttributeValue = {
 export type SlotAttributeValue = Statement &
   BaseAttributeValue<AttributeValueKind.Slot>;
 
-export type AttributeValue = StringAttributeValue | SlotAttributeValue;
+export enum DynamicStringAttributeValuePartKind {
+  Literal = "Literal",
+  Slot = "Slot"
+}
+
+export type DynamicStringAttributeValuePart =
+  | ({
+      value: string;
+      location: SourceLocation;
+    } & { partKind: DynamicStringAttributeValuePartKind.Literal })
+  | (Statement & { partKind: DynamicStringAttributeValuePartKind.Slot });
+
+export type DynamicStringAttributeValue = {
+  values: DynamicStringAttributeValuePart[];
+  location: SourceLocation;
+} & BaseAttributeValue<AttributeValueKind.DynamicString>;
+
+export type AttributeValue =
+  | StringAttributeValue
+  | DynamicStringAttributeValue
+  | SlotAttributeValue;
 
 export type Fragment = {
   value: string;
@@ -124,6 +147,7 @@ export type BaseConditional<
 > = {
   conditionalBlockKind: TconditionalBlockKind;
   body: Node;
+  location: SourceLocation;
 };
 
 export type PassFailConditional = {
@@ -1,4 +1,12 @@
+export type SourcePosition = {
+  line: number;
+  column: number;
+  utf16Column: number;
+};
+
 export type SourceLocation = {
   start: number;
   end: number;
+  startPosition: SourcePosition;
+  endPosition: SourcePosition;
 };
@@ -3,8 +3,17 @@ export type CompilerOptions = {
   [identifier: string]: String;
 };
 
+export type LintRuleSetting = "off" | "warning" | "error";
+
+export type LintOptions = {
+  rules?: {
+    [ruleId: string]: LintRuleSetting;
+  };
+};
+
 export type PaperclipConfig = {
   filesGlob: string;
   moduleDirectories: string[];
   compilerOptions: CompilerOptions;
+  lint?: LintOptions;
 };
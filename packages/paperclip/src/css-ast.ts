@@ -7,6 +7,7 @@ export type Sheet = {
 export enum RuleKind {
   Style = "Style",
   Charset = "Charset",
+  Import = "Import",
   Namespace = "Namespace",
   FontFace = "FontFace",
   Media = "Media",
@@ -27,6 +28,9 @@ export enum SelectorKind {
   PseudoElement = "PseudoElement",
   PseudoParamElement = "PseudoParamElement",
   Not = "Not",
+  Global = "Global",
+  This = "This",
+  Nesting = "Nesting",
   Child = "Child",
   Adjacent = "Adjacent",
   Sibling = "Sibling",
@@ -39,6 +43,7 @@ export enum SelectorKind {
 
 export type BaseSelector<TKind extends SelectorKind> = {
   kind: TKind;
+  location: SourceLocation;
 };
 
 type IdSelector = {
@@ -47,17 +52,105 @@ type IdSelector = {
 
 export type Selector = IdSelector;
 
-type StyleDeclaration = {
+export enum ValueKind {
+  Keyword = "Keyword",
+  Number = "Number",
+  Color = "Color",
+  String = "String",
+  Url = "Url",
+  Var = "Var",
+  Function = "Function",
+  Comma = "Comma",
+  Operator = "Operator"
+}
+
+type BaseValue<TKind extends ValueKind> = {
+  kind: TKind;
+  location: SourceLocation;
+};
+
+type KeywordValue = {
   name: string;
+} & BaseValue<ValueKind.Keyword>;
+
+type NumberValue = {
   value: string;
+  unit: string;
+} & BaseValue<ValueKind.Number>;
+
+type ColorValue = {
+  hex: string;
+} & BaseValue<ValueKind.Color>;
+
+type StringValue = {
+  value: string;
+} & BaseValue<ValueKind.String>;
+
+type UrlValue = {
+  url: string;
+} & BaseValue<ValueKind.Url>;
+
+type VarValue = {
+  name: string;
+  fallback: Value[] | null;
+} & BaseValue<ValueKind.Var>;
+
+type FunctionValue = {
+  name: string;
+  arguments: Value[];
+} & BaseValue<ValueKind.Function>;
+
+type CommaValue = BaseValue<ValueKind.Comma>;
+
+type OperatorValue = {
+  value: string;
+} & BaseValue<ValueKind.Operator>;
+
+export type Value =
+  | KeywordValue
+  | NumberValue
+  | ColorValue
+  | StringValue
+  | UrlValue
+  | VarValue
+  | FunctionValue
+  | CommaValue
+  | OperatorValue;
+
+export type DeclarationValue = {
+  values: Value[];
+  important: boolean;
+
+  // source text of the value
+  raw: string;
+};
+
+type StyleDeclaration = {
+  name: string;
+  value: DeclarationValue;
   location: SourceLocation;
   nameLocation: SourceLocation;
   valueLocation: SourceLocation;
 };
 
-type StyleRule = {
+export type StyleRule = {
   selector: Selector;
   declarations: StyleDeclaration[];
+  children: Rule[];
+  location: SourceLocation;
 } & BaseRule<RuleKind.Style>;
 
-export type Rule = StyleRule;
+type ImportRule = {
+  uri: string;
+  location: SourceLocation;
+} & BaseRule<RuleKind.Import>;
+
+type ConditionRule = {
+  name: string;
+  condition_text: string;
+  rules: Rule[];
+} & BaseRule<
+  RuleKind.Media | RuleKind.Supports | RuleKind.Page | RuleKind.Document
+>;
+
+export type Rule = StyleRule | ImportRule | ConditionRule;
@@ -4,7 +4,8 @@ import * as fs from "fs";
 import * as path from "path";
 import { NativeEngine } from "../native/pkg/paperclip";
 import { PC_CONFIG_FILE_NAME } from "./constants";
-import { PaperclipConfig } from "./config";
+import { PaperclipConfig, LintOptions } from "./config";
+import { fromNativeShapeInference } from "./infer";
 
 export type FileContent = {
   [identifier: string]: string;
@@ -28,6 +29,16 @@ const mapResult = result => {
 
 export type EngineEventListener = (event: EngineEvent) => void;
 
+export type PreviewInfo = {
+  name?: string;
+  width?: string;
+  height?: string;
+};
+
+export type HtmlOptions = {
+  pretty?: boolean;
+};
+
 export class Engine {
   private _native: any;
   private _listeners: EngineEventListener[] = [];
@@ -71,6 +82,38 @@ export class Engine {
   parseContent(content: string) {
     return mapResult(this._native.parse_content(content));
   }
+  // options default to the "lint" section of the nearest pcconfig.json
+  lintFile(uri: string, options?: LintOptions) {
+    return mapResult(this._native.lint_file(uri, options));
+  }
+  inferFileParts(uri: string) {
+    const result = mapResult(this._native.infer_file_parts(uri));
+    if (!Array.isArray(result)) {
+      return result;
+    }
+    return result.map(({ id, props }) => ({
+      id,
+      props: fromNativeShapeInference(props)
+    }));
+  }
+  generateFileDefinition(uri: string) {
+    return mapResult(this._native.generate_file_definition(uri));
+  }
+  evaluatePart(uri: string, part: string, props: Record<string, any> = {}) {
+    return mapResult(this._native.evaluate_part(uri, part, props));
+  }
+  getFilePreviews(uri: string): PreviewInfo[] {
+    return mapResult(this._native.get_file_previews(uri));
+  }
+  evaluatePreview(uri: string, name: string) {
+    return mapResult(this._native.evaluate_preview(uri, name));
+  }
+  renderFileHtml(uri: string, options: HtmlOptions = {}) {
+    return mapResult(this._native.render_file_html(uri, options));
+  }
+  registerHelper(name: string, helper: (...args: any[]) => any) {
+    this._native.register_helper(name, (args: any[]) => helper(...args));
+  }
   updateVirtualFileContent(uri: string, content: string) {
     this._dispatch({ kind: EngineEventKind.Updating, uri });
     return mapResult(this._native.update_virtual_file_content(uri, content));
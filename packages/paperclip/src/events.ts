@@ -1,4 +1,4 @@
-import { VirtualNode } from "./virt";
+import { VirtualNode, VirtualStyleSheet } from "./virt";
 import { Node } from "./ast";
 import { SourceLocation } from "./base-ast";
 
@@ -6,6 +6,7 @@ export enum EngineEventKind {
   Loading = "Loading",
   Updating = "Updating",
   Evaluated = "Evaluated",
+  Diffed = "Diffed",
   Error = "Error",
   NodeParsed = "NodeParsed"
 }
@@ -25,9 +26,85 @@ type BaseEngineEvent<KKind extends EngineEventKind> = {
 
 export type EvaluatedEvent = {
   uri: string;
+
+  // set for named previews
+  preview?: string;
   node?: VirtualNode;
 } & BaseEngineEvent<EngineEventKind.Evaluated>;
 
+export enum ActionKind {
+  InsertChild = "InsertChild",
+  DeleteChild = "DeleteChild",
+  MoveChild = "MoveChild",
+  SetAttribute = "SetAttribute",
+  RemoveAttribute = "RemoveAttribute",
+  SetText = "SetText",
+  ReplaceNode = "ReplaceNode",
+  ReplaceSheet = "ReplaceSheet"
+}
+
+type BaseAction<TKind extends ActionKind> = {
+  kind: TKind;
+};
+
+export type InsertChild = {
+  child: VirtualNode;
+  index: number;
+} & BaseAction<ActionKind.InsertChild>;
+
+export type DeleteChild = {
+  index: number;
+} & BaseAction<ActionKind.DeleteChild>;
+
+export type MoveChild = {
+  oldIndex: number;
+  newIndex: number;
+} & BaseAction<ActionKind.MoveChild>;
+
+export type SetAttribute = {
+  name: string;
+  value?: string;
+} & BaseAction<ActionKind.SetAttribute>;
+
+export type RemoveAttribute = {
+  name: string;
+} & BaseAction<ActionKind.RemoveAttribute>;
+
+export type SetText = {
+  value: string;
+} & BaseAction<ActionKind.SetText>;
+
+export type ReplaceNode = {
+  replacement: VirtualNode;
+} & BaseAction<ActionKind.ReplaceNode>;
+
+export type ReplaceSheet = {
+  sheet: VirtualStyleSheet;
+} & BaseAction<ActionKind.ReplaceSheet>;
+
+export type Action =
+  | InsertChild
+  | DeleteChild
+  | MoveChild
+  | SetAttribute
+  | RemoveAttribute
+  | SetText
+  | ReplaceNode
+  | ReplaceSheet;
+
+// Applied in order. `nodePath` is the list of child indices from the root (fragments
+// included) to the target, as the tree is after all prior mutations have been applied.
+export type Mutation = {
+  nodePath: number[];
+  action: Action;
+};
+
+export type DiffedEvent = {
+  uri: string;
+  preview?: string;
+  mutations: Mutation[];
+} & BaseEngineEvent<EngineEventKind.Diffed>;
+
 export type NodeParsedEvent = {
   uri: string;
   node?: Node;
@@ -82,6 +159,7 @@ export type UpdatingEvent = {
 export type EngineErrorEvent = GraphErrorEvent | RuntimeErrorEvent;
 export type EngineEvent =
   | EvaluatedEvent
+  | DiffedEvent
   | EngineErrorEvent
   | NodeParsedEvent
   | LoadingEvent
@@ -5,6 +5,7 @@ import {
   Element,
   AttributeKind,
   AttributeValueKind,
+  DynamicStringAttributeValuePartKind,
   BlockKind,
   ConditionalBlockKind,
   EachBlock,
@@ -31,7 +32,7 @@ import {
 } from "./js-ast";
 import { PREVIEW_TAG_NAME, PART_TAG_NAME } from "./constants";
 
-// TODO - this should be built in rust
+// Mirrors native/src/pc/infer.rs, which is exposed via Engine.inferFileParts
 
 export enum InferenceKind {
   Shape,
@@ -234,6 +235,39 @@ const setScope = (
   }
 });
 
+export type PartInference = {
+  id: string;
+  props: ShapeInference;
+};
+
+// native inferences are tagged with kind names, e.g: { kind: "Array", value: { kind: "Any" } }
+const fromNativeInference = (inference: any): Inference => {
+  switch (inference.kind) {
+    case "Shape":
+      return fromNativeShapeInference(inference);
+    case "Array":
+      return {
+        kind: InferenceKind.Array,
+        value: fromNativeInference(inference.value)
+      };
+    default:
+      return { kind: InferenceKind.Any };
+  }
+};
+
+// part props aren't tagged since they're always shapes
+export const fromNativeShapeInference = (inference: any): ShapeInference => {
+  const properties = {};
+  for (const name in inference.properties) {
+    properties[name] = fromNativeInference(inference.properties[name]);
+  }
+  return {
+    kind: InferenceKind.Shape,
+    fromSpread: inference.fromSpread,
+    properties
+  };
+};
+
 export const infer = (ast: Node): ShapeInference => {
   return inferNode(ast, true, {
     scope: {},
@@ -316,6 +350,15 @@ const inferAttribute = (attribute: Attribute, context: Context) => {
         attribute.value.attrValueKind === AttributeValueKind.Slot
       ) {
         context = inferStatement(attribute.value, context);
+      } else if (
+        attribute.value &&
+        attribute.value.attrValueKind === AttributeValueKind.DynamicString
+      ) {
+        for (const part of attribute.value.values) {
+          if (part.partKind === DynamicStringAttributeValuePartKind.Slot) {
+            context = inferStatement(part, context);
+          }
+        }
       }
       break;
     }
@@ -363,6 +406,61 @@ const inferStatement = (
       );
       break;
     }
+    case StatementKind.Node: {
+      context = inferNode(statement, false, context);
+      break;
+    }
+    case StatementKind.Array: {
+      context = inferStatements(statement.values, context);
+      break;
+    }
+    case StatementKind.Object: {
+      context = inferStatements(
+        statement.properties.map(property => property.value),
+        context
+      );
+      break;
+    }
+    case StatementKind.Member: {
+      context = inferStatement(statement.object, context);
+      if (statement.computed) {
+        context = inferStatement(statement.property, context);
+      }
+      break;
+    }
+    case StatementKind.Call: {
+      context = inferStatements(statement.arguments, context);
+      break;
+    }
+    case StatementKind.Template: {
+      context = inferStatements(statement.parts, context);
+      break;
+    }
+    case StatementKind.Group: {
+      context = inferStatement(statement.expression, context, defaultInference);
+      break;
+    }
+    case StatementKind.Unary: {
+      context = inferStatement(statement.expression, context);
+      break;
+    }
+    case StatementKind.Binary: {
+      context = inferStatement(statement.left, context);
+      context = inferStatement(statement.right, context);
+      break;
+    }
+    case StatementKind.Conditional: {
+      context = inferStatement(statement.test, context);
+      context = inferStatement(statement.consequent, context, defaultInference);
+      context = inferStatement(statement.alternate, context, defaultInference);
+      break;
+    }
   }
   return context;
 };
+
+const inferStatements = (statements: Statement[], context: Context) =>
+  statements.reduce(
+    (context, statement) => inferStatement(statement, context),
+    context
+  );
@@ -1,17 +1,26 @@
 import { Node } from "./ast";
+import { SourceLocation } from "./base-ast";
 
 export enum StatementKind {
   Node = "Node",
   Reference = "Reference",
+  Member = "Member",
+  Call = "Call",
   Array = "Array",
   Object = "Object",
   String = "String",
+  Template = "Template",
   Number = "Number",
-  Boolean = "Boolean"
+  Boolean = "Boolean",
+  Group = "Group",
+  Unary = "Unary",
+  Binary = "Binary",
+  Conditional = "Conditional"
 }
 
 type BaseStatement<TKind extends StatementKind> = {
   jsKind: TKind;
+  location: SourceLocation;
 };
 
 export type JsNode = Node & BaseStatement<StatementKind.Node>;
@@ -32,6 +41,10 @@ export type JsString = {
   value: string;
 } & BaseStatement<StatementKind.String>;
 
+export type JsTemplateLiteral = {
+  parts: Statement[];
+} & BaseStatement<StatementKind.Template>;
+
 export type JsBoolean = {
   value: boolean;
 } & BaseStatement<StatementKind.Boolean>;
@@ -44,11 +57,69 @@ export type Reference = {
   path: string[];
 } & BaseStatement<StatementKind.Reference>;
 
+export type JsMemberExpression = {
+  object: Statement;
+  property: Statement;
+  computed: boolean;
+} & BaseStatement<StatementKind.Member>;
+
+export type JsCallExpression = {
+  callee: string;
+  arguments: Statement[];
+} & BaseStatement<StatementKind.Call>;
+
+export type JsGroup = {
+  expression: Statement;
+} & BaseStatement<StatementKind.Group>;
+
+export type UnaryOperator = "!" | "-";
+
+export type JsUnaryExpression = {
+  operator: UnaryOperator;
+  expression: Statement;
+} & BaseStatement<StatementKind.Unary>;
+
+export type BinaryOperator =
+  | "||"
+  | "&&"
+  | "=="
+  | "!="
+  | "==="
+  | "!=="
+  | "<"
+  | "<="
+  | ">"
+  | ">="
+  | "+"
+  | "-"
+  | "*"
+  | "/"
+  | "%";
+
+export type JsBinaryExpression = {
+  left: Statement;
+  operator: BinaryOperator;
+  right: Statement;
+} & BaseStatement<StatementKind.Binary>;
+
+export type JsConditionalExpression = {
+  test: Statement;
+  consequent: Statement;
+  alternate: Statement;
+} & BaseStatement<StatementKind.Conditional>;
+
 export type Statement =
   | Reference
+  | JsMemberExpression
+  | JsCallExpression
   | JsNode
   | JsObject
   | JsArray
   | JsNumber
   | JsString
-  | JsBoolean;
+  | JsTemplateLiteral
+  | JsBoolean
+  | JsGroup
+  | JsUnaryExpression
+  | JsBinaryExpression
+  | JsConditionalExpression;
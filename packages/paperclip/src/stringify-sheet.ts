@@ -1,3 +1,5 @@
+import { Value, ValueKind } from "./css-ast";
+
 export const stringifyCSSSheet = (sheet, protocol: string | null) => {
   return sheet.rules.map(rule => stringifyCSSRule(rule, protocol)).join("\n");
 };
@@ -20,7 +22,7 @@ const stringifyConditionRule = (
   protocol?: string
 ) => {
   return `@${name} ${condition_text} {
-    ${rules.map(style => stringifyStyleRule(style, protocol)).join("\n")}
+    ${rules.map(rule => stringifyCSSRule(rule, protocol)).join("\n")}
   }`;
 };
 
@@ -38,3 +40,37 @@ const stringifyStyleRule = ({ selector_text, style }, protocol: string) => {
 
 const stringifyStyle = ({ name, value }, protocol) =>
   `${name}:${value && protocol ? value.replace(/file:/, protocol) : value};`;
+
+export const stringifyCSSValue = (value: Value): string => {
+  switch (value.kind) {
+    case ValueKind.Keyword:
+      return value.name;
+    case ValueKind.Number:
+      return value.value + value.unit;
+    case ValueKind.Color:
+      return "#" + value.hex;
+    case ValueKind.String:
+      return JSON.stringify(value.value);
+    case ValueKind.Url:
+      return `url(${JSON.stringify(value.url)})`;
+    case ValueKind.Var:
+      return value.fallback
+        ? `var(${value.name}, ${stringifyCSSValues(value.fallback)})`
+        : `var(${value.name})`;
+    case ValueKind.Function:
+      return `${value.name}(${stringifyCSSValues(value.arguments)})`;
+    case ValueKind.Comma:
+      return ",";
+    case ValueKind.Operator:
+      return value.value;
+  }
+};
+
+const stringifyCSSValues = (values: Value[]) =>
+  values
+    .map((value, i) =>
+      i > 0 && value.kind !== ValueKind.Comma
+        ? " " + stringifyCSSValue(value)
+        : stringifyCSSValue(value)
+    )
+    .join("");
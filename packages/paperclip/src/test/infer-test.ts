@@ -228,6 +228,48 @@ describe(__filename + "#", () => {
         }
       }
     ],
+    [
+      `<button disabled={!a} title="{b.c} {d}">{e ? f : g + h}{i[j]}</button>`,
+      {
+        kind: 0,
+        fromSpread: false,
+        properties: {
+          a: {
+            kind: 2
+          },
+          b: {
+            kind: 0,
+            fromSpread: false,
+            properties: {
+              c: {
+                kind: 2
+              }
+            }
+          },
+          d: {
+            kind: 2
+          },
+          e: {
+            kind: 2
+          },
+          f: {
+            kind: 2
+          },
+          g: {
+            kind: 2
+          },
+          h: {
+            kind: 2
+          },
+          i: {
+            kind: 2
+          },
+          j: {
+            kind: 2
+          }
+        }
+      }
+    ],
     [
       `<preview>{a}</preview>`,
       {
@@ -1,7 +1,10 @@
+import { SourceLocation } from "./base-ast";
+
 export enum VirtualNodeKind {
   Element = "Element",
   Text = "Text",
-  Fragment = "Fragment"
+  Fragment = "Fragment",
+  StyleElement = "StyleElement"
 }
 
 type VirtualBaseNode<KKind extends VirtualNodeKind> = {
@@ -9,16 +12,41 @@ type VirtualBaseNode<KKind extends VirtualNodeKind> = {
 };
 
 export type VirtualAttribute = {
+  id: string;
   name: string;
-  value: string;
+  value?: string;
 };
+
 export type VirtualElement = {
+  id: string;
+  sourceUri: string;
+  sourceLocation: SourceLocation;
+  tagName: string;
   attributes: VirtualAttribute[];
-  children: VirtualNode;
+  children: VirtualNode[];
 } & VirtualBaseNode<VirtualNodeKind.Element>;
 
 export type VirtualText = {
+  id: string;
   value: string;
-} & VirtualBaseNode<VirtualNodeKind.Element>;
+} & VirtualBaseNode<VirtualNodeKind.Text>;
+
+export type VirtualFragment = {
+  children: VirtualNode[];
+} & VirtualBaseNode<VirtualNodeKind.Fragment>;
+
+// evaluated CSS, see stringify-sheet.ts
+export type VirtualStyleSheet = {
+  rules: any[];
+};
+
+export type VirtualStyleElement = {
+  id: string;
+  sheet: VirtualStyleSheet;
+} & VirtualBaseNode<VirtualNodeKind.StyleElement>;
 
-export type VirtualNode = VirtualElement | VirtualText;
+export type VirtualNode =
+  | VirtualElement
+  | VirtualText
+  | VirtualFragment
+  | VirtualStyleElement;